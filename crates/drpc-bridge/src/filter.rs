@@ -0,0 +1,114 @@
+use crate::protocol::{ServerFrame, ServerMessage};
+use drpc_core::ActivityOrigin;
+pub use drpc_core::glob_match;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+// Each list is an any-of match; an empty list lets everything through
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BridgeFilter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub client_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub origins: Vec<ActivityOrigin>,
+    // Case-insensitive globs on the activity name; `*` and `?` are wildcards
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+}
+
+impl BridgeFilter {
+    // Comma separated lists from the connect URL, e.g. `?origin=ipc,ws&name=*craft*`
+    pub fn from_query(
+        client_id: Option<&str>,
+        origin: Option<&str>,
+        name: Option<&str>,
+    ) -> Result<Self, String> {
+        let list = |v: Option<&str>| -> Vec<String> {
+            v.into_iter()
+                .flat_map(|s| s.split(','))
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        Ok(Self {
+            client_ids: list(client_id),
+            origins: list(origin)
+                .iter()
+                .map(|o| o.parse())
+                .collect::<Result<_, _>>()?,
+            names: list(name),
+        })
+    }
+
+    // The client id is the activity's application_id, which RPC transports set from it
+    pub fn matches(&self, origin: ActivityOrigin, activity: &Value) -> bool {
+        let client_ok = self.client_ids.is_empty()
+            || activity
+                .get("application_id")
+                .and_then(Value::as_str)
+                .is_some_and(|id| self.client_ids.iter().any(|c| c == id));
+        let name = activity.get("name").and_then(Value::as_str).unwrap_or("");
+        client_ok
+            && (self.origins.is_empty() || self.origins.contains(&origin))
+            && (self.names.is_empty() || self.names.iter().any(|p| glob_match(p, name)))
+    }
+}
+
+// Per-connection view: filters frames and remembers which sockets the client has seen, so an
+// activity that stops matching is cleared for it rather than left stale
+#[derive(Debug, Default)]
+pub(crate) struct FilteredView {
+    pub filter: BridgeFilter,
+    visible: HashSet<String>,
+}
+
+impl FilteredView {
+    pub fn new(filter: BridgeFilter) -> Self {
+        Self {
+            filter,
+            visible: HashSet::new(),
+        }
+    }
+
+    pub fn apply(&mut self, frame: &ServerFrame) -> Option<ServerFrame> {
+        match &frame.msg {
+            ServerMessage::Snapshot { activities } => {
+                let activities: Vec<_> = activities
+                    .iter()
+                    .filter(|a| self.filter.matches(a.origin, &a.activity))
+                    .cloned()
+                    .collect();
+                self.visible = activities.iter().map(|a| a.socket_id.clone()).collect();
+                Some(ServerFrame::new(
+                    frame.seq,
+                    ServerMessage::Snapshot { activities },
+                ))
+            }
+            ServerMessage::ActivityUpdate {
+                socket_id,
+                origin,
+                activity,
+            } => {
+                if self.filter.matches(*origin, activity) {
+                    self.visible.insert(socket_id.clone());
+                    Some(frame.clone())
+                } else if self.visible.remove(socket_id) {
+                    Some(ServerFrame::new(
+                        frame.seq,
+                        ServerMessage::ActivityClear {
+                            socket_id: socket_id.clone(),
+                        },
+                    ))
+                } else {
+                    None
+                }
+            }
+            ServerMessage::ActivityClear { socket_id } => {
+                self.visible.remove(socket_id).then(|| frame.clone())
+            }
+            _ => Some(frame.clone()),
+        }
+    }
+}
@@ -1,36 +1,63 @@
 use axum::{
     Router,
+    extract::Query,
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    response::IntoResponse,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
     routing::get,
 };
-use drpc_core::{EventBus, EventKind};
+use drpc_core::{ActivityOrigin, AssetResolver, AuthConfig, EventBus, EventKind, is_local_host};
 use futures::StreamExt;
 use parking_lot::RwLock;
+use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::info;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tracing::{debug, info};
+
+pub mod filter;
+pub mod protocol;
+
+pub use filter::*;
+pub use protocol::*;
+
+// `Default` listens on 1337 with an in-memory asset resolver and no authentication
+#[derive(Clone, Default)]
+pub struct BridgeOptions {
+    // Defaults to 1337; Some(0) picks a free port
+    pub port: Option<u16>,
+    pub assets: Arc<AssetResolver>,
+    pub auth: AuthConfig,
+}
 
 pub struct Bridge {
     port: u16,
 }
 
 impl Bridge {
-    pub async fn run(bus: EventBus, port: Option<u16>) -> anyhow::Result<Self> {
+    pub async fn run(bus: EventBus, options: BridgeOptions) -> anyhow::Result<Self> {
+        let BridgeOptions { port, assets, auth } = options;
         let requested = port.unwrap_or(1337);
         let state = Arc::new(BridgeState {
-            activities: RwLock::new(HashMap::new()),
+            data: RwLock::new(BridgeData::default()),
             bus: bus.clone(),
             clients: RwLock::new(Vec::new()),
+            assets,
+            auth,
         });
-        // subscriber task
+        // Subscribed before returning so events published right after startup aren't missed
         let sub_state = state.clone();
+        let rx = bus.subscribe();
         tokio::spawn(async move {
-            bridge_subscriber(bus, sub_state).await;
+            bridge_subscriber(rx, sub_state).await;
         });
         let app = Router::new().route(
             "/",
-            get(move |ws: WebSocketUpgrade| bridge_handler(ws, state.clone())),
+            get(
+                move |ws: WebSocketUpgrade, headers: HeaderMap, Query(q): Query<BridgeQuery>| {
+                    bridge_handler(ws, headers, q, state.clone())
+                },
+            ),
         );
         let listener = tokio::net::TcpListener::bind(("127.0.0.1", requested)).await?;
         let actual_port = listener.local_addr()?.port();
@@ -49,87 +76,333 @@ impl Bridge {
     }
 }
 
+#[derive(Default)]
+struct BridgeData {
+    activities: HashMap<String, (ActivityOrigin, serde_json::Value)>, // socket_id -> activity json
+    seq: u64,
+}
+
 struct BridgeState {
-    activities: RwLock<HashMap<String, serde_json::Value>>, // socket_id -> activity json                                                        //
+    // Activities and the sequence number move together so snapshots line up with frames
+    data: RwLock<BridgeData>,
     #[allow(dead_code)] // for now
     bus: EventBus,
-    clients: RwLock<Vec<tokio::sync::mpsc::UnboundedSender<String>>>,
+    clients: RwLock<Vec<UnboundedSender<Arc<ServerFrame>>>>,
+    assets: Arc<AssetResolver>,
+    auth: AuthConfig,
 }
 
-async fn bridge_handler(ws: WebSocketUpgrade, state: Arc<BridgeState>) -> impl IntoResponse {
-    ws.on_upgrade(|socket| async move { handle_socket(socket, state).await })
+impl BridgeState {
+    fn snapshot(&self) -> ServerFrame {
+        let data = self.data.read();
+        let mut activities: Vec<_> = data
+            .activities
+            .iter()
+            .map(|(k, (origin, v))| SocketActivity {
+                socket_id: k.clone(),
+                origin: *origin,
+                activity: v.clone(),
+            })
+            .collect();
+        activities.sort_by(|a, b| a.socket_id.cmp(&b.socket_id));
+        ServerFrame::new(data.seq, ServerMessage::Snapshot { activities })
+    }
+
+    fn seq(&self) -> u64 {
+        self.data.read().seq
+    }
+
+    // Applies a change and broadcasts the message it yields under the next sequence number.
+    // Sent while the lock is held, so clients receive frames in sequence order.
+    fn update(&self, change: impl FnOnce(&mut BridgeData) -> Option<ServerMessage>) {
+        let mut data = self.data.write();
+        let Some(msg) = change(&mut data) else {
+            return;
+        };
+        data.seq += 1;
+        broadcast(self, ServerFrame::new(data.seq, msg));
+    }
 }
 
-async fn handle_socket(mut socket: WebSocket, state: Arc<BridgeState>) {
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-    state.clients.write().push(tx);
-    let snapshot: Vec<_> = state
-        .activities
-        .read()
-        .iter()
-        .filter_map(|(k, v)| {
-            if v.is_null() {
-                None
-            } else {
-                Some((k.clone(), v.clone()))
+#[derive(Deserialize)]
+struct BridgeQuery {
+    v: Option<u32>,
+    client_id: Option<String>,
+    origin: Option<String>,
+    name: Option<String>,
+    // Browsers can't set headers on a WebSocket, so the token may come in the URL instead
+    token: Option<String>,
+}
+
+// Checked before upgrading, so rejected pages only see an HTTP error
+fn check_upgrade(
+    auth: &AuthConfig,
+    headers: &HeaderMap,
+    q: &BridgeQuery,
+) -> Result<(), StatusCode> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if !header(header::HOST).is_some_and(is_local_host)
+        || !auth.origin_allowed(header(header::ORIGIN))
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !auth.check_token(q.token.as_deref()) && !auth.check_bearer(header(header::AUTHORIZATION)) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
+async fn bridge_handler(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    q: BridgeQuery,
+    state: Arc<BridgeState>,
+) -> Response {
+    if let Err(status) = check_upgrade(&state.auth, &headers, &q) {
+        debug!(%status, origin = ?headers.get(header::ORIGIN), "bridge connection rejected");
+        return status.into_response();
+    }
+    let version = q.v.unwrap_or(LEGACY_VERSION);
+    let filter = BridgeFilter::from_query(
+        q.client_id.as_deref(),
+        q.origin.as_deref(),
+        q.name.as_deref(),
+    );
+    ws.on_upgrade(move |mut socket| async move {
+        let view = match filter {
+            Ok(f) => FilteredView::new(f),
+            Err(e) => {
+                let err = ServerFrame::error(state.seq(), BridgeErrorCode::InvalidFilter, e);
+                let _ = send_text(&mut socket, err.to_json()).await;
+                let _ = socket.send(Message::Close(None)).await;
+                return;
             }
-        })
-        .collect();
-    for (sid, act) in snapshot {
-        let msg = serde_json::json!({"socketId": sid, "activity": act});
-        if socket
-            .send(Message::Text(msg.to_string().into()))
-            .await
-            .is_err()
-        {
+        };
+        match version {
+            LEGACY_VERSION => handle_legacy(socket, state, view).await,
+            _ => handle_v2(socket, state, version, view).await,
+        }
+    })
+    .into_response()
+}
+
+async fn send_text(socket: &mut WebSocket, text: String) -> bool {
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
+
+async fn handle_legacy(mut socket: WebSocket, state: Arc<BridgeState>, mut view: FilteredView) {
+    let (tx, mut rx) = unbounded_channel::<Arc<ServerFrame>>();
+    state.clients.write().push(tx);
+    let Some(snapshot) = view.apply(&state.snapshot()) else {
+        return;
+    };
+    for msg in snapshot.to_legacy() {
+        if !send_text(&mut socket, msg).await {
             return;
         }
     }
     loop {
         tokio::select! {
             Some(Ok(msg)) = socket.next() => { if matches!(msg, Message::Close(_)) { break; } }
-            Some(out) = rx.recv() => { if socket.send(Message::Text(out.into())).await.is_err() { break; } }
+            Some(frame) = rx.recv() => {
+                if frame.seq <= snapshot.seq {
+                    continue;
+                }
+                let Some(frame) = view.apply(&frame) else {
+                    continue;
+                };
+                for msg in frame.to_legacy() {
+                    if !send_text(&mut socket, msg).await {
+                        return;
+                    }
+                }
+            }
             else => break,
         }
     }
 }
 
-async fn bridge_subscriber(bus: EventBus, state: Arc<BridgeState>) {
-    let mut rx = bus.subscribe();
-    while let Some(evt) = rx.recv().await {
-        match evt {
-            EventKind::ActivityUpdate { socket_id, payload } => {
-                state
-                    .activities
-                    .write()
-                    .insert(socket_id.clone(), payload.clone());
-                broadcast(
-                    &state,
-                    serde_json::json!({"socketId": socket_id, "activity": payload}),
-                )
-                .await;
+// Server hello, then the client's hello picks the version; state frames only follow a snapshot
+async fn handle_v2(
+    mut socket: WebSocket,
+    state: Arc<BridgeState>,
+    requested: u32,
+    mut view: FilteredView,
+) {
+    let hello = ServerFrame::new(state.seq(), ServerMessage::Hello { v: BRIDGE_VERSION });
+    if requested != BRIDGE_VERSION {
+        reject_version(&mut socket, &state, requested).await;
+        return;
+    }
+    let (tx, mut rx) = unbounded_channel::<Arc<ServerFrame>>();
+    state.clients.write().push(tx);
+    if !send_text(&mut socket, hello.to_json()).await {
+        return;
+    }
+    // Frames at or below this were covered by the last snapshot; None until the handshake
+    let mut synced: Option<u64> = None;
+    loop {
+        tokio::select! {
+            Some(Ok(msg)) = socket.next() => {
+                let text = match msg {
+                    Message::Text(t) => t,
+                    Message::Close(_) => break,
+                    _ => continue,
+                };
+                let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                    Ok(ClientMessage::Hello { v }) if v == BRIDGE_VERSION => Some(state.snapshot()),
+                    Ok(ClientMessage::Hello { v }) => {
+                        reject_version(&mut socket, &state, v).await;
+                        return;
+                    }
+                    Ok(ClientMessage::Snapshot) if synced.is_some() => Some(state.snapshot()),
+                    Ok(ClientMessage::Subscribe(filter)) if synced.is_some() => {
+                        view = FilteredView::new(filter);
+                        Some(state.snapshot())
+                    }
+                    Ok(ClientMessage::Snapshot | ClientMessage::Subscribe(_)) => Some(ServerFrame::error(
+                        state.seq(),
+                        BridgeErrorCode::HandshakeRequired,
+                        "send hello first",
+                    )),
+                    Err(e) => {
+                        debug!(error=%e, "invalid bridge message");
+                        Some(ServerFrame::error(
+                            state.seq(),
+                            BridgeErrorCode::InvalidMessage,
+                            e.to_string(),
+                        ))
+                    }
+                };
+                if let Some(frame) = reply.and_then(|f| view.apply(&f)) {
+                    if matches!(frame.msg, ServerMessage::Snapshot { .. }) {
+                        synced = Some(frame.seq);
+                    }
+                    if !send_text(&mut socket, frame.to_json()).await {
+                        break;
+                    }
+                }
             }
-            EventKind::Clear { socket_id } => {
-                state
-                    .activities
-                    .write()
-                    .insert(socket_id.clone(), serde_json::Value::Null);
-                broadcast(
-                    &state,
-                    serde_json::json!({"socketId": socket_id, "activity": serde_json::Value::Null}),
-                )
-                .await;
+            Some(frame) = rx.recv() => {
+                if synced.is_none_or(|seq| frame.seq <= seq) {
+                    continue;
+                }
+                let Some(frame) = view.apply(&frame) else {
+                    continue;
+                };
+                if !send_text(&mut socket, frame.to_json()).await {
+                    break;
+                }
             }
-            EventKind::PrivacyRefresh => {
-                // No-op for now (bridge does not filter historical data)
+            else => break,
+        }
+    }
+}
+
+async fn reject_version(socket: &mut WebSocket, state: &BridgeState, v: u32) {
+    let err = ServerFrame::error(
+        state.seq(),
+        BridgeErrorCode::UnsupportedVersion,
+        format!("unsupported bridge version {v}; supported: {LEGACY_VERSION}, {BRIDGE_VERSION}"),
+    );
+    let _ = send_text(socket, err.to_json()).await;
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+async fn bridge_subscriber(
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<EventKind>,
+    state: Arc<BridgeState>,
+) {
+    while let Some(evt) = rx.recv().await {
+        match evt {
+            EventKind::ActivityUpdate {
+                socket_id,
+                origin,
+                payload,
+            } => {
+                // Sent right away with what is known. Missing asset lists and images are
+                // fetched in the background and the activity re-sent once they are in.
+                let mut annotated = payload.clone();
+                let missing = state.assets.annotate(&mut annotated);
+                let downloads = state.assets.localize_cached(&mut annotated);
+                state.update(|data| {
+                    // A null activity is still relayed, but snapshots only list current ones
+                    if annotated.is_null() {
+                        data.activities.remove(&socket_id);
+                    } else {
+                        data.activities
+                            .insert(socket_id.clone(), (origin, annotated.clone()));
+                    }
+                    Some(ServerMessage::ActivityUpdate {
+                        socket_id: socket_id.clone(),
+                        origin,
+                        activity: annotated.clone(),
+                    })
+                });
+                if missing.is_some() || downloads {
+                    tokio::spawn(refine(
+                        state.clone(),
+                        socket_id,
+                        origin,
+                        payload,
+                        annotated,
+                        missing,
+                    ));
+                }
             }
+            EventKind::Clear { socket_id } => state.update(|data| {
+                data.activities.remove(&socket_id);
+                Some(ServerMessage::ActivityClear { socket_id })
+            }),
+            EventKind::PrivacyRefresh => state.update(|_| Some(ServerMessage::PrivacyChanged)),
+            EventKind::DetectablesUpdated { .. }
+            | EventKind::ActivityJoin { .. }
+            | EventKind::ActivitySpectate { .. }
+            | EventKind::ActivityJoinRequest { .. } => {}
         }
     }
 }
 
-async fn broadcast(state: &Arc<BridgeState>, msg: serde_json::Value) {
-    let text = msg.to_string();
-    for sender in state.clients.read().iter() {
-        let _ = sender.send(text.clone());
+// Annotates `payload` again once `app`'s asset list is loaded and its images are downloaded;
+// only re-sent while `sent` is still the socket's current activity and the result differs
+async fn refine(
+    state: Arc<BridgeState>,
+    socket_id: String,
+    origin: ActivityOrigin,
+    mut payload: serde_json::Value,
+    sent: serde_json::Value,
+    app: Option<String>,
+) {
+    if let Some(app) = app {
+        state.assets.load(&app).await;
     }
+    state.assets.annotate(&mut payload);
+    state.assets.localize(&mut payload).await;
+    if payload == sent {
+        return;
+    }
+    state.update(|data| {
+        if data
+            .activities
+            .get(&socket_id)
+            .is_none_or(|(_, cur)| *cur != sent)
+        {
+            return None;
+        }
+        data.activities
+            .insert(socket_id.clone(), (origin, payload.clone()));
+        Some(ServerMessage::ActivityUpdate {
+            socket_id,
+            origin,
+            activity: payload,
+        })
+    });
+}
+
+fn broadcast(state: &BridgeState, frame: ServerFrame) {
+    let frame = Arc::new(frame);
+    state
+        .clients
+        .write()
+        .retain(|sender| sender.send(frame.clone()).is_ok());
 }
@@ -0,0 +1,109 @@
+use crate::filter::BridgeFilter;
+use drpc_core::ActivityOrigin;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Clients that don't ask for a version get the arRPC `{socketId, activity}` messages
+pub const LEGACY_VERSION: u32 = 1;
+pub const BRIDGE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocketActivity {
+    #[serde(rename = "socketId")]
+    pub socket_id: String,
+    pub origin: ActivityOrigin,
+    pub activity: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeErrorCode {
+    UnsupportedVersion,
+    HandshakeRequired,
+    InvalidMessage,
+    InvalidFilter,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Hello {
+        v: u32,
+    },
+    Snapshot {
+        activities: Vec<SocketActivity>,
+    },
+    ActivityUpdate {
+        #[serde(rename = "socketId")]
+        socket_id: String,
+        origin: ActivityOrigin,
+        activity: Value,
+    },
+    ActivityClear {
+        #[serde(rename = "socketId")]
+        socket_id: String,
+    },
+    PrivacyChanged,
+    Error {
+        code: BridgeErrorCode,
+        message: String,
+    },
+}
+
+// `seq` is the bridge-wide sequence number of the last state change the message reflects, so
+// a client that sees a gap can ask for a fresh snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerFrame {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub msg: ServerMessage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello { v: u32 },
+    Snapshot,
+    // Replaces the connection's filter; answered with a filtered snapshot
+    Subscribe(BridgeFilter),
+}
+
+impl ServerFrame {
+    pub fn new(seq: u64, msg: ServerMessage) -> Self {
+        Self { seq, msg }
+    }
+
+    pub fn error(seq: u64, code: BridgeErrorCode, message: impl Into<String>) -> Self {
+        Self::new(
+            seq,
+            ServerMessage::Error {
+                code,
+                message: message.into(),
+            },
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    // arRPC shape; messages it has no equivalent for are dropped
+    pub fn to_legacy(&self) -> Vec<String> {
+        let one = |socket_id: &str, activity: &Value| {
+            serde_json::json!({"socketId": socket_id, "activity": activity}).to_string()
+        };
+        match &self.msg {
+            ServerMessage::Snapshot { activities } => activities
+                .iter()
+                .map(|a| one(&a.socket_id, &a.activity))
+                .collect(),
+            ServerMessage::ActivityUpdate {
+                socket_id,
+                activity,
+                ..
+            } => vec![one(socket_id, activity)],
+            ServerMessage::ActivityClear { socket_id } => vec![one(socket_id, &Value::Null)],
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use drpc_core::{ActivityOrigin, AppAsset, AssetResolver, EventBus, EventKind};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_json<S>(ws: &mut S) -> Value
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("timed out")
+            .expect("frame")
+            .expect("ok");
+        match msg {
+            Message::Text(t) => return serde_json::from_str(&t).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn activity_is_sent_at_once_and_resent_when_its_asset_list_loads() {
+    let dir = std::env::temp_dir().join(format!(
+        "drpc-bridge-assets-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let list = vec![AppAsset {
+        id: "900".into(),
+        name: "cover".into(),
+        kind: 1,
+    }];
+    std::fs::write(dir.join("42.json"), serde_json::to_vec(&list).unwrap()).unwrap();
+    let bus = EventBus::new();
+    let assets = Arc::new(AssetResolver::default().with_cache_dir(&dir));
+    let bridge = drpc_bridge::Bridge::run(
+        bus.clone(),
+        drpc_bridge::BridgeOptions {
+            port: Some(0),
+            assets,
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("bridge");
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{}/?v=2", bridge.port()))
+        .await
+        .unwrap();
+    let _ = next_json(&mut ws).await; // hello
+    ws.send(Message::Text(r#"{"type":"hello","v":2}"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(next_json(&mut ws).await["type"], "snapshot");
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "s1".into(),
+        origin: ActivityOrigin::Ipc,
+        payload: json!({"name": "G", "application_id": "42", "assets": {"large_image": "cover"}}),
+    });
+    let first = next_json(&mut ws).await;
+    assert_eq!(first["type"], "activity_update");
+    assert_eq!(first["seq"], 1);
+    assert!(first["activity"]["assets"].get("large_image_url").is_none());
+    let second = next_json(&mut ws).await;
+    assert_eq!(second["type"], "activity_update");
+    assert_eq!(second["seq"], 2);
+    assert_eq!(
+        second["activity"]["assets"]["large_image_url"],
+        "https://cdn.discordapp.com/app-assets/42/900.png"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
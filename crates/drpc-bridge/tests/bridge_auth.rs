@@ -0,0 +1,63 @@
+use drpc_bridge::BridgeOptions;
+use drpc_core::{AuthConfig, EventBus};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+async fn start() -> u16 {
+    let bridge = drpc_bridge::Bridge::run(
+        EventBus::new(),
+        BridgeOptions {
+            port: Some(0),
+            auth: AuthConfig::default().with_token("t0ken"),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("bridge");
+    bridge.port()
+}
+
+// `Ok` for an accepted upgrade, else the HTTP status it was refused with
+async fn connect(url: &str, headers: &[(&'static str, &str)]) -> Result<(), StatusCode> {
+    let mut req = url.into_client_request().unwrap();
+    for (name, value) in headers {
+        req.headers_mut()
+            .insert(*name, value.parse().expect("header value"));
+    }
+    match connect_async(req).await {
+        Ok(_) => Ok(()),
+        Err(tokio_tungstenite::tungstenite::Error::Http(resp)) => Err(resp.status()),
+        Err(e) => panic!("unexpected error {e:?}"),
+    }
+}
+
+#[tokio::test]
+async fn upgrades_need_token_local_host_and_allowed_origin() {
+    let port = start().await;
+    let base = format!("ws://127.0.0.1:{port}/");
+    let with_token = format!("{base}?v=2&token=t0ken");
+
+    assert_eq!(connect(&base, &[]).await, Err(StatusCode::UNAUTHORIZED));
+    assert_eq!(
+        connect(&format!("{base}?token=nope"), &[]).await,
+        Err(StatusCode::UNAUTHORIZED)
+    );
+    assert_eq!(connect(&with_token, &[]).await, Ok(()));
+    assert_eq!(
+        connect(&base, &[("authorization", "Bearer t0ken")]).await,
+        Ok(())
+    );
+    assert_eq!(
+        connect(&with_token, &[("origin", "https://discord.com")]).await,
+        Ok(())
+    );
+    assert_eq!(
+        connect(&with_token, &[("origin", "https://evil.example")]).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    assert_eq!(
+        connect(&with_token, &[("host", "evil.example:1337")]).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+}
@@ -0,0 +1,165 @@
+use drpc_bridge::{BridgeFilter, glob_match};
+use drpc_core::{ActivityOrigin, EventBus, EventKind};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_json<S>(ws: &mut S) -> Value
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("timed out")
+            .expect("frame")
+            .expect("ok");
+        match msg {
+            Message::Text(t) => return serde_json::from_str(&t).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+}
+
+fn update(socket_id: &str, origin: ActivityOrigin, name: &str, app: &str) -> EventKind {
+    EventKind::ActivityUpdate {
+        socket_id: socket_id.into(),
+        origin,
+        payload: json!({"name": name, "application_id": app}),
+    }
+}
+
+async fn start_with_activities() -> (EventBus, u16) {
+    let bus = EventBus::new();
+    let port = drpc_bridge::Bridge::run(
+        bus.clone(),
+        drpc_bridge::BridgeOptions {
+            port: Some(0),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("bridge")
+    .port();
+    bus.publish(update("ipc-1", ActivityOrigin::Ipc, "Minecraft", "100"));
+    bus.publish(update("ws-1", ActivityOrigin::Ws, "Spotify", "200"));
+    bus.publish(update(
+        "proc-1",
+        ActivityOrigin::Process,
+        "Minecraft Launcher",
+        "300",
+    ));
+    // Wait until all three are applied
+    for _ in 0..50 {
+        let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+            .await
+            .unwrap();
+        if next_json(&mut ws).await["seq"].as_u64() >= Some(3) {
+            return (bus, port);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("bridge never applied the activities");
+}
+
+fn socket_ids(snapshot: &Value) -> Vec<&str> {
+    snapshot["activities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["socketId"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn glob_patterns_are_case_insensitive() {
+    assert!(glob_match("*craft*", "Minecraft Launcher"));
+    assert!(glob_match("minecraft", "MineCraft"));
+    assert!(glob_match("sp?tify", "Spotify"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("minecraft", "Minecraft Launcher"));
+    assert!(!glob_match("*craft", "Minecraft Launcher"));
+}
+
+#[test]
+fn query_filters_parse_comma_lists() {
+    let f = BridgeFilter::from_query(Some("1, 2"), Some("ipc,WS"), Some("*game*")).unwrap();
+    assert_eq!(f.client_ids, ["1", "2"]);
+    assert_eq!(f.origins, [ActivityOrigin::Ipc, ActivityOrigin::Ws]);
+    assert_eq!(f.names, ["*game*"]);
+    assert!(BridgeFilter::from_query(None, Some("carrier-pigeon"), None).is_err());
+    assert_eq!(
+        BridgeFilter::from_query(None, None, None).unwrap(),
+        BridgeFilter::default()
+    );
+}
+
+#[tokio::test]
+async fn legacy_query_filter_applies_to_replay_and_live_updates() {
+    let (bus, port) = start_with_activities().await;
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?name=minecraft"))
+        .await
+        .unwrap();
+    let first = next_json(&mut ws).await;
+    assert_eq!(first["socketId"], "ipc-1");
+
+    bus.publish(update("ws-2", ActivityOrigin::Ws, "Other", "200"));
+    // The game switching away from the filter clears it for this client
+    bus.publish(update("ipc-1", ActivityOrigin::Ipc, "Terraria", "100"));
+    assert_eq!(
+        next_json(&mut ws).await,
+        json!({"socketId": "ipc-1", "activity": null})
+    );
+}
+
+#[tokio::test]
+async fn v2_subscribe_replaces_the_filter() {
+    let (bus, port) = start_with_activities().await;
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2&origin=ipc,process"))
+        .await
+        .unwrap();
+    let _ = next_json(&mut ws).await;
+    ws.send(Message::Text(r#"{"type":"hello","v":2}"#.into()))
+        .await
+        .unwrap();
+    let snap = next_json(&mut ws).await;
+    assert_eq!(socket_ids(&snap), ["ipc-1", "proc-1"]);
+    assert_eq!(snap["activities"][1]["origin"], "process");
+
+    ws.send(Message::Text(
+        r#"{"type":"subscribe","client_ids":["200","300"],"names":["*i*"]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let snap = next_json(&mut ws).await;
+    assert_eq!(snap["type"], "snapshot");
+    assert_eq!(socket_ids(&snap), ["proc-1", "ws-1"]);
+
+    // Filtered out: no frame; the clear of a visible socket comes through
+    bus.publish(update("ipc-2", ActivityOrigin::Ipc, "Minecraft", "100"));
+    bus.publish(EventKind::Clear {
+        socket_id: "ws-1".into(),
+    });
+    let clear = next_json(&mut ws).await;
+    assert_eq!(clear["type"], "activity_clear");
+    assert_eq!(clear["socketId"], "ws-1");
+
+    ws.send(Message::Text(
+        r#"{"type":"subscribe","origins":["fax"]}"#.into(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(next_json(&mut ws).await["code"], "invalid_message");
+}
+
+#[tokio::test]
+async fn invalid_query_filter_is_rejected() {
+    let (_bus, port) = start_with_activities().await;
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?origin=fax"))
+        .await
+        .unwrap();
+    let err = next_json(&mut ws).await;
+    assert_eq!(err["code"], "invalid_filter");
+}
@@ -0,0 +1,241 @@
+use drpc_core::{ActivityOrigin, EventBus, EventKind};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_json<S>(ws: &mut S) -> Value
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    loop {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(5), ws.next())
+            .await
+            .expect("timed out")
+            .expect("frame")
+            .expect("ok");
+        match msg {
+            Message::Text(t) => return serde_json::from_str(&t).unwrap(),
+            Message::Ping(_) | Message::Pong(_) => continue,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+}
+
+async fn start() -> (EventBus, u16) {
+    let bus = EventBus::new();
+    let bridge = drpc_bridge::Bridge::run(
+        bus.clone(),
+        drpc_bridge::BridgeOptions {
+            port: Some(0),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("bridge");
+    (bus, bridge.port())
+}
+
+fn update(socket_id: &str, name: &str) -> EventKind {
+    EventKind::ActivityUpdate {
+        socket_id: socket_id.into(),
+        origin: ActivityOrigin::Ws,
+        payload: json!({"name": name}),
+    }
+}
+
+// The subscriber applies events asynchronously; wait until a v2 hello reports `seq`
+async fn settle(port: u16, seq: u64) {
+    for _ in 0..50 {
+        let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+            .await
+            .unwrap();
+        let hello = next_json(&mut ws).await;
+        if hello["seq"].as_u64() >= Some(seq) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("bridge never reached seq {seq}");
+}
+
+#[tokio::test]
+async fn v2_handshake_snapshot_and_sequenced_updates() {
+    let (bus, port) = start().await;
+    bus.publish(update("a", "First"));
+    settle(port, 1).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+        .await
+        .unwrap();
+    let hello = next_json(&mut ws).await;
+    assert_eq!(hello["type"], "hello");
+    assert_eq!(hello["v"], 2);
+
+    // Nothing but errors before the client says hello
+    ws.send(Message::Text(r#"{"type":"snapshot"}"#.into()))
+        .await
+        .unwrap();
+    let err = next_json(&mut ws).await;
+    assert_eq!(err["type"], "error");
+    assert_eq!(err["code"], "handshake_required");
+
+    ws.send(Message::Text(r#"{"type":"hello","v":2}"#.into()))
+        .await
+        .unwrap();
+    let snap = next_json(&mut ws).await;
+    assert_eq!(snap["type"], "snapshot");
+    assert_eq!(snap["seq"], 1);
+    assert_eq!(snap["activities"][0]["socketId"], "a");
+    assert_eq!(snap["activities"][0]["activity"]["name"], "First");
+
+    bus.publish(update("b", "Second"));
+    let upd = next_json(&mut ws).await;
+    assert_eq!(upd["type"], "activity_update");
+    assert_eq!(upd["seq"], 2);
+    assert_eq!(upd["socketId"], "b");
+
+    bus.publish(EventKind::Clear {
+        socket_id: "a".into(),
+    });
+    let clear = next_json(&mut ws).await;
+    assert_eq!(clear["type"], "activity_clear");
+    assert_eq!(clear["seq"], 3);
+    assert_eq!(clear["socketId"], "a");
+
+    bus.publish(EventKind::PrivacyRefresh);
+    let privacy = next_json(&mut ws).await;
+    assert_eq!(privacy, json!({"type": "privacy_changed", "seq": 4}));
+
+    ws.send(Message::Text("not json".into())).await.unwrap();
+    assert_eq!(next_json(&mut ws).await["code"], "invalid_message");
+
+    // Resync on request
+    ws.send(Message::Text(r#"{"type":"snapshot"}"#.into()))
+        .await
+        .unwrap();
+    let snap = next_json(&mut ws).await;
+    assert_eq!(snap["seq"], 4);
+    assert_eq!(
+        snap["activities"],
+        json!([{"socketId": "b", "origin": "ws", "activity": {"name": "Second"}}])
+    );
+}
+
+#[tokio::test]
+async fn unsupported_version_is_rejected() {
+    let (_bus, port) = start().await;
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=9"))
+        .await
+        .unwrap();
+    let err = next_json(&mut ws).await;
+    assert_eq!(err["type"], "error");
+    assert_eq!(err["code"], "unsupported_version");
+    assert!(matches!(
+        ws.next().await,
+        Some(Ok(Message::Close(_))) | None
+    ));
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+        .await
+        .unwrap();
+    let _ = next_json(&mut ws).await;
+    ws.send(Message::Text(r#"{"type":"hello","v":3}"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(next_json(&mut ws).await["code"], "unsupported_version");
+}
+
+#[tokio::test]
+async fn legacy_clients_get_arrpc_messages() {
+    let (bus, port) = start().await;
+    bus.publish(update("a", "Existing"));
+    settle(port, 1).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/"))
+        .await
+        .unwrap();
+    assert_eq!(
+        next_json(&mut ws).await,
+        json!({"socketId": "a", "activity": {"name": "Existing"}})
+    );
+    // Privacy changes have no legacy shape, so the clear is the next message
+    bus.publish(EventKind::PrivacyRefresh);
+    bus.publish(EventKind::Clear {
+        socket_id: "a".into(),
+    });
+    assert_eq!(
+        next_json(&mut ws).await,
+        json!({"socketId": "a", "activity": null})
+    );
+}
+
+#[tokio::test]
+async fn privacy_refresh_clears_denied_activities() {
+    let (bus, port) = start().await;
+    bus.publish(update("a", "Secret Game"));
+    bus.publish(update("b", "Open Game"));
+    settle(port, 2).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+        .await
+        .unwrap();
+    let _ = next_json(&mut ws).await;
+    ws.send(Message::Text(r#"{"type":"hello","v":2}"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        next_json(&mut ws).await["activities"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    bus.privacy()
+        .set(drpc_core::PrivacySettings {
+            deny: Some(vec!["secret".into()]),
+            ..Default::default()
+        })
+        .unwrap();
+    bus.publish(EventKind::PrivacyRefresh);
+    let clear = next_json(&mut ws).await;
+    assert_eq!(clear["type"], "activity_clear");
+    assert_eq!(clear["socketId"], "a");
+    assert_eq!(next_json(&mut ws).await["type"], "privacy_changed");
+
+    // Denied updates never reach the bridge
+    bus.publish(update("c", "Secret Again"));
+    bus.publish(update("d", "Visible"));
+    assert_eq!(next_json(&mut ws).await["socketId"], "d");
+}
+
+#[tokio::test]
+async fn snapshot_leaves_out_null_activities() {
+    let (bus, port) = start().await;
+    bus.publish(update("a", "First"));
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "a".into(),
+        origin: ActivityOrigin::Ws,
+        payload: Value::Null,
+    });
+    bus.publish(update("b", "Second"));
+    settle(port, 3).await;
+
+    let (mut ws, _) = connect_async(format!("ws://127.0.0.1:{port}/?v=2"))
+        .await
+        .unwrap();
+    let _ = next_json(&mut ws).await; // hello
+    ws.send(Message::Text(r#"{"type":"hello","v":2}"#.into()))
+        .await
+        .unwrap();
+    let snap = next_json(&mut ws).await;
+    assert_eq!(snap["type"], "snapshot");
+    let ids: Vec<&str> = snap["activities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|a| a["socketId"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["b"]);
+}
@@ -0,0 +1,214 @@
+use crate::protocol::{
+    Activity, ActivityAssets, ActivityButton, ActivityParty, ActivitySecrets, ActivityTimestamps,
+    ActivityType, StatusDisplayType,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// Anything below this is taken as Unix seconds: 1e11 s is year 5138, 1e11 ms is 1973
+pub const SECONDS_CUTOFF: u64 = 100_000_000_000;
+pub const FLAG_INSTANCE: u32 = 1;
+
+pub type NormalizeStep = fn(&mut Activity, u64);
+
+// Applied in order by `Activity::normalize_at`; each step is idempotent on its own
+pub const NORMALIZE_STEPS: &[(&str, NormalizeStep)] = &[
+    ("default_type", default_type),
+    ("created_at", created_at),
+    ("timestamps_to_ms", timestamps_to_ms),
+    ("instance_flag", instance_flag),
+];
+
+// Bridge clients render by type; an untyped presence is a game
+pub fn default_type(a: &mut Activity, _now_ms: u64) {
+    a.kind.get_or_insert(ActivityType::Playing);
+}
+
+pub fn created_at(a: &mut Activity, now_ms: u64) {
+    let created = a.created_at.map_or(now_ms, seconds_to_ms);
+    a.created_at = Some(created);
+}
+
+pub fn timestamps_to_ms(a: &mut Activity, _now_ms: u64) {
+    if let Some(ts) = a.timestamps.as_mut() {
+        ts.start = ts.start.map(seconds_to_ms);
+        ts.end = ts.end.map(seconds_to_ms);
+    }
+}
+
+pub fn instance_flag(a: &mut Activity, _now_ms: u64) {
+    if a.instance == Some(true) {
+        a.flags = Some(a.flags.unwrap_or(0) | FLAG_INSTANCE);
+    }
+}
+
+pub fn seconds_to_ms(v: u64) -> u64 {
+    if v < SECONDS_CUTOFF { v * 1000 } else { v }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ActivityMetadata {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub button_urls: Vec<String>,
+    #[serde(flatten, default)]
+    pub extra: Map<String, Value>,
+}
+
+// What goes out to RPC clients and the bridge: buttons are bare labels with the
+// URLs carried separately in metadata, the way Discord dispatches them
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct WireActivity {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ActivityType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_display_type: Option<StatusDisplayType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<ActivityTimestamps>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<ActivityAssets>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub party: Option<ActivityParty>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<ActivitySecrets>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buttons: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ActivityMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flags: Option<u32>,
+    #[serde(flatten, default)]
+    pub extra: Map<String, Value>,
+}
+
+impl From<Activity> for WireActivity {
+    fn from(mut a: Activity) -> Self {
+        let mut metadata = a
+            .extra
+            .remove("metadata")
+            .and_then(|m| serde_json::from_value::<ActivityMetadata>(m).ok());
+        let buttons = a.buttons.map(|btns| {
+            let meta = metadata.get_or_insert_with(Default::default);
+            meta.button_urls = btns.iter().map(|b| b.url.clone()).collect();
+            btns.into_iter().map(|b| b.label).collect()
+        });
+        WireActivity {
+            name: a.name,
+            kind: a.kind,
+            application_id: a.application_id,
+            status_display_type: a.status_display_type,
+            state: a.state,
+            state_url: a.state_url,
+            details: a.details,
+            details_url: a.details_url,
+            created_at: a.created_at,
+            timestamps: a.timestamps,
+            assets: a.assets,
+            party: a.party,
+            secrets: a.secrets,
+            buttons,
+            metadata,
+            instance: a.instance,
+            flags: a.flags,
+            extra: a.extra,
+        }
+    }
+}
+
+impl From<WireActivity> for Activity {
+    fn from(mut w: WireActivity) -> Self {
+        let buttons = w.buttons.map(|labels| {
+            let urls = w
+                .metadata
+                .as_mut()
+                .map(|m| std::mem::take(&mut m.button_urls))
+                .unwrap_or_default();
+            labels
+                .into_iter()
+                .zip(urls.into_iter().chain(std::iter::repeat(String::new())))
+                .map(|(label, url)| ActivityButton { label, url })
+                .collect()
+        });
+        if let Some(meta) = w.metadata.filter(|m| *m != ActivityMetadata::default()) {
+            w.extra.insert(
+                "metadata".into(),
+                serde_json::to_value(meta).unwrap_or_default(),
+            );
+        }
+        Activity {
+            name: w.name,
+            kind: w.kind,
+            application_id: w.application_id,
+            status_display_type: w.status_display_type,
+            state: w.state,
+            state_url: w.state_url,
+            details: w.details,
+            details_url: w.details_url,
+            created_at: w.created_at,
+            timestamps: w.timestamps,
+            assets: w.assets,
+            party: w.party,
+            secrets: w.secrets,
+            buttons,
+            instance: w.instance,
+            flags: w.flags,
+            extra: w.extra,
+        }
+    }
+}
+
+impl Activity {
+    // Accepts either the input shape or an already-normalized wire activity
+    pub fn from_value(v: Value) -> Result<Self, serde_json::Error> {
+        let wire_buttons = v
+            .get("buttons")
+            .and_then(Value::as_array)
+            .is_some_and(|b| b.iter().any(Value::is_string));
+        if wire_buttons {
+            serde_json::from_value::<WireActivity>(v).map(Activity::from)
+        } else {
+            serde_json::from_value(v)
+        }
+    }
+
+    pub fn normalize(self) -> WireActivity {
+        self.normalize_at(current_millis())
+    }
+
+    // Deterministic variant: the clock only feeds the created_at default
+    pub fn normalize_at(mut self, now_ms: u64) -> WireActivity {
+        for (_, step) in NORMALIZE_STEPS {
+            step(&mut self, now_ms);
+        }
+        WireActivity::from(self)
+    }
+}
+
+impl WireActivity {
+    pub fn into_activity(self) -> Activity {
+        Activity::from(self)
+    }
+}
+
+fn current_millis() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
@@ -18,6 +18,13 @@ impl ActivityRegistry {
     pub fn clear(&self, socket: &str) {
         self.inner.write().insert(socket.to_string(), Value::Null);
     }
+    pub fn get(&self, socket: &str) -> Option<Value> {
+        self.inner
+            .read()
+            .get(socket)
+            .filter(|v| !v.is_null())
+            .cloned()
+    }
     pub fn snapshot(&self) -> HashMap<String, Value> {
         self.inner.read().clone()
     }
@@ -0,0 +1,226 @@
+use crate::activity_normalize::seconds_to_ms;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+// Limits Discord enforces on SET_ACTIVITY; null fields count as absent
+pub const TEXT_MIN_CHARS: usize = 2;
+pub const TEXT_MAX_CHARS: usize = 128;
+pub const BUTTON_LABEL_MAX_CHARS: usize = 32;
+pub const BUTTON_URL_MAX_CHARS: usize = 512;
+pub const FIELD_URL_MAX_CHARS: usize = 256;
+pub const MAX_BUTTONS: usize = 2;
+// Playing, Listening, Watching, Competing; Streaming and Custom can't be set over RPC
+pub const ALLOWED_ACTIVITY_TYPES: [u64; 4] = [0, 2, 3, 5];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{}", summary(.errors))]
+pub struct ActivityValidationError {
+    pub errors: Vec<FieldError>,
+}
+
+fn summary(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+// Checks a raw `args.activity` object; every violation is reported, not just the first
+pub fn validate_activity(activity: &Value) -> Result<(), ActivityValidationError> {
+    let mut v = Validator::default();
+    let Some(obj) = activity.as_object() else {
+        v.push("activity", "must be an object");
+        return v.finish();
+    };
+    let field = |name: &str| obj.get(name).filter(|x| !x.is_null());
+
+    v.text("state", field("state"), TEXT_MIN_CHARS, TEXT_MAX_CHARS);
+    v.text("details", field("details"), TEXT_MIN_CHARS, TEXT_MAX_CHARS);
+
+    if let Some(t) = field("type") {
+        match t.as_u64() {
+            Some(n) if ALLOWED_ACTIVITY_TYPES.contains(&n) => {}
+            _ => v.push("type", "must be one of 0, 2, 3 or 5"),
+        }
+    }
+
+    if let Some(t) = field("status_display_type")
+        && t.as_u64().is_none_or(|n| n > 2)
+    {
+        v.push("status_display_type", "must be 0, 1 or 2");
+    }
+    for name in ["state_url", "details_url"] {
+        if let Some(url) = field(name) {
+            v.url(name, Some(url), FIELD_URL_MAX_CHARS);
+        }
+    }
+    if field("created_at").is_some_and(|x| x.as_u64().is_none()) {
+        v.push("created_at", "must be a non-negative integer");
+    }
+    if let Some(id) = field("application_id")
+        && !id
+            .as_str()
+            .is_some_and(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+    {
+        v.push("application_id", "must be a snowflake string");
+    }
+
+    if let Some(ts) = field("timestamps") {
+        let start = ts.get("start").filter(|x| !x.is_null());
+        let end = ts.get("end").filter(|x| !x.is_null());
+        for (name, val) in [("timestamps.start", start), ("timestamps.end", end)] {
+            if val.is_some_and(|x| x.as_u64().is_none()) {
+                v.push(name, "must be a non-negative integer");
+            }
+        }
+        // Compared as normalization will store them, since either may be in seconds or ms
+        if let (Some(s), Some(e)) = (start.and_then(Value::as_u64), end.and_then(Value::as_u64))
+            && seconds_to_ms(s) > seconds_to_ms(e)
+        {
+            v.push("timestamps", "start must not be after end");
+        }
+    }
+
+    if let Some(assets) = field("assets") {
+        if assets.is_object() {
+            for key in ["large_text", "small_text"] {
+                let val = assets.get(key).filter(|x| !x.is_null());
+                v.text(
+                    &format!("assets.{key}"),
+                    val,
+                    TEXT_MIN_CHARS,
+                    TEXT_MAX_CHARS,
+                );
+            }
+            for key in ["large_url", "small_url"] {
+                if let Some(url) = assets.get(key).filter(|x| !x.is_null()) {
+                    v.url(&format!("assets.{key}"), Some(url), FIELD_URL_MAX_CHARS);
+                }
+            }
+        } else {
+            v.push("assets", "must be an object");
+        }
+    }
+
+    let party = field("party");
+    let party_id = party.and_then(|p| p.get("id")).filter(|x| !x.is_null());
+    if let Some(party) = party {
+        v.text("party.id", party_id, TEXT_MIN_CHARS, TEXT_MAX_CHARS);
+        if let Some(size) = party.get("size").filter(|x| !x.is_null()) {
+            match size.as_array().map(|a| a.as_slice()) {
+                Some([cur, max]) => match (cur.as_u64(), max.as_u64()) {
+                    (Some(cur), Some(max)) if cur >= 1 && cur <= max => {}
+                    (Some(_), Some(_)) => {
+                        v.push("party.size", "current size must be between 1 and max size")
+                    }
+                    _ => v.push("party.size", "sizes must be non-negative integers"),
+                },
+                _ => v.push("party.size", "must be [current_size, max_size]"),
+            }
+        }
+    }
+
+    let secrets = field("secrets");
+    let mut has_secret = false;
+    if let Some(secrets) = secrets {
+        for key in ["join", "spectate", "match"] {
+            let val = secrets.get(key).filter(|x| !x.is_null());
+            has_secret |= val.is_some();
+            v.text(&format!("secrets.{key}"), val, 1, TEXT_MAX_CHARS);
+        }
+        if has_secret && party_id.is_none() {
+            v.push("party.id", "required when secrets are set");
+        }
+    }
+
+    let buttons = field("buttons");
+    if let Some(buttons) = buttons {
+        match buttons.as_array() {
+            Some(list) => {
+                if list.len() > MAX_BUTTONS {
+                    v.push("buttons", "max 2 buttons");
+                }
+                for (i, b) in list.iter().enumerate() {
+                    v.text(
+                        &format!("buttons[{i}].label"),
+                        Some(b.get("label").unwrap_or(&Value::Null)),
+                        TEXT_MIN_CHARS,
+                        BUTTON_LABEL_MAX_CHARS,
+                    );
+                    v.url(
+                        &format!("buttons[{i}].url"),
+                        b.get("url"),
+                        BUTTON_URL_MAX_CHARS,
+                    );
+                }
+            }
+            None => v.push("buttons", "must be an array"),
+        }
+        let instance = field("instance").and_then(Value::as_bool).unwrap_or(false);
+        if instance || has_secret {
+            v.push("buttons", "cannot be combined with instance or secrets");
+        }
+    }
+
+    v.finish()
+}
+
+#[derive(Default)]
+struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    fn push(&mut self, field: &str, message: &str) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    fn text(&mut self, field: &str, val: Option<&Value>, min: usize, max: usize) {
+        let Some(val) = val else { return };
+        match val.as_str() {
+            Some(s) => {
+                let n = s.chars().count();
+                if n < min || n > max {
+                    self.push(field, &format!("must be {min}-{max} characters"));
+                }
+            }
+            None => self.push(field, "must be a string"),
+        }
+    }
+
+    fn url(&mut self, field: &str, val: Option<&Value>, max: usize) {
+        let Some(s) = val.and_then(Value::as_str) else {
+            self.push(field, "must be a string");
+            return;
+        };
+        let scheme_ok = reqwest::Url::parse(s)
+            .map(|u| matches!(u.scheme(), "http" | "https"))
+            .unwrap_or(false);
+        if !scheme_ok {
+            self.push(field, "must be an http(s) URL");
+        } else if s.chars().count() > max {
+            self.push(field, &format!("must be at most {max} characters"));
+        }
+    }
+
+    fn finish(self) -> Result<(), ActivityValidationError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ActivityValidationError {
+                errors: self.errors,
+            })
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use crate::image_cache::ImageCache;
+use crate::protocol::ReadyConfig;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+pub const MEDIA_PROXY_HOST: &str = "media.discordapp.net";
+pub const DEFAULT_ASSETS_URL: &str =
+    "https://discord.com/api/v9/oauth2/applications/{application_id}/assets";
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+const FETCH_RETRY: Duration = Duration::from_secs(5 * 60);
+// Disk copies older than this are fetched again, and only used when that fails
+pub const ASSET_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+// One entry of an application's rich presence asset list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppAsset {
+    pub id: String,
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub kind: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageRef<'a> {
+    // http(s) URL, used as is
+    Url(&'a str),
+    // `mp:` media proxy path, e.g. `mp:external/abc/https/example.com/a.png`
+    MediaProxy(&'a str),
+    // Asset name or id from the application's asset list
+    AssetKey(&'a str),
+}
+
+impl<'a> ImageRef<'a> {
+    pub fn parse(image: &'a str) -> Self {
+        if let Some(path) = image.strip_prefix("mp:") {
+            ImageRef::MediaProxy(path)
+        } else if image.starts_with("https://") || image.starts_with("http://") {
+            ImageRef::Url(image)
+        } else {
+            ImageRef::AssetKey(image)
+        }
+    }
+}
+
+pub fn media_proxy_url(path: &str) -> String {
+    format!(
+        "https://{MEDIA_PROXY_HOST}/{}",
+        path.trim_start_matches('/')
+    )
+}
+
+pub fn app_asset_url(cdn_host: &str, application_id: &str, asset_id: &str) -> String {
+    format!("https://{cdn_host}/app-assets/{application_id}/{asset_id}.png")
+}
+
+// Maps activity images to URLs. Asset lists are kept in memory per application, backed by
+// `<cache_dir>/<application_id>.json` and fetched when the `network` feature allows it.
+pub struct AssetResolver {
+    cdn_host: String,
+    source_url: String,
+    cache_dir: Option<PathBuf>,
+    assets: RwLock<HashMap<String, Arc<Vec<AppAsset>>>>,
+    loading: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+    // When each application's last fetch failed
+    failed: Mutex<HashMap<String, Instant>>,
+    images: Option<Arc<ImageCache>>,
+}
+
+impl Default for AssetResolver {
+    fn default() -> Self {
+        Self::new(&ReadyConfig::default())
+    }
+}
+
+impl AssetResolver {
+    pub fn new(config: &ReadyConfig) -> Self {
+        Self {
+            cdn_host: config.cdn_host.clone(),
+            source_url: DEFAULT_ASSETS_URL.to_string(),
+            cache_dir: None,
+            assets: RwLock::new(HashMap::new()),
+            loading: Mutex::new(HashMap::new()),
+            failed: Mutex::new(HashMap::new()),
+            images: None,
+        }
+    }
+
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    // `{application_id}` is replaced with the application's id
+    pub fn with_source_url(mut self, url: impl Into<String>) -> Self {
+        self.source_url = url.into();
+        self
+    }
+
+    pub fn with_image_cache(mut self, images: Arc<ImageCache>) -> Self {
+        self.images = Some(images);
+        self
+    }
+
+    pub fn image_cache(&self) -> Option<&Arc<ImageCache>> {
+        self.images.as_ref()
+    }
+
+    pub fn cdn_host(&self) -> &str {
+        &self.cdn_host
+    }
+
+    pub fn insert(&self, application_id: &str, assets: Vec<AppAsset>) {
+        self.assets
+            .write()
+            .insert(application_id.to_string(), Arc::new(assets));
+    }
+
+    pub fn cached(&self, application_id: &str) -> Option<Arc<Vec<AppAsset>>> {
+        self.assets.read().get(application_id).cloned()
+    }
+
+    // Only consults asset lists that are already loaded
+    pub fn resolve(&self, application_id: Option<&str>, image: &str) -> Option<String> {
+        match ImageRef::parse(image) {
+            ImageRef::Url(url) => Some(url.to_string()),
+            ImageRef::MediaProxy(path) => Some(media_proxy_url(path)),
+            ImageRef::AssetKey(key) => {
+                let app = application_id?;
+                let list = self.cached(app);
+                let asset_id = list
+                    .as_ref()
+                    .and_then(|l| l.iter().find(|a| a.name == key || a.id == key))
+                    .map(|a| a.id.as_str())
+                    // A bare snowflake is already an asset id
+                    .or_else(|| is_snowflake(key).then_some(key))?;
+                Some(app_asset_url(&self.cdn_host, app, asset_id))
+            }
+        }
+    }
+
+    // Memory, then a fresh disk copy, then the network, falling back to a stale disk copy. A
+    // failed fetch without one yields an empty list but isn't kept: the network is tried again
+    // once `FETCH_RETRY` has passed.
+    pub async fn load(&self, application_id: &str) -> Arc<Vec<AppAsset>> {
+        if let Some(list) = self.cached(application_id) {
+            return list;
+        }
+        // One load per application at a time; whoever waited finds its result cached
+        let lock = self
+            .loading
+            .lock()
+            .entry(application_id.to_string())
+            .or_default()
+            .clone();
+        let guard = lock.lock().await;
+        let list = match self.cached(application_id) {
+            Some(list) => Some(list),
+            None => self.load_uncached(application_id).await,
+        };
+        drop(guard);
+        self.loading.lock().remove(application_id);
+        list.unwrap_or_default()
+    }
+
+    async fn load_uncached(&self, application_id: &str) -> Option<Arc<Vec<AppAsset>>> {
+        let stale = match self.read_cache(application_id) {
+            Some((list, true)) => return Some(self.keep(application_id, list)),
+            stale => stale.map(|(list, _)| list),
+        };
+        if !self.recently_failed(application_id) {
+            match self.fetch(application_id).await {
+                Ok(list) => {
+                    self.write_cache(application_id, &list);
+                    self.failed.lock().remove(application_id);
+                    return Some(self.keep(application_id, list));
+                }
+                Err(e) => {
+                    debug!(application_id, error=%e, "asset list unavailable");
+                    self.failed
+                        .lock()
+                        .insert(application_id.to_string(), Instant::now());
+                }
+            }
+        }
+        stale.map(|list| self.keep(application_id, list))
+    }
+
+    fn keep(&self, application_id: &str, list: Vec<AppAsset>) -> Arc<Vec<AppAsset>> {
+        let list = Arc::new(list);
+        self.assets
+            .write()
+            .insert(application_id.to_string(), list.clone());
+        list
+    }
+
+    // Runs `load` on its own task, unless the list is loaded, a load is already running or
+    // the last fetch failed less than `FETCH_RETRY` ago. For callers that can't wait, e.g.
+    // REST handlers that are polled.
+    pub fn spawn_load(self: &Arc<Self>, application_id: &str) {
+        if self.cached(application_id).is_some() || self.recently_failed(application_id) {
+            return;
+        }
+        // Reserved here rather than in the task, so concurrent calls see it at once
+        match self.loading.lock().entry(application_id.to_string()) {
+            Entry::Occupied(_) => return,
+            Entry::Vacant(e) => {
+                e.insert(Arc::default());
+            }
+        }
+        let this = self.clone();
+        let app = application_id.to_string();
+        tokio::spawn(async move {
+            this.load(&app).await;
+        });
+    }
+
+    fn recently_failed(&self, application_id: &str) -> bool {
+        self.failed
+            .lock()
+            .get(application_id)
+            .is_some_and(|at| at.elapsed() < FETCH_RETRY)
+    }
+
+    // Adds `large_image_url`/`small_image_url` next to the images in `activity.assets`, using
+    // only asset lists that are already loaded. Returns the application whose list is still
+    // needed; `load` it (off the event path) and annotate again.
+    pub fn annotate(&self, activity: &mut Value) -> Option<String> {
+        let app_id = activity
+            .get("application_id")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        let assets = activity.get_mut("assets").and_then(Value::as_object_mut)?;
+        let needs_list = ["large_image", "small_image"].iter().any(|k| {
+            assets
+                .get(*k)
+                .and_then(Value::as_str)
+                .is_some_and(|s| matches!(ImageRef::parse(s), ImageRef::AssetKey(_)))
+        });
+        for key in ["large_image", "small_image"] {
+            let url_key = format!("{key}_url");
+            let resolved = assets
+                .get(key)
+                .and_then(Value::as_str)
+                .and_then(|img| self.resolve(app_id.as_deref(), img));
+            match resolved {
+                Some(url) => {
+                    assets.insert(url_key, Value::String(url));
+                }
+                None => {
+                    assets.remove(&url_key);
+                }
+            }
+        }
+        app_id.filter(|app| needs_list && self.cached(app).is_none())
+    }
+
+    // Points resolved image URLs at images the local cache already holds. Returns whether
+    // others could still be downloaded by `localize`.
+    pub fn localize_cached(&self, activity: &mut Value) -> bool {
+        let Some(images) = self.images.as_ref().filter(|i| i.is_served()) else {
+            return false;
+        };
+        let Some(assets) = activity.get_mut("assets").and_then(Value::as_object_mut) else {
+            return false;
+        };
+        let mut pending = false;
+        for key in ["large_image_url", "small_image_url"] {
+            let Some(url) = assets.get(key).and_then(Value::as_str) else {
+                continue;
+            };
+            match images.cached_url(url) {
+                Some(local) => {
+                    assets.insert(key.to_string(), Value::String(local));
+                }
+                None => pending |= images.accepts(url),
+            }
+        }
+        pending
+    }
+
+    // Points resolved image URLs at the local image cache; images it can't fetch stay remote
+    pub async fn localize(&self, activity: &mut Value) {
+        let Some(images) = &self.images else { return };
+        let Some(assets) = activity.get_mut("assets").and_then(Value::as_object_mut) else {
+            return;
+        };
+        for key in ["large_image_url", "small_image_url"] {
+            let Some(url) = assets.get(key).and_then(Value::as_str) else {
+                continue;
+            };
+            if let Some(local) = images.localize(url).await {
+                assets.insert(key.to_string(), Value::String(local));
+            }
+        }
+    }
+
+    fn cache_file(&self, application_id: &str) -> Option<PathBuf> {
+        if !is_snowflake(application_id) {
+            return None;
+        }
+        Some(
+            self.cache_dir
+                .as_ref()?
+                .join(format!("{application_id}.json")),
+        )
+    }
+
+    // The list and whether it is younger than `ASSET_CACHE_TTL`
+    fn read_cache(&self, application_id: &str) -> Option<(Vec<AppAsset>, bool)> {
+        let path = self.cache_file(application_id)?;
+        let data = std::fs::read_to_string(&path).ok()?;
+        let list = serde_json::from_str(&data)
+            .inspect_err(|e| warn!(application_id, error=%e, "invalid asset cache"))
+            .ok()?;
+        let fresh = std::fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .is_some_and(|age| age < ASSET_CACHE_TTL);
+        Some((list, fresh))
+    }
+
+    fn write_cache(&self, application_id: &str, list: &[AppAsset]) {
+        let Some(path) = self.cache_file(application_id) else {
+            return;
+        };
+        let res = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_vec(list)?));
+        if let Err(e) = res {
+            warn!(path=%path.display(), error=%e, "failed to write asset cache");
+        }
+    }
+
+    #[cfg(feature = "network")]
+    async fn fetch(&self, application_id: &str) -> Result<Vec<AppAsset>, AssetError> {
+        if std::env::var("DRPC_OFFLINE").ok().as_deref() == Some("1") {
+            return Err(AssetError::Offline);
+        }
+        if !is_snowflake(application_id) {
+            return Err(AssetError::InvalidApplication(application_id.to_string()));
+        }
+        let url = self.source_url.replace("{application_id}", application_id);
+        let resp = crate::http::client()?
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(AssetError::Status(resp.status().as_u16()));
+        }
+        Ok(resp.json().await?)
+    }
+
+    #[cfg(not(feature = "network"))]
+    async fn fetch(&self, _application_id: &str) -> Result<Vec<AppAsset>, AssetError> {
+        let _ = &self.source_url;
+        Err(AssetError::NetworkDisabled)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AssetError {
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected status {0}")]
+    Status(u16),
+    #[error("invalid application id {0:?}")]
+    InvalidApplication(String),
+    #[error("offline")]
+    Offline,
+    #[error("network feature disabled")]
+    NetworkDisabled,
+}
+
+fn is_snowflake(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+pub fn assets_dir() -> Option<PathBuf> {
+    Some(std::env::home_dir()?.join(".drpc").join("assets"))
+}
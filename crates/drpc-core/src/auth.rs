@@ -0,0 +1,138 @@
+use crate::glob::glob_match;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+// Browser origins the Bridge accepts by default: Discord's web clients and local pages. Pages
+// opened from a file send `null`, but so do sandboxed iframes anywhere, so it must be opted into.
+pub const DEFAULT_BRIDGE_ORIGINS: &[&str] = &[
+    "https://discord.com",
+    "https://*.discord.com",
+    "http://localhost:*",
+    "http://127.0.0.1:*",
+];
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to generate token: {0}")]
+    Random(String),
+    #[error("token file {0} is empty")]
+    Empty(PathBuf),
+}
+
+// Shared by REST and the Bridge. Without a token only the Host (and for the Bridge, Origin)
+// checks apply.
+#[derive(Debug, Clone)]
+pub struct AuthConfig {
+    pub token: Option<String>,
+    // Globs (`*`, `?`) matched case-insensitively against the Origin header
+    pub bridge_origins: Vec<String>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            bridge_origins: DEFAULT_BRIDGE_ORIGINS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    // `Authorization: Bearer <token>`
+    pub fn check_bearer(&self, header: Option<&str>) -> bool {
+        let given = header
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .map(str::trim);
+        self.check_token(given)
+    }
+
+    pub fn check_token(&self, given: Option<&str>) -> bool {
+        match &self.token {
+            None => true,
+            Some(token) => given.is_some_and(|g| constant_time_eq(g.as_bytes(), token.as_bytes())),
+        }
+    }
+
+    // Requests without an Origin don't come from a browser page
+    pub fn origin_allowed(&self, origin: Option<&str>) -> bool {
+        origin.is_none_or(|o| self.bridge_origins.iter().any(|p| glob_match(p, o)))
+    }
+}
+
+// A DNS rebinding page reaches us under its own host name, so only loopback names are served
+pub fn is_local_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or(""),
+        None => host.rsplit_once(':').map_or(host, |(name, port)| {
+            if port.bytes().all(|b| b.is_ascii_digit()) {
+                name
+            } else {
+                host
+            }
+        }),
+    };
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    name == "localhost"
+        || name.ends_with(".localhost")
+        || name == "::1"
+        || name
+            .parse::<std::net::Ipv4Addr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn token_path() -> Option<PathBuf> {
+    Some(std::env::home_dir()?.join(".drpc").join("token"))
+}
+
+// Reuses an existing token so clients keep working across restarts; a new one is written
+// readable by the owner only
+pub fn load_or_create_token(path: &Path) -> Result<String, AuthError> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => {
+            let token = data.trim();
+            if token.is_empty() {
+                return Err(AuthError::Empty(path.to_path_buf()));
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            }
+            return Ok(token.to_string());
+        }
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        Err(_) => {}
+    }
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| AuthError::Random(e.to_string()))?;
+    let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    let mut file = opts.open(path)?;
+    file.write_all(token.as_bytes())?;
+    file.write_all(b"\n")?;
+    Ok(token)
+}
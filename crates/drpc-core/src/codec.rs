@@ -0,0 +1,92 @@
+use crate::protocol::{IncomingFrame, OutgoingFrame};
+use serde_json::Value;
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Error)]
+pub enum CodecError {
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "etf")]
+    #[error("etf error: {0}")]
+    Etf(#[from] crate::etf::EtfError),
+    #[error("{codec} codec does not accept {kind} messages")]
+    UnexpectedMessage {
+        codec: &'static str,
+        kind: &'static str,
+    },
+    #[error("unsupported encoding {0:?}")]
+    UnsupportedEncoding(String),
+}
+
+// Converts RPC frames to and from transport messages; negotiated once per connection
+pub trait WireCodec: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn encode_value(&self, v: &Value) -> Result<WireMessage, CodecError>;
+    fn decode_value(&self, msg: &WireMessage) -> Result<Value, CodecError>;
+
+    fn encode_frame(&self, frame: &OutgoingFrame) -> Result<WireMessage, CodecError> {
+        self.encode_value(&serde_json::to_value(frame)?)
+    }
+    fn decode_frame(&self, msg: &WireMessage) -> Result<IncomingFrame, CodecError> {
+        Ok(serde_json::from_value(self.decode_value(msg)?)?)
+    }
+}
+
+pub struct JsonCodec;
+
+impl WireCodec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+    fn encode_value(&self, v: &Value) -> Result<WireMessage, CodecError> {
+        Ok(WireMessage::Text(serde_json::to_string(v)?))
+    }
+    fn decode_value(&self, msg: &WireMessage) -> Result<Value, CodecError> {
+        match msg {
+            WireMessage::Text(t) => Ok(serde_json::from_str(t)?),
+            WireMessage::Binary(_) => Err(CodecError::UnexpectedMessage {
+                codec: self.name(),
+                kind: "binary",
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "etf")]
+pub struct EtfCodec;
+
+#[cfg(feature = "etf")]
+impl WireCodec for EtfCodec {
+    fn name(&self) -> &'static str {
+        "etf"
+    }
+    fn encode_value(&self, v: &Value) -> Result<WireMessage, CodecError> {
+        Ok(WireMessage::Binary(crate::etf::encode(v)?))
+    }
+    fn decode_value(&self, msg: &WireMessage) -> Result<Value, CodecError> {
+        match msg {
+            WireMessage::Binary(b) => Ok(crate::etf::decode(b)?),
+            WireMessage::Text(_) => Err(CodecError::UnexpectedMessage {
+                codec: self.name(),
+                kind: "text",
+            }),
+        }
+    }
+}
+
+// Maps the `encoding` query parameter to a codec; encodings not compiled in are an error
+pub fn negotiate_codec(encoding: &str) -> Result<Arc<dyn WireCodec>, CodecError> {
+    match encoding.to_ascii_lowercase().as_str() {
+        "json" => Ok(Arc::new(JsonCodec)),
+        #[cfg(feature = "etf")]
+        "etf" => Ok(Arc::new(EtfCodec)),
+        other => Err(CodecError::UnsupportedEncoding(other.to_string())),
+    }
+}
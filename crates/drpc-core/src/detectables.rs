@@ -1,17 +1,56 @@
-use parking_lot::RwLock;
+use crate::detectables_cache::DetectablesCache;
+use crate::detectables_source::{DetectablesSource, FetchError, Fetched, fetch_first};
+use crate::protocol::{Activity, ActivityAssets, ActivityTimestamps, ActivityType};
+use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::{
-    path::PathBuf,
+    path::{Path, PathBuf},
     time::{Duration, SystemTime},
 };
 use tracing::{debug, info, warn};
 
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum DetectableOs {
+    Win32,
+    Linux,
+    Darwin,
+    #[serde(other)]
+    Unknown,
+}
+
+impl DetectableOs {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            DetectableOs::Win32
+        } else if cfg!(target_os = "macos") {
+            DetectableOs::Darwin
+        } else {
+            DetectableOs::Linux
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct DetectableExecutable {
     pub name: String,
     #[serde(default)]
     pub is_launcher: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<DetectableOs>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct ThirdPartySku {
+    pub distributor: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sku: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -20,16 +59,114 @@ pub struct DetectableEntry {
     pub name: String,
     #[serde(default)]
     pub executables: Vec<DetectableExecutable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    // Discord application type (1 = game, 2 = music, ...)
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub app_type: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub themes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub third_party_skus: Vec<ThirdPartySku>,
+    // Not part of Discord's list; set by local overrides for games without an icon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_image: Option<String>,
+}
+
+impl DetectableEntry {
+    pub fn icon_url(&self, cdn_host: &str) -> Option<String> {
+        let id = self.id.as_deref()?;
+        let hash = self.icon_hash.as_deref()?;
+        Some(format!("https://{cdn_host}/app-icons/{id}/{hash}.png"))
+    }
+
+    pub fn activity_type(&self) -> ActivityType {
+        match self.app_type {
+            Some(2) => ActivityType::Listening,
+            _ => ActivityType::Playing,
+        }
+    }
+
+    // Activity shown for a detected process, mirroring what the Discord client displays
+    pub fn to_activity(&self, cdn_host: &str, start_ms: u64) -> Activity {
+        let mut act = Activity {
+            name: self.name.clone(),
+            kind: Some(self.activity_type()),
+            application_id: self.id.clone(),
+            timestamps: Some(ActivityTimestamps {
+                start: Some(start_ms),
+                end: None,
+            }),
+            ..Default::default()
+        };
+        if let Some(url) = self.large_image.clone().or_else(|| self.icon_url(cdn_host)) {
+            act.assets = Some(ActivityAssets {
+                large_image: Some(url),
+                large_text: Some(self.name.clone()),
+                ..Default::default()
+            });
+        }
+        act
+    }
 }
 
+// Readers get a cheap `Arc` snapshot; a refresh is a single pointer swap
 #[derive(Clone, Default)]
 pub struct Detectables {
-    pub inner: Arc<RwLock<Vec<DetectableEntry>>>,
+    current: Arc<ArcSwap<Vec<DetectableEntry>>>,
+    generation: Arc<AtomicU64>,
 }
 
 impl Detectables {
+    pub fn new(list: impl Into<Arc<Vec<DetectableEntry>>>) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::new(list.into())),
+            generation: Arc::default(),
+        }
+    }
     pub fn list(&self) -> Arc<Vec<DetectableEntry>> {
-        Arc::new(self.inner.read().clone())
+        self.current.load_full()
+    }
+    pub fn len(&self) -> usize {
+        self.current.load().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    pub fn replace(&self, list: impl Into<Arc<Vec<DetectableEntry>>>) {
+        let list = list.into();
+        crate::metrics::DETECTABLES_COUNT.store(list.len() as u64, Ordering::Relaxed);
+        self.current.store(list);
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+    // Bumped on every `replace`, so derived data (e.g. match indexes) knows when to rebuild
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectablesOptions {
+    // Tried in order until one succeeds
+    pub sources: Vec<DetectablesSource>,
+    pub force_refresh: bool,
+    pub ttl_hours: u64,
+    pub retries: usize,
+    // Defaults to ~/.drpc/detectables.json
+    pub cache_path: Option<PathBuf>,
+}
+
+impl Default for DetectablesOptions {
+    fn default() -> Self {
+        Self {
+            sources: vec![DetectablesSource::default()],
+            force_refresh: false,
+            ttl_hours: 24,
+            retries: 3,
+            cache_path: None,
+        }
     }
 }
 
@@ -37,113 +174,117 @@ pub async fn load_detectables_async(
     force_refresh: bool,
     ttl_hours: u64,
 ) -> Result<Detectables, Box<dyn std::error::Error>> {
-    let path = detectables_path()?;
-    let mut need_fetch = force_refresh || !path.exists();
+    load_detectables_with(&DetectablesOptions {
+        force_refresh,
+        ttl_hours,
+        ..Default::default()
+    })
+    .await
+}
+
+impl DetectablesOptions {
+    pub fn cache_file(&self) -> Option<PathBuf> {
+        self.cache_path.clone().or_else(|| detectables_path().ok())
+    }
+}
+
+pub async fn load_detectables_with(
+    opts: &DetectablesOptions,
+) -> Result<Detectables, Box<dyn std::error::Error>> {
+    let path = match &opts.cache_path {
+        Some(p) => p.clone(),
+        None => detectables_path()?,
+    };
+    let ttl_hours = opts.ttl_hours;
+    let cached = DetectablesCache::read_with_fallback(&path);
+    let mut need_fetch = opts.force_refresh || cached.is_none();
     if !need_fetch
-        && let Ok(meta) = std::fs::metadata(&path)
-        && let Ok(modified) = meta.modified()
-        && let Ok(age) = SystemTime::now().duration_since(modified)
+        && let Some(age) = cache_age(&path)
         && age > Duration::from_secs(ttl_hours * 3600)
     {
         need_fetch = true;
     }
     let mut list: Vec<DetectableEntry> = Vec::new();
     if need_fetch {
-        #[cfg(feature = "network")]
-        {
-            if std::env::var("DRPC_OFFLINE").ok().as_deref() == Some("1") {
-                debug!("offline mode; skipping network fetch; using empty detectables");
-            } else {
-                debug!("fetching detectables from network");
-                match fetch_remote_with_retries(3).await {
-                    Ok(remote) => {
-                        list = remote;
-                        if let Some(parent) = path.parent() {
-                            let _ = std::fs::create_dir_all(parent);
-                        }
-                        if let Ok(json) = serde_json::to_string_pretty(&list) {
-                            let _ = std::fs::write(&path, json);
-                        }
-                    }
-                    Err(e) => warn!(error=?e, "failed fetch; using fallback"),
+        if !opts.sources.iter().any(DetectablesSource::available) {
+            debug!("offline mode or network feature disabled; using cached detectables");
+            list = cached.map(|c| c.entries).unwrap_or_default();
+        } else {
+            match fetch_and_store(&path, cached.as_ref(), opts).await {
+                Ok(Some(fresh)) => list = fresh,
+                Ok(None) => list = cached.map(|c| c.entries).unwrap_or_default(),
+                Err(e) => {
+                    warn!(error=%e, "failed fetch; using cached detectables");
+                    list = cached.map(|c| c.entries).unwrap_or_default();
                 }
             }
         }
-        #[cfg(not(feature = "network"))]
-        {
-            debug!("network feature disabled; using empty detectables");
-        }
-    } else if let Ok(data) = std::fs::read_to_string(&path) {
-        match serde_json::from_str::<Vec<DetectableEntry>>(&data) {
-            Ok(v) => list = v,
-            Err(e) => warn!(error=?e, "failed to parse detectables; using empty"),
-        }
+    } else if let Some(c) = cached {
+        list = c.entries;
     }
-    if !path.exists() {
-        if let Some(parent) = path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        }
-        if let Ok(json) = serde_json::to_string_pretty(&list) {
-            let _ = std::fs::write(&path, json);
-        }
-    }
-    let mut age_hours: Option<u64> = None;
-    if let Ok(meta) = std::fs::metadata(&path)
-        && let Ok(modified) = meta.modified()
-        && let Ok(age) = SystemTime::now().duration_since(modified)
-    {
-        age_hours = Some(age.as_secs() / 3600);
+    let local = crate::detectables_local::load_local_detectables();
+    if !local.is_empty() {
+        local.apply(&mut list);
     }
+    let age_hours = cache_age(&path).map(|age| age.as_secs() / 3600);
     info!(count = list.len(), stale=need_fetch, age_hours=?age_hours, ttl_hours, "loaded detectables");
-    crate::metrics::DETECTABLES_COUNT
-        .store(list.len() as u64, std::sync::atomic::Ordering::Relaxed);
-    Ok(Detectables {
-        inner: Arc::new(RwLock::new(list)),
-    })
+    crate::metrics::DETECTABLES_COUNT.store(list.len() as u64, Ordering::Relaxed);
+    Ok(Detectables::new(list))
 }
 
-fn detectables_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let home = std::env::home_dir()
-        .ok_or_else(|| Box::<dyn std::error::Error>::from("Unable to find home directory"))?;
-    Ok(home.join(".drpc").join("detectables.json"))
-}
-
-#[cfg(feature = "network")]
-async fn fetch_remote() -> Result<Vec<DetectableEntry>, reqwest::Error> {
-    let url = "https://discord.com/api/v9/applications/detectable";
-    let resp = reqwest::get(url).await?;
-    if !resp.status().is_success() {
-        return Ok(Vec::new());
-    }
-    let val: serde_json::Value = resp.json().await?;
-    if let Some(arr) = val.as_array() {
-        let mut out = Vec::new();
-        for v in arr {
-            if let Ok(entry) = serde_json::from_value::<DetectableEntry>(v.clone()) {
-                out.push(entry);
-            }
-        }
-        Ok(out)
-    } else {
-        Ok(Vec::new())
+// Fetches regardless of TTL, with local additions applied; Ok(None) when the source reports no change
+pub async fn refresh_detectables(
+    opts: &DetectablesOptions,
+) -> Result<Option<Vec<DetectableEntry>>, FetchError> {
+    let path = opts.cache_file().ok_or_else(|| {
+        FetchError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "unable to find home directory",
+        ))
+    })?;
+    let cached = DetectablesCache::read_with_fallback(&path);
+    let Some(mut list) = fetch_and_store(&path, cached.as_ref(), opts).await? else {
+        return Ok(None);
+    };
+    let local = crate::detectables_local::load_local_detectables();
+    if !local.is_empty() {
+        local.apply(&mut list);
     }
+    Ok(Some(list))
 }
 
-#[cfg(feature = "network")]
-async fn fetch_remote_with_retries(max: usize) -> Result<Vec<DetectableEntry>, reqwest::Error> {
-    let mut attempt = 0;
-    loop {
-        if attempt > 0 {
-            tokio::time::sleep(std::time::Duration::from_millis(200 * attempt as u64)).await;
+async fn fetch_and_store(
+    path: &Path,
+    cached: Option<&DetectablesCache>,
+    opts: &DetectablesOptions,
+) -> Result<Option<Vec<DetectableEntry>>, FetchError> {
+    match fetch_first(&opts.sources, cached, opts.retries).await? {
+        Fetched::Modified(fresh) => {
+            if let Err(e) = fresh.write_atomic(path) {
+                warn!(error=%e, "failed to write detectables cache");
+            }
+            Ok(Some(fresh.entries))
         }
-        match fetch_remote().await {
-            Ok(v) => return Ok(v),
-            Err(e) if attempt + 1 < max => {
-                warn!(error=?e, attempt, "detectables fetch retry");
-                attempt += 1;
-                continue;
+        Fetched::NotModified => {
+            debug!("detectables not modified");
+            // Rewriting refreshes the TTL and restores the main file if it came from backup
+            if let Some(c) = cached
+                && let Err(e) = c.write_atomic(path)
+            {
+                warn!(error=%e, "failed to write detectables cache");
             }
-            Err(e) => return Err(e),
+            Ok(None)
         }
     }
 }
+
+pub(crate) fn cache_age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+fn detectables_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home = std::env::home_dir()
+        .ok_or_else(|| Box::<dyn std::error::Error>::from("Unable to find home directory"))?;
+    Ok(home.join(".drpc").join("detectables.json"))
+}
@@ -0,0 +1,141 @@
+use crate::detectables::DetectableEntry;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::warn;
+
+// Bump when the envelope layout changes and add a migration arm in `DetectablesCache::parse`
+pub const DETECTABLES_CACHE_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid cache json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("unsupported cache version {0}")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DetectablesCache {
+    pub version: u32,
+    // Source the entries came from, e.g. the detectables URL or a file:// mirror
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    // Validators from the last successful fetch, replayed as conditional request headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<DetectableEntry>,
+}
+
+impl DetectablesCache {
+    pub fn new(entries: Vec<DetectableEntry>) -> Self {
+        Self {
+            version: DETECTABLES_CACHE_VERSION,
+            entries,
+            ..Default::default()
+        }
+    }
+
+    pub fn parse(data: &str) -> Result<Self, CacheError> {
+        let val: serde_json::Value = serde_json::from_str(data)?;
+        // Version 0: the bare array written before the envelope existed
+        if val.is_array() {
+            return Ok(Self::new(serde_json::from_value(val)?));
+        }
+        let version = val
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0) as u32;
+        match version {
+            DETECTABLES_CACHE_VERSION => Ok(serde_json::from_value(val)?),
+            v => Err(CacheError::UnsupportedVersion(v)),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, CacheError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    // Main file first, then the last good copy; None only when neither is usable
+    pub fn read_with_fallback(path: &Path) -> Option<Self> {
+        let err = match Self::read(path) {
+            Ok(c) => return Some(c),
+            Err(e) => e,
+        };
+        let bak = backup_path(path);
+        let missing = matches!(&err, CacheError::Io(e) if e.kind() == std::io::ErrorKind::NotFound);
+        if missing && !bak.exists() {
+            return None;
+        }
+        if !missing {
+            warn!(error=%err, path=%path.display(), "failed to read detectables cache");
+        }
+        match Self::read(&bak) {
+            Ok(c) => {
+                warn!(path=%bak.display(), "using backup detectables cache");
+                Some(c)
+            }
+            Err(e) => {
+                warn!(error=%e, path=%bak.display(), "failed to read backup detectables cache");
+                None
+            }
+        }
+    }
+
+    // Writes to a sibling temp file and renames it over `path`, so readers never see a partial
+    // file. A previous file that is well-formed JSON is kept as `<path>.bak`.
+    pub fn write_atomic(&self, path: &Path) -> Result<(), CacheError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // The refresher and REST refreshes can write at once; the backup swap must not interleave
+        static WRITE_LOCK: Mutex<()> = Mutex::new(());
+        let _guard = WRITE_LOCK.lock();
+        let tmp = sibling_path(path, &format!(".tmp-{}", std::process::id()));
+        let res = self.write_to(&tmp).and_then(|_| {
+            if is_well_formed(path) {
+                std::fs::rename(path, backup_path(path))?;
+            }
+            std::fs::rename(&tmp, path)?;
+            Ok(())
+        });
+        if res.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        res
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), CacheError> {
+        let file = File::create(path)?;
+        let mut w = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut w, self)?;
+        w.flush()?;
+        w.get_ref().sync_all()?;
+        Ok(())
+    }
+}
+
+// Syntax check only, so choosing whether to back a file up doesn't build every entry
+fn is_well_formed(path: &Path) -> bool {
+    File::open(path).is_ok_and(|f| {
+        serde_json::from_reader::<_, serde::de::IgnoredAny>(BufReader::new(f)).is_ok()
+    })
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".bak")
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
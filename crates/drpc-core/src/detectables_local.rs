@@ -0,0 +1,139 @@
+use crate::detectables::{DetectableEntry, DetectableExecutable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+// User overlay merged on top of the downloaded detectables, read from
+// `~/.drpc/detectables.local.toml`:
+//
+// [[entries]]
+// name = "Studio Tool"
+// executables = [{ name = "studio-tool" }]
+//
+// [overrides."356875570916753438"]
+// name = "Minecraft (modded)"
+// large_image = "https://example.com/mc.png"
+//
+// [blocklist]
+// ids = ["1124049195335426139"]
+// executables = ["gameclient.exe"]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LocalDetectables {
+    pub entries: Vec<DetectableEntry>,
+    pub overrides: HashMap<String, DetectableOverride>,
+    pub blocklist: DetectableBlocklist,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DetectableOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_image: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DetectableBlocklist {
+    pub ids: Vec<String>,
+    // Executable names (case-insensitive) that never identify a game
+    pub executables: Vec<String>,
+}
+
+impl LocalDetectables {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+            && self.overrides.is_empty()
+            && self.blocklist.ids.is_empty()
+            && self.blocklist.executables.is_empty()
+    }
+
+    pub fn from_toml(s: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(s)
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let data = std::fs::read_to_string(path).ok()?;
+        match Self::from_toml(&data) {
+            Ok(local) => Some(local),
+            Err(e) => {
+                warn!(path=%path.display(), error=%e, "invalid local detectables; ignoring");
+                None
+            }
+        }
+    }
+
+    // Additions first (local entries replace downloaded ones with the same id), then the
+    // blocklist so it also applies to local entries, then overrides.
+    pub fn apply(&self, list: &mut Vec<DetectableEntry>) {
+        let before = list.len();
+        let mut entries = std::mem::take(list);
+        entries.retain(|d| {
+            d.id.as_ref()
+                .is_none_or(|id| !self.entries.iter().any(|l| l.id.as_ref() == Some(id)))
+        });
+        entries.extend(self.entries.iter().cloned());
+        entries.retain(|d| {
+            d.id.as_ref()
+                .is_none_or(|id| !self.blocklist.ids.contains(id))
+        });
+        if !self.blocklist.executables.is_empty() {
+            let blocked: Vec<String> = self
+                .blocklist
+                .executables
+                .iter()
+                .map(|e| normalize_exe(e))
+                .collect();
+            for d in entries.iter_mut() {
+                d.executables
+                    .retain(|e: &DetectableExecutable| !is_blocked(&blocked, &e.name));
+            }
+        }
+        for d in entries.iter_mut() {
+            let Some(o) = d.id.as_ref().and_then(|id| self.overrides.get(id)) else {
+                continue;
+            };
+            if let Some(name) = &o.name {
+                d.name = name.clone();
+            }
+            if let Some(hash) = &o.icon_hash {
+                d.icon_hash = Some(hash.clone());
+            }
+            if let Some(image) = &o.large_image {
+                d.large_image = Some(image.clone());
+            }
+        }
+        debug!(before, after = entries.len(), "applied local detectables");
+        *list = entries;
+    }
+}
+
+fn normalize_exe(name: &str) -> String {
+    name.trim_start_matches('>')
+        .to_lowercase()
+        .replace('\\', "/")
+}
+
+// A blocked name matches a detectable executable with the same trailing path components, so
+// `game.exe` also blocks `bin/game.exe`, as the scanner would match either against it
+fn is_blocked(blocked: &[String], name: &str) -> bool {
+    let name = normalize_exe(name);
+    blocked
+        .iter()
+        .any(|b| name == *b || name.ends_with(&format!("/{b}")))
+}
+
+pub fn local_detectables_path() -> Option<PathBuf> {
+    std::env::home_dir().map(|h| h.join(".drpc").join("detectables.local.toml"))
+}
+
+pub fn load_local_detectables() -> LocalDetectables {
+    local_detectables_path()
+        .and_then(|p| LocalDetectables::load(&p))
+        .unwrap_or_default()
+}
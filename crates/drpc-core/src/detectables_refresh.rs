@@ -0,0 +1,123 @@
+use crate::detectables::{Detectables, DetectablesOptions, cache_age, refresh_detectables};
+use crate::detectables_source::{DetectablesSource, FetchError};
+use crate::event::{EventBus, EventKind};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_BACKOFF: Duration = Duration::from_secs(30);
+
+pub struct DetectablesRefresher {
+    detectables: Detectables,
+    bus: EventBus,
+    opts: DetectablesOptions,
+    interval: Duration,
+    backoff: Duration,
+}
+
+impl DetectablesRefresher {
+    pub fn new(detectables: Detectables, bus: EventBus, opts: DetectablesOptions) -> Self {
+        let interval = Duration::from_secs(opts.ttl_hours * 3600).max(MIN_INTERVAL);
+        Self {
+            detectables,
+            bus,
+            opts,
+            interval,
+            backoff: DEFAULT_BACKOFF,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    // First retry delay after a failed refresh; doubles per failure up to the interval
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        let opts = DetectablesOptions {
+            force_refresh: true,
+            ..self.opts.clone()
+        };
+        // The startup load already honoured the TTL; wait out whatever is left of it. Without a
+        // cache the startup fetch failed, so retry on the backoff schedule instead.
+        let mut delay = match opts.cache_file().and_then(|p| cache_age(&p)) {
+            Some(age) => jitter(self.interval.saturating_sub(age)),
+            None => jitter(self.backoff),
+        };
+        let mut failures: u32 = 0;
+        loop {
+            tokio::time::sleep(delay).await;
+            if !opts.sources.iter().any(DetectablesSource::available) {
+                debug!("no detectables source available; skipping refresh");
+                delay = jitter(self.interval);
+                continue;
+            }
+            match refresh_detectables(&opts).await {
+                Ok(Some(list)) => {
+                    let count = list.len();
+                    self.detectables.replace(list);
+                    self.bus.publish(EventKind::DetectablesUpdated { count });
+                    info!(count, "refreshed detectables");
+                    failures = 0;
+                    delay = jitter(self.interval);
+                }
+                Ok(None) => {
+                    debug!("detectables unchanged");
+                    failures = 0;
+                    delay = jitter(self.interval);
+                }
+                Err(FetchError::NoSource) => delay = jitter(self.interval),
+                Err(e) => {
+                    failures += 1;
+                    delay = jitter(backoff_delay(self.backoff, self.interval, failures));
+                    warn!(error=%e, failures, retry_in=?delay, "detectables refresh failed");
+                }
+            }
+        }
+    }
+}
+
+fn backoff_delay(base: Duration, max: Duration, failures: u32) -> Duration {
+    let factor = 1u32 << failures.saturating_sub(1).min(16);
+    base.saturating_mul(factor).min(max)
+}
+
+// Up to +10% so many instances don't hit the source at the same moment
+fn jitter(d: Duration) -> Duration {
+    let spread = d.as_millis() as u64 / 10;
+    d + Duration::from_millis(fastrand::u64(0..=spread))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let base = Duration::from_secs(30);
+        let max = Duration::from_secs(3600);
+        assert_eq!(backoff_delay(base, max, 1), Duration::from_secs(30));
+        assert_eq!(backoff_delay(base, max, 2), Duration::from_secs(60));
+        assert_eq!(backoff_delay(base, max, 4), Duration::from_secs(240));
+        assert_eq!(backoff_delay(base, max, 10), max);
+        assert_eq!(backoff_delay(base, max, u32::MAX), max);
+    }
+
+    #[test]
+    fn jitter_stays_within_ten_percent() {
+        let d = Duration::from_secs(100);
+        for _ in 0..100 {
+            let j = jitter(d);
+            assert!(j >= d && j <= Duration::from_secs(110));
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::detectables::DetectableEntry;
+use crate::detectables_cache::DetectablesCache;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+pub const DEFAULT_DETECTABLES_URL: &str = "https://discord.com/api/v9/applications/detectable";
+// Per attempt, body included; the full list is a few megabytes
+pub const DETECTABLES_FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectablesSource {
+    Http(String),
+    File(PathBuf),
+}
+
+impl DetectablesSource {
+    // HTTP sources need the `network` feature and are skipped in offline mode
+    pub fn available(&self) -> bool {
+        match self {
+            DetectablesSource::Http(_) => {
+                cfg!(feature = "network")
+                    && std::env::var("DRPC_OFFLINE").ok().as_deref() != Some("1")
+            }
+            DetectablesSource::File(_) => true,
+        }
+    }
+}
+
+impl Default for DetectablesSource {
+    fn default() -> Self {
+        DetectablesSource::Http(DEFAULT_DETECTABLES_URL.to_string())
+    }
+}
+
+impl FromStr for DetectablesSource {
+    type Err = FetchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("file://") {
+            Ok(DetectablesSource::File(PathBuf::from(path)))
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(DetectablesSource::Http(s.to_string()))
+        } else {
+            Err(FetchError::InvalidSource(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for DetectablesSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectablesSource::Http(url) => f.write_str(url),
+            DetectablesSource::File(path) => write!(f, "file://{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected status {0}")]
+    Status(u16),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("malformed detectables: {0}")]
+    Malformed(String),
+    #[error("invalid detectables source {0:?}; expected http(s):// or file://")]
+    InvalidSource(String),
+    #[error("network feature disabled")]
+    NetworkDisabled,
+    #[error("no detectables source available")]
+    NoSource,
+}
+
+impl FetchError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Http(_) => true,
+            FetchError::Status(s) => *s >= 500 || *s == 429,
+            _ => false,
+        }
+    }
+}
+
+pub(crate) enum Fetched {
+    Modified(DetectablesCache),
+    // Only HTTP sources answer conditional requests
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    NotModified,
+}
+
+// Tries each available source in order; the first success wins
+pub(crate) async fn fetch_first(
+    sources: &[DetectablesSource],
+    cached: Option<&DetectablesCache>,
+    retries: usize,
+) -> Result<Fetched, FetchError> {
+    let mut last_err = FetchError::NoSource;
+    for src in sources.iter().filter(|s| s.available()) {
+        debug!(source=%src, "fetching detectables");
+        match fetch_with_retries(src, cached, retries).await {
+            Ok(f) => return Ok(f),
+            Err(e) => {
+                warn!(source=%src, error=%e, "detectables source failed");
+                last_err = e;
+            }
+        }
+    }
+    Err(last_err)
+}
+
+async fn fetch_with_retries(
+    src: &DetectablesSource,
+    cached: Option<&DetectablesCache>,
+    max: usize,
+) -> Result<Fetched, FetchError> {
+    let mut attempt = 0;
+    loop {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_millis(200 * attempt as u64)).await;
+        }
+        match fetch_source(src, cached).await {
+            Ok(v) => return Ok(v),
+            Err(e) if e.is_retryable() && attempt + 1 < max => {
+                warn!(error=%e, attempt, "detectables fetch retry");
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn fetch_source(
+    src: &DetectablesSource,
+    cached: Option<&DetectablesCache>,
+) -> Result<Fetched, FetchError> {
+    // Validators are only meaningful for the source that issued them
+    let cached = cached.filter(|c| c.source.as_deref() == Some(src.to_string().as_str()));
+    match src {
+        DetectablesSource::File(path) => {
+            let data = tokio::fs::read_to_string(path).await?;
+            Ok(Fetched::Modified(DetectablesCache {
+                source: Some(src.to_string()),
+                ..DetectablesCache::new(parse_entries(&data)?)
+            }))
+        }
+        #[cfg(feature = "network")]
+        DetectablesSource::Http(url) => fetch_http(url, cached).await,
+        #[cfg(not(feature = "network"))]
+        DetectablesSource::Http(_) => {
+            let _ = cached;
+            Err(FetchError::NetworkDisabled)
+        }
+    }
+}
+
+#[cfg(feature = "network")]
+async fn fetch_http(url: &str, cached: Option<&DetectablesCache>) -> Result<Fetched, FetchError> {
+    use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+    let mut req = crate::http::client()?
+        .get(url)
+        .timeout(DETECTABLES_FETCH_TIMEOUT);
+    if let Some(c) = cached {
+        if let Some(etag) = &c.etag {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(lm) = &c.last_modified {
+            req = req.header(IF_MODIFIED_SINCE, lm);
+        }
+    }
+    let resp = req.send().await?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !status.is_success() {
+        return Err(FetchError::Status(status.as_u16()));
+    }
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let body = resp.text().await?;
+    Ok(Fetched::Modified(DetectablesCache {
+        etag,
+        last_modified,
+        source: Some(url.to_string()),
+        ..DetectablesCache::new(parse_entries(&body)?)
+    }))
+}
+
+// Accepts Discord's bare array (skipping entries that don't parse) or a copied cache file
+fn parse_entries(data: &str) -> Result<Vec<DetectableEntry>, FetchError> {
+    let val: serde_json::Value =
+        serde_json::from_str(data).map_err(|e| FetchError::Malformed(e.to_string()))?;
+    match val {
+        serde_json::Value::Array(arr) => Ok(arr
+            .into_iter()
+            .filter_map(|v| serde_json::from_value(v).ok())
+            .collect()),
+        serde_json::Value::Object(_) => DetectablesCache::parse(data)
+            .map(|c| c.entries)
+            .map_err(|e| FetchError::Malformed(e.to_string())),
+        _ => Err(FetchError::Malformed("expected a JSON array".into())),
+    }
+}
@@ -0,0 +1,328 @@
+// Erlang External Term Format codec for RPC payloads, matching Discord's erlpack encoder:
+// strings as BINARY_EXT, null/bools as the atoms nil/true/false, empty arrays as NIL_EXT and
+// integers as the narrowest of SMALL_INTEGER_EXT, INTEGER_EXT and SMALL_BIG_EXT.
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+const VERSION: u8 = 131;
+const NEW_FLOAT_EXT: u8 = 70;
+const SMALL_INTEGER_EXT: u8 = 97;
+const INTEGER_EXT: u8 = 98;
+const FLOAT_EXT: u8 = 99;
+const ATOM_EXT: u8 = 100;
+const SMALL_TUPLE_EXT: u8 = 104;
+const LARGE_TUPLE_EXT: u8 = 105;
+const NIL_EXT: u8 = 106;
+const STRING_EXT: u8 = 107;
+const LIST_EXT: u8 = 108;
+const BINARY_EXT: u8 = 109;
+const SMALL_BIG_EXT: u8 = 110;
+const LARGE_BIG_EXT: u8 = 111;
+const SMALL_ATOM_EXT: u8 = 115;
+const MAP_EXT: u8 = 116;
+const ATOM_UTF8_EXT: u8 = 118;
+const SMALL_ATOM_UTF8_EXT: u8 = 119;
+
+// Guards the recursive decoder against hostile nesting
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum EtfError {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    #[error("unsupported version byte {0}")]
+    BadVersion(u8),
+    #[error("unsupported term tag {0}")]
+    UnsupportedTag(u8),
+    #[error("invalid float")]
+    InvalidFloat,
+    #[error("invalid map key")]
+    InvalidKey,
+    #[error("list without NIL_EXT tail")]
+    ImproperList,
+    #[error("term nested deeper than {MAX_DEPTH}")]
+    TooDeep,
+    #[error("{0} trailing bytes after term")]
+    TrailingData(usize),
+    #[error("{0} does not fit in ETF")]
+    TooLarge(&'static str),
+}
+
+pub fn encode(v: &Value) -> Result<Vec<u8>, EtfError> {
+    let mut out = vec![VERSION];
+    encode_value(&mut out, v)?;
+    Ok(out)
+}
+
+pub fn decode(bin: &[u8]) -> Result<Value, EtfError> {
+    let mut d = Decoder { bin, pos: 0 };
+    let version = d.u8()?;
+    if version != VERSION {
+        return Err(EtfError::BadVersion(version));
+    }
+    let v = d.term(0)?;
+    match bin.len() - d.pos {
+        0 => Ok(v),
+        n => Err(EtfError::TrailingData(n)),
+    }
+}
+
+fn encode_value(out: &mut Vec<u8>, v: &Value) -> Result<(), EtfError> {
+    match v {
+        Value::Null => atom(out, "nil"),
+        Value::Bool(true) => atom(out, "true"),
+        Value::Bool(false) => atom(out, "false"),
+        Value::Number(n) => number(out, n)?,
+        Value::String(s) => binary(out, s)?,
+        Value::Array(items) => {
+            if items.is_empty() {
+                out.push(NIL_EXT);
+            } else {
+                out.push(LIST_EXT);
+                out.extend_from_slice(&len32(items.len(), "list")?.to_be_bytes());
+                for item in items {
+                    encode_value(out, item)?;
+                }
+                out.push(NIL_EXT);
+            }
+        }
+        Value::Object(map) => {
+            out.push(MAP_EXT);
+            out.extend_from_slice(&len32(map.len(), "map")?.to_be_bytes());
+            for (k, val) in map {
+                binary(out, k)?;
+                encode_value(out, val)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn atom(out: &mut Vec<u8>, name: &str) {
+    out.push(SMALL_ATOM_EXT);
+    out.push(name.len() as u8);
+    out.extend_from_slice(name.as_bytes());
+}
+
+fn binary(out: &mut Vec<u8>, s: &str) -> Result<(), EtfError> {
+    out.push(BINARY_EXT);
+    out.extend_from_slice(&len32(s.len(), "binary")?.to_be_bytes());
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+fn number(out: &mut Vec<u8>, n: &Number) -> Result<(), EtfError> {
+    if let Some(u) = n.as_u64() {
+        if u <= u8::MAX as u64 {
+            out.extend_from_slice(&[SMALL_INTEGER_EXT, u as u8]);
+        } else if u <= i32::MAX as u64 {
+            out.push(INTEGER_EXT);
+            out.extend_from_slice(&(u as i32).to_be_bytes());
+        } else {
+            small_big(out, false, u);
+        }
+    } else if let Some(i) = n.as_i64() {
+        match i32::try_from(i) {
+            Ok(small) => {
+                out.push(INTEGER_EXT);
+                out.extend_from_slice(&small.to_be_bytes());
+            }
+            Err(_) => small_big(out, true, i.unsigned_abs()),
+        }
+    } else {
+        let f = n.as_f64().ok_or(EtfError::InvalidFloat)?;
+        out.push(NEW_FLOAT_EXT);
+        out.extend_from_slice(&f.to_be_bytes());
+    }
+    Ok(())
+}
+
+// Little-endian magnitude without trailing zero bytes, as erlpack writes it
+fn small_big(out: &mut Vec<u8>, negative: bool, mut magnitude: u64) {
+    let mut digits = Vec::with_capacity(8);
+    while magnitude > 0 {
+        digits.push(magnitude as u8);
+        magnitude >>= 8;
+    }
+    out.extend_from_slice(&[SMALL_BIG_EXT, digits.len() as u8, negative as u8]);
+    out.extend_from_slice(&digits);
+}
+
+fn len32(len: usize, what: &'static str) -> Result<u32, EtfError> {
+    u32::try_from(len).map_err(|_| EtfError::TooLarge(what))
+}
+
+struct Decoder<'a> {
+    bin: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], EtfError> {
+        let end = self.pos.checked_add(n).ok_or(EtfError::UnexpectedEof)?;
+        let s = self.bin.get(self.pos..end).ok_or(EtfError::UnexpectedEof)?;
+        self.pos = end;
+        Ok(s)
+    }
+
+    fn u8(&mut self) -> Result<u8, EtfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<usize, EtfError> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()) as usize)
+    }
+
+    fn u32(&mut self) -> Result<usize, EtfError> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn term(&mut self, depth: usize) -> Result<Value, EtfError> {
+        if depth > MAX_DEPTH {
+            return Err(EtfError::TooDeep);
+        }
+        let tag = self.u8()?;
+        Ok(match tag {
+            SMALL_INTEGER_EXT => Value::from(self.u8()?),
+            INTEGER_EXT => Value::from(i32::from_be_bytes(self.take(4)?.try_into().unwrap())),
+            NEW_FLOAT_EXT => {
+                let f = f64::from_be_bytes(self.take(8)?.try_into().unwrap());
+                Value::Number(Number::from_f64(f).ok_or(EtfError::InvalidFloat)?)
+            }
+            FLOAT_EXT => {
+                let raw = self.take(31)?;
+                let text = String::from_utf8_lossy(raw);
+                let f: f64 = text
+                    .trim_end_matches('\0')
+                    .trim()
+                    .parse()
+                    .map_err(|_| EtfError::InvalidFloat)?;
+                Value::Number(Number::from_f64(f).ok_or(EtfError::InvalidFloat)?)
+            }
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                let n = self.u16()?;
+                atom_value(self.take(n)?)
+            }
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let n = self.u8()? as usize;
+                atom_value(self.take(n)?)
+            }
+            BINARY_EXT => {
+                let n = self.u32()?;
+                Value::String(String::from_utf8_lossy(self.take(n)?).into_owned())
+            }
+            // Erlang charlists; clients only send these for short strings
+            STRING_EXT => {
+                let n = self.u16()?;
+                Value::String(String::from_utf8_lossy(self.take(n)?).into_owned())
+            }
+            NIL_EXT => Value::Array(Vec::new()),
+            LIST_EXT => {
+                let n = self.u32()?;
+                let items = self.items(n, depth)?;
+                if self.u8()? != NIL_EXT {
+                    return Err(EtfError::ImproperList);
+                }
+                Value::Array(items)
+            }
+            SMALL_TUPLE_EXT => {
+                let n = self.u8()? as usize;
+                Value::Array(self.items(n, depth)?)
+            }
+            LARGE_TUPLE_EXT => {
+                let n = self.u32()?;
+                Value::Array(self.items(n, depth)?)
+            }
+            SMALL_BIG_EXT => {
+                let n = self.u8()? as usize;
+                self.big(n)?
+            }
+            LARGE_BIG_EXT => {
+                let n = self.u32()?;
+                self.big(n)?
+            }
+            MAP_EXT => {
+                let n = self.u32()?;
+                let mut map = Map::new();
+                for _ in 0..n {
+                    let key = match self.term(depth + 1)? {
+                        Value::String(s) => s,
+                        Value::Number(n) => n.to_string(),
+                        Value::Bool(b) => b.to_string(),
+                        Value::Null => "nil".to_string(),
+                        _ => return Err(EtfError::InvalidKey),
+                    };
+                    map.insert(key, self.term(depth + 1)?);
+                }
+                Value::Object(map)
+            }
+            other => return Err(EtfError::UnsupportedTag(other)),
+        })
+    }
+
+    fn items(&mut self, n: usize, depth: usize) -> Result<Vec<Value>, EtfError> {
+        // Every element takes at least one byte, so a bogus count can't over-allocate
+        let mut items = Vec::with_capacity(n.min(self.bin.len() - self.pos));
+        for _ in 0..n {
+            items.push(self.term(depth + 1)?);
+        }
+        Ok(items)
+    }
+
+    fn big(&mut self, n: usize) -> Result<Value, EtfError> {
+        let negative = self.u8()? != 0;
+        let digits = self.take(n)?;
+        let significant = digits.iter().rposition(|&d| d != 0).map_or(0, |i| i + 1);
+        if significant <= 8 {
+            let magnitude = digits[..significant]
+                .iter()
+                .rev()
+                .fold(0u64, |acc, &d| (acc << 8) | d as u64);
+            if !negative {
+                return Ok(Value::from(magnitude));
+            }
+            if magnitude <= i64::MAX as u64 + 1 {
+                return Ok(Value::from((magnitude as i64).wrapping_neg()));
+            }
+        }
+        // Beyond 64 bits a JSON number can't hold the value losslessly; keep it as a decimal string
+        Ok(Value::String(big_to_decimal(
+            negative,
+            &digits[..significant],
+        )))
+    }
+}
+
+fn atom_value(name: &[u8]) -> Value {
+    match name {
+        b"nil" | b"null" => Value::Null,
+        b"true" => Value::Bool(true),
+        b"false" => Value::Bool(false),
+        other => Value::String(String::from_utf8_lossy(other).into_owned()),
+    }
+}
+
+fn big_to_decimal(negative: bool, le_digits: &[u8]) -> String {
+    // Repeated division of the base-256 magnitude by 10
+    let mut mag: Vec<u8> = le_digits.iter().rev().copied().collect();
+    let mut out = Vec::new();
+    while !mag.is_empty() {
+        let mut rem = 0u16;
+        for d in mag.iter_mut() {
+            let cur = (rem << 8) | *d as u16;
+            *d = (cur / 10) as u8;
+            rem = cur % 10;
+        }
+        out.push(b'0' + rem as u8);
+        let lead = mag.iter().position(|&d| d != 0).unwrap_or(mag.len());
+        mag.drain(..lead);
+    }
+    if out.is_empty() {
+        out.push(b'0');
+    }
+    if negative {
+        out.push(b'-');
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap()
+}
@@ -1,17 +1,110 @@
+use crate::privacy::PrivacyPolicy;
+use crate::protocol::RpcEvent;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 
+// Which transport an activity came in on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityOrigin {
+    Ipc,
+    Ws,
+    Rest,
+    Process,
+}
+
+impl ActivityOrigin {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActivityOrigin::Ipc => "ipc",
+            ActivityOrigin::Ws => "ws",
+            ActivityOrigin::Rest => "rest",
+            ActivityOrigin::Process => "process",
+        }
+    }
+}
+
+impl fmt::Display for ActivityOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ActivityOrigin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ipc" => Ok(ActivityOrigin::Ipc),
+            "ws" => Ok(ActivityOrigin::Ws),
+            "rest" => Ok(ActivityOrigin::Rest),
+            "process" => Ok(ActivityOrigin::Process),
+            _ => Err(format!("unknown activity origin {s:?}")),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum EventKind {
-    ActivityUpdate { socket_id: String, payload: Value },
-    Clear { socket_id: String },
+    ActivityUpdate {
+        socket_id: String,
+        origin: ActivityOrigin,
+        payload: Value,
+    },
+    Clear {
+        socket_id: String,
+    },
     PrivacyRefresh,
+    DetectablesUpdated {
+        count: usize,
+    },
+    // Targeted at the connection that owns the activity; delivered only if it subscribed
+    ActivityJoin {
+        socket_id: String,
+        secret: String,
+    },
+    ActivitySpectate {
+        socket_id: String,
+        secret: String,
+    },
+    ActivityJoinRequest {
+        socket_id: String,
+        user: Value,
+    },
+}
+
+impl EventKind {
+    // Target socket, RPC event and DISPATCH data for events forwarded to RPC clients
+    pub fn rpc_dispatch(&self) -> Option<(&str, RpcEvent, Value)> {
+        match self {
+            EventKind::ActivityJoin { socket_id, secret } => Some((
+                socket_id,
+                RpcEvent::ActivityJoin,
+                serde_json::json!({ "secret": secret }),
+            )),
+            EventKind::ActivitySpectate { socket_id, secret } => Some((
+                socket_id,
+                RpcEvent::ActivitySpectate,
+                serde_json::json!({ "secret": secret }),
+            )),
+            EventKind::ActivityJoinRequest { socket_id, user } => Some((
+                socket_id,
+                RpcEvent::ActivityJoinRequest,
+                serde_json::json!({ "user": user }),
+            )),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct EventBus {
     inner: Arc<RwLock<Vec<tokio::sync::mpsc::UnboundedSender<EventKind>>>>,
+    privacy: PrivacyPolicy,
 }
 
 impl Default for EventBus {
@@ -22,22 +115,35 @@ impl Default for EventBus {
 
 impl EventBus {
     pub fn new() -> Self {
+        Self::with_privacy(PrivacyPolicy::default())
+    }
+    // Every published event passes through the policy, whatever transport it came from
+    pub fn with_privacy(privacy: PrivacyPolicy) -> Self {
         Self {
             inner: Arc::new(RwLock::new(Vec::new())),
+            privacy,
         }
     }
+    pub fn privacy(&self) -> &PrivacyPolicy {
+        &self.privacy
+    }
     pub fn subscribe(&self) -> tokio::sync::mpsc::UnboundedReceiver<EventKind> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
-        self.inner.write().push(tx);
+        let mut list = self.inner.write();
+        // Receivers of finished connections are dropped; don't keep their senders around
+        list.retain(|s| !s.is_closed());
+        list.push(tx);
         rx
     }
     pub fn publish(&self, evt: EventKind) {
-        let list = self.inner.read();
-        for sub in list.iter() {
-            let _ = sub.send(evt.clone());
-        }
-        if let EventKind::ActivityUpdate { .. } = &evt {
-            crate::metrics::ACTIVITIES_SET.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        }
+        self.privacy.apply(evt, |evt| {
+            let list = self.inner.read();
+            for sub in list.iter() {
+                let _ = sub.send(evt.clone());
+            }
+            if let EventKind::ActivityUpdate { .. } = &evt {
+                crate::metrics::ACTIVITIES_SET.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            }
+        });
     }
 }
@@ -0,0 +1,61 @@
+use crate::image_cache::{check_public_url, is_public_ip};
+use once_cell::sync::OnceCell;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+static CLIENT: OnceCell<reqwest::Client> = OnceCell::new();
+
+// Built on first use and shared, so connections and TLS sessions are reused between requests.
+// Requests set their own total timeout on top of the connect timeout.
+pub(crate) fn client() -> Result<&'static reqwest::Client, reqwest::Error> {
+    CLIENT.get_or_try_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+    })
+}
+
+const MAX_REDIRECTS: usize = 5;
+
+static PUBLIC_CLIENT: OnceCell<reqwest::Client> = OnceCell::new();
+
+// For URLs taken from activities: host names only resolve to public addresses, and every
+// redirect hop is checked like the first URL. No proxy, as it would resolve names itself.
+pub(crate) fn public_client() -> Result<&'static reqwest::Client, reqwest::Error> {
+    PUBLIC_CLIENT.get_or_try_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .no_proxy()
+            .dns_resolver(std::sync::Arc::new(PublicResolver))
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if let Err(e) = check_public_url(attempt.url()) {
+                    attempt.error(e)
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()
+    })
+}
+
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|a| is_public_ip(a.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{host} has no public address").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
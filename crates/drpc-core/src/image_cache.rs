@@ -0,0 +1,409 @@
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tracing::{debug, warn};
+
+pub const DEFAULT_IMAGE_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 4 * 1024 * 1024;
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Error)]
+pub enum ImageCacheError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("unexpected status {0}")]
+    Status(u16),
+    #[error("unsupported content type {0:?}")]
+    ContentType(String),
+    #[error("image larger than {0} bytes")]
+    TooLarge(u64),
+    #[error("refusing to download {0}: only https URLs on public hosts are fetched")]
+    Blocked(String),
+    #[error("network feature disabled")]
+    NetworkDisabled,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageCacheOptions {
+    // Defaults to ~/.drpc/cache
+    pub dir: PathBuf,
+    // Total size kept on disk; least recently used images are evicted past it
+    pub max_bytes: u64,
+    pub max_image_bytes: u64,
+    // Also download over plain http and from loopback or private addresses, e.g. from a
+    // media server on the LAN. Off by default so activities can't make drpc probe the network.
+    pub allow_local: bool,
+}
+
+impl Default for ImageCacheOptions {
+    fn default() -> Self {
+        let home = std::env::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        Self {
+            dir: home.join(".drpc").join("cache"),
+            max_bytes: DEFAULT_IMAGE_CACHE_BYTES,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            allow_local: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedImage {
+    pub hash: String,
+    pub content_type: &'static str,
+    pub bytes: Vec<u8>,
+}
+
+struct Entry {
+    file: String,
+    size: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Index {
+    entries: HashMap<String, Entry>,
+    total: u64,
+    tick: u64,
+}
+
+impl Index {
+    fn insert(&mut self, hash: String, file: String, size: u64) {
+        self.tick += 1;
+        let entry = Entry {
+            file,
+            size,
+            last_used: self.tick,
+        };
+        if let Some(old) = self.entries.insert(hash, entry) {
+            self.total -= old.size;
+        }
+        self.total += size;
+    }
+
+    fn touch(&mut self, hash: &str) -> Option<String> {
+        self.tick += 1;
+        let tick = self.tick;
+        let e = self.entries.get_mut(hash)?;
+        e.last_used = tick;
+        Some(e.file.clone())
+    }
+
+    // Oldest first until the total fits; returns the files to delete
+    fn evict(&mut self, max_bytes: u64) -> Vec<String> {
+        let mut removed = Vec::new();
+        while self.total > max_bytes {
+            let Some(hash) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(h, _)| h.clone())
+            else {
+                break;
+            };
+            let e = self.entries.remove(&hash).unwrap();
+            self.total -= e.size;
+            removed.push(e.file);
+        }
+        removed
+    }
+}
+
+// Downloaded activity artwork, stored as `<dir>/<hash>.<ext>` and served back by hash
+pub struct ImageCache {
+    opts: ImageCacheOptions,
+    index: Mutex<Index>,
+    base_url: RwLock<Option<String>>,
+}
+
+impl ImageCache {
+    // Picks up files left by a previous run, most recently modified counting as most recently used
+    pub fn open(opts: ImageCacheOptions) -> Result<Self, ImageCacheError> {
+        std::fs::create_dir_all(&opts.dir)?;
+        let mut found = Vec::new();
+        for ent in std::fs::read_dir(&opts.dir)?.flatten() {
+            let name = ent.file_name().to_string_lossy().into_owned();
+            let Some((hash, ext)) = name.split_once('.') else {
+                continue;
+            };
+            if !is_hash(hash) || content_type_for(ext).is_none() {
+                continue;
+            }
+            let Ok(meta) = ent.metadata() else { continue };
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            found.push((modified, hash.to_string(), name, meta.len()));
+        }
+        found.sort();
+        let mut index = Index::default();
+        for (_, hash, file, size) in found {
+            index.insert(hash, file, size);
+        }
+        let stale = index.evict(opts.max_bytes);
+        let cache = Self {
+            opts,
+            index: Mutex::new(index),
+            base_url: RwLock::new(None),
+        };
+        cache.remove_files(&stale);
+        Ok(cache)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.opts.dir
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.index.lock().total
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.index.lock().entries.contains_key(hash)
+    }
+
+    // Where `/assets/{hash}` is served from, e.g. `http://127.0.0.1:3020`
+    pub fn set_base_url(&self, url: impl Into<String>) {
+        *self.base_url.write() = Some(url.into().trim_end_matches('/').to_string());
+    }
+
+    pub fn local_url(&self, hash: &str) -> Option<String> {
+        let base = self.base_url.read().clone()?;
+        Some(format!("{base}/assets/{hash}"))
+    }
+
+    pub fn get(&self, hash: &str) -> Option<CachedImage> {
+        if !is_hash(hash) {
+            return None;
+        }
+        let file = self.index.lock().touch(hash)?;
+        let path = self.opts.dir.join(&file);
+        let bytes = match std::fs::read(&path) {
+            Ok(b) => b,
+            Err(e) => {
+                warn!(path=%path.display(), error=%e, "cached image unreadable");
+                let mut index = self.index.lock();
+                if let Some(e) = index.entries.remove(hash) {
+                    index.total -= e.size;
+                }
+                return None;
+            }
+        };
+        // Keeps the on-disk recency in step for the next start
+        if let Ok(f) = std::fs::File::options().append(true).open(&path) {
+            let _ = f.set_modified(SystemTime::now());
+        }
+        let ext = file.rsplit_once('.').map_or("", |(_, e)| e);
+        Some(CachedImage {
+            hash: hash.to_string(),
+            content_type: content_type_for(ext).unwrap_or("application/octet-stream"),
+            bytes,
+        })
+    }
+
+    // Returns the hash of `url`, downloading it first unless it is already cached
+    pub async fn fetch(&self, url: &str) -> Result<String, ImageCacheError> {
+        let hash = url_hash(url);
+        if self.index.lock().touch(&hash).is_some() {
+            return Ok(hash);
+        }
+        let (bytes, ext) = self.download(url).await?;
+        let file = format!("{hash}.{ext}");
+        let path = self.opts.dir.join(&file);
+        // Unique per download, so concurrent fetches of one URL never share a temp file
+        static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+        let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+        let tmp = self
+            .opts
+            .dir
+            .join(format!(".{file}.tmp-{}-{seq}", std::process::id()));
+        std::fs::write(&tmp, &bytes)?;
+        if let Err(e) = std::fs::rename(&tmp, &path) {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        let stale = {
+            let mut index = self.index.lock();
+            index.insert(hash.clone(), file, bytes.len() as u64);
+            index.evict(self.opts.max_bytes)
+        };
+        self.remove_files(&stale);
+        debug!(url, hash, size = bytes.len(), "cached image");
+        Ok(hash)
+    }
+
+    // Local URL for `url` if it is already cached and something serves the cache
+    pub fn cached_url(&self, url: &str) -> Option<String> {
+        self.base_url.read().as_ref()?;
+        let hash = url_hash(url);
+        self.index.lock().touch(&hash)?;
+        self.local_url(&hash)
+    }
+
+    // Whether `localize` would have anything to serve the result from
+    pub fn is_served(&self) -> bool {
+        self.base_url.read().is_some()
+    }
+
+    // Whether `url` may be downloaded at all
+    pub fn accepts(&self, url: &str) -> bool {
+        self.opts.allow_local
+            || reqwest::Url::parse(url).is_ok_and(|u| check_public_url(&u).is_ok())
+    }
+
+    // Local URL for `url`, or None when it can't be cached or nothing serves the cache yet
+    pub async fn localize(&self, url: &str) -> Option<String> {
+        self.base_url.read().as_ref()?;
+        match self.fetch(url).await {
+            Ok(hash) => self.local_url(&hash),
+            Err(e) => {
+                debug!(url, error=%e, "image not cached");
+                None
+            }
+        }
+    }
+
+    fn remove_files(&self, files: &[String]) {
+        for file in files {
+            let _ = std::fs::remove_file(self.opts.dir.join(file));
+        }
+    }
+
+    #[cfg(feature = "network")]
+    async fn download(&self, url: &str) -> Result<(Vec<u8>, &'static str), ImageCacheError> {
+        let limit = self.opts.max_image_bytes;
+        let client = if self.opts.allow_local {
+            crate::http::client()?
+        } else {
+            let parsed =
+                reqwest::Url::parse(url).map_err(|_| ImageCacheError::Blocked(url.to_string()))?;
+            check_public_url(&parsed)?;
+            crate::http::public_client()?
+        };
+        let mut resp = client.get(url).timeout(DOWNLOAD_TIMEOUT).send().await?;
+        if !resp.status().is_success() {
+            return Err(ImageCacheError::Status(resp.status().as_u16()));
+        }
+        let content_type = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let ext = extension_for(&content_type)
+            .ok_or_else(|| ImageCacheError::ContentType(content_type.clone()))?;
+        if resp.content_length().is_some_and(|n| n > limit) {
+            return Err(ImageCacheError::TooLarge(limit));
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > limit {
+                return Err(ImageCacheError::TooLarge(limit));
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok((bytes, ext))
+    }
+
+    #[cfg(not(feature = "network"))]
+    async fn download(&self, _url: &str) -> Result<(Vec<u8>, &'static str), ImageCacheError> {
+        Err(ImageCacheError::NetworkDisabled)
+    }
+}
+
+// First 128 bits of the URL's SHA-256, so a page can't craft URLs that collide with cached
+// images; stable across builds so cached files stay addressable after an upgrade
+pub fn url_hash(url: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(url.as_bytes())[..HASH_BYTES]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+const HASH_BYTES: usize = 16;
+
+fn is_hash(s: &str) -> bool {
+    s.len() == HASH_BYTES * 2 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// `https` with a host that isn't an address outside the public ranges. Host names are
+// checked again when they resolve, since their addresses aren't known here.
+pub fn check_public_url(url: &reqwest::Url) -> Result<(), ImageCacheError> {
+    let blocked = || ImageCacheError::Blocked(url.to_string());
+    if url.scheme() != "https" {
+        return Err(blocked());
+    }
+    let Some(host) = url.host_str() else {
+        return Err(blocked());
+    };
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) if !is_public_ip(ip) => Err(blocked()),
+        _ => Ok(()),
+    }
+}
+
+// False for loopback, private, link-local, shared, documentation, multicast and reserved
+// ranges, including IPv4 addresses mapped into IPv6
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || a >= 240
+                // 100.64.0.0/10 carrier-grade NAT, 198.18.0.0/15 benchmarking
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b))
+                || (a == 192 && b == 0 && v4.octets()[2] == 0))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(v4.into());
+            }
+            let first = v6.segments()[0];
+            !(v6.is_unspecified()
+                || v6.is_loopback()
+                || v6.is_multicast()
+                // fc00::/7 unique local, fe80::/10 link-local, 2001:db8::/32 documentation
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+const IMAGE_TYPES: [(&str, &str); 4] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+];
+
+fn content_type_for(ext: &str) -> Option<&'static str> {
+    IMAGE_TYPES.iter().find(|(e, _)| *e == ext).map(|(_, t)| *t)
+}
+
+#[cfg_attr(not(feature = "network"), allow(dead_code))]
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim();
+    IMAGE_TYPES
+        .iter()
+        .find(|(_, t)| t.eq_ignore_ascii_case(mime))
+        .map(|(e, _)| *e)
+}
@@ -1,15 +1,47 @@
+pub mod activity_normalize;
 pub mod activity_registry;
+pub mod activity_validation;
+pub mod assets;
+pub mod auth;
+pub mod codec;
 pub mod detectables;
+pub mod detectables_cache;
+pub mod detectables_local;
+pub mod detectables_refresh;
+pub mod detectables_source;
+#[cfg(feature = "etf")]
+pub mod etf;
 pub mod event;
 pub mod frame;
+pub mod glob;
+#[cfg(feature = "network")]
+mod http;
+pub mod image_cache;
 pub mod metrics;
+pub mod privacy;
+pub mod privacy_rules;
 pub mod protocol;
+pub mod subscriptions;
 
+pub use activity_normalize::*;
 pub use activity_registry::*;
+pub use activity_validation::*;
+pub use assets::*;
+pub use auth::*;
+pub use codec::*;
 pub use detectables::*;
+pub use detectables_cache::*;
+pub use detectables_local::*;
+pub use detectables_refresh::*;
+pub use detectables_source::*;
 pub use event::*;
 pub use frame::*;
+pub use glob::*;
+pub use image_cache::*;
+pub use privacy::*;
+pub use privacy_rules::*;
 pub use protocol::*;
+pub use subscriptions::*;
 
 #[cfg(feature = "test-helpers")]
 pub mod test_helpers {
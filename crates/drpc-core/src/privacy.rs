@@ -0,0 +1,344 @@
+use crate::event::{ActivityOrigin, EventKind};
+use crate::privacy_rules::{CompiledRule, PrivacyRule, RuleAction};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PrivacyError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid config: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("invalid config: {0}")]
+    Edit(#[from] toml_edit::TomlError),
+    #[error("failed to serialize config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("rule {index}: invalid {field}: {message}")]
+    InvalidRule {
+        index: usize,
+        field: &'static str,
+        message: String,
+    },
+}
+
+// Rules are tried in order and the first match decides. Activities no rule matches fall back
+// to the name lists: case-insensitive substrings where deny wins, and with an allow list only
+// matching names pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacySettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deny: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<PrivacyRule>,
+}
+
+impl PrivacySettings {
+    pub fn allows(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        let hit = |list: &Vec<String>| list.iter().any(|p| name.contains(&p.to_lowercase()));
+        if self.deny.as_ref().is_some_and(hit) {
+            return false;
+        }
+        self.allow.as_ref().is_none_or(hit)
+    }
+}
+
+// How the policy treats one current activity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PrivacyVerdict {
+    pub socket_id: String,
+    pub origin: ActivityOrigin,
+    // Index of the deciding rule; `None` when the name lists decided
+    pub rule: Option<usize>,
+    pub action: RuleAction,
+    // What subscribers get, `None` when hidden
+    pub activity: Option<Value>,
+}
+
+#[derive(Default)]
+struct Compiled {
+    settings: PrivacySettings,
+    rules: Vec<CompiledRule>,
+}
+
+impl Compiled {
+    fn new(settings: PrivacySettings) -> Result<Self, PrivacyError> {
+        let rules = settings
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                CompiledRule::new(rule.clone()).map_err(|(field, message)| {
+                    PrivacyError::InvalidRule {
+                        index,
+                        field,
+                        message,
+                    }
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { settings, rules })
+    }
+
+    fn evaluate(
+        &self,
+        origin: ActivityOrigin,
+        activity: &Value,
+    ) -> (Option<usize>, RuleAction, Option<Value>) {
+        let decided = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, r)| r.matches(origin, activity));
+        let (index, action) = match decided {
+            Some((i, r)) => (Some(i), r.rule().action),
+            None => {
+                let name = activity.get("name").and_then(Value::as_str).unwrap_or("");
+                let action = if self.settings.allows(name) {
+                    RuleAction::Allow
+                } else {
+                    RuleAction::Deny
+                };
+                (None, action)
+            }
+        };
+        let shown = match action {
+            RuleAction::Allow => Some(activity.clone()),
+            RuleAction::Deny => None,
+            RuleAction::Redact => {
+                let mut redacted = activity.clone();
+                if let Some((_, r)) = decided {
+                    r.rule().redact.apply(&mut redacted);
+                }
+                Some(redacted)
+            }
+        };
+        (index, action, shown)
+    }
+}
+
+// Last update per socket as published, and what the policy let through
+struct Tracked {
+    origin: ActivityOrigin,
+    original: Value,
+    shown: Option<Value>,
+}
+
+#[derive(Default)]
+struct PolicyState {
+    compiled: Compiled,
+    config_path: Option<PathBuf>,
+    activities: HashMap<String, Tracked>,
+}
+
+impl PolicyState {
+    fn sorted(&self) -> Vec<(&String, &Tracked)> {
+        let mut list: Vec<_> = self.activities.iter().collect();
+        list.sort_by(|a, b| a.0.cmp(b.0));
+        list
+    }
+}
+
+// Shared by the EventBus, which runs every event through `apply` before delivering it
+#[derive(Clone, Default)]
+pub struct PrivacyPolicy {
+    inner: Arc<RwLock<PolicyState>>,
+}
+
+impl PrivacyPolicy {
+    pub fn new(settings: PrivacySettings) -> Result<Self, PrivacyError> {
+        let policy = Self::default();
+        policy.inner.write().compiled = Compiled::new(settings)?;
+        Ok(policy)
+    }
+
+    // Updates are written to the `[privacy]` table of this TOML file
+    pub fn with_config_path(self, path: impl Into<PathBuf>) -> Self {
+        self.inner.write().config_path = Some(path.into());
+        self
+    }
+
+    pub fn settings(&self) -> PrivacySettings {
+        self.inner.read().compiled.settings.clone()
+    }
+
+    // Invalid rules are rejected and nothing changes. Otherwise the settings take effect
+    // immediately, and an error only reports that persisting failed; publish `PrivacyRefresh`
+    // to apply them to current activities.
+    pub fn set(&self, settings: PrivacySettings) -> Result<(), PrivacyError> {
+        let compiled = Compiled::new(settings.clone())?;
+        let path = {
+            let mut st = self.inner.write();
+            st.compiled = compiled;
+            st.config_path.clone()
+        };
+        match path {
+            Some(p) => save_privacy(&p, &settings),
+            None => Ok(()),
+        }
+    }
+
+    // Dry run over the current activities, with `candidate` instead of the active settings
+    // when given
+    pub fn preview(
+        &self,
+        candidate: Option<PrivacySettings>,
+    ) -> Result<Vec<PrivacyVerdict>, PrivacyError> {
+        let candidate = candidate.map(Compiled::new).transpose()?;
+        let st = self.inner.read();
+        let compiled = candidate.as_ref().unwrap_or(&st.compiled);
+        Ok(st
+            .sorted()
+            .into_iter()
+            .map(|(socket_id, t)| {
+                let (rule, action, activity) = compiled.evaluate(t.origin, &t.original);
+                PrivacyVerdict {
+                    socket_id: socket_id.clone(),
+                    origin: t.origin,
+                    rule,
+                    action,
+                    activity,
+                }
+            })
+            .collect())
+    }
+
+    // Updates go out redacted, or not at all when denied; a denied update for a socket whose
+    // activity was shown becomes a clear. A refresh re-evaluates every current activity.
+    // `emit` runs under the lock so concurrent publishers can't reorder a clear and an update.
+    pub(crate) fn apply(&self, evt: EventKind, mut emit: impl FnMut(EventKind)) {
+        let mut st = self.inner.write();
+        let st = &mut *st;
+        match evt {
+            EventKind::ActivityUpdate {
+                socket_id,
+                origin,
+                payload,
+            } => {
+                let (_, _, shown) = st.compiled.evaluate(origin, &payload);
+                let was_shown = st
+                    .activities
+                    .get(&socket_id)
+                    .is_some_and(|t| t.shown.is_some());
+                match &shown {
+                    Some(activity) => emit(EventKind::ActivityUpdate {
+                        socket_id: socket_id.clone(),
+                        origin,
+                        payload: activity.clone(),
+                    }),
+                    None if was_shown => emit(EventKind::Clear {
+                        socket_id: socket_id.clone(),
+                    }),
+                    None => {}
+                }
+                st.activities.insert(
+                    socket_id,
+                    Tracked {
+                        origin,
+                        original: payload,
+                        shown,
+                    },
+                );
+            }
+            EventKind::Clear { socket_id } => {
+                st.activities.remove(&socket_id);
+                emit(EventKind::Clear { socket_id });
+            }
+            EventKind::PrivacyRefresh => {
+                let mut changed = Vec::new();
+                for (socket_id, t) in st.sorted() {
+                    let (_, _, shown) = st.compiled.evaluate(t.origin, &t.original);
+                    if shown != t.shown {
+                        changed.push((socket_id.clone(), shown));
+                    }
+                }
+                for (socket_id, shown) in changed {
+                    let Some(t) = st.activities.get_mut(&socket_id) else {
+                        continue;
+                    };
+                    t.shown = shown.clone();
+                    match shown {
+                        Some(payload) => emit(EventKind::ActivityUpdate {
+                            socket_id,
+                            origin: t.origin,
+                            payload,
+                        }),
+                        None => emit(EventKind::Clear { socket_id }),
+                    }
+                }
+                emit(EventKind::PrivacyRefresh);
+            }
+            other => emit(other),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct PrivacyFile {
+    #[serde(default)]
+    privacy: PrivacySettings,
+}
+
+pub fn load_privacy(path: &Path) -> Result<PrivacySettings, PrivacyError> {
+    match std::fs::read_to_string(path) {
+        Ok(data) => Ok(toml::from_str::<PrivacyFile>(&data)?.privacy),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(PrivacySettings::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Rewrites only the `[privacy]` table; other settings in the file are kept
+pub fn save_privacy(path: &Path, settings: &PrivacySettings) -> Result<(), PrivacyError> {
+    let mut doc: toml_edit::DocumentMut = match std::fs::read_to_string(path) {
+        Ok(data) => data.parse()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => toml_edit::DocumentMut::new(),
+        Err(e) => return Err(e.into()),
+    };
+    // Only `[privacy]` is replaced; the rest of the file keeps its comments and layout
+    let mut section = toml::Table::new();
+    section.insert(
+        "privacy".into(),
+        toml::Value::try_from(settings).map_err(PrivacyError::Serialize)?,
+    );
+    let mut rendered: toml_edit::DocumentMut = toml::to_string(&section)?.parse()?;
+    let mut privacy = rendered.remove("privacy").unwrap_or_default();
+    if let Some(table) = privacy.as_table_mut() {
+        clear_positions(table);
+        match doc.get("privacy").and_then(toml_edit::Item::as_table) {
+            Some(old) => {
+                table.set_position(old.position());
+                *table.decor_mut() = old.decor().clone();
+            }
+            None if !doc.is_empty() => table.decor_mut().set_prefix("\n"),
+            None => {}
+        }
+    }
+    doc.insert("privacy", privacy);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("toml.tmp-{}", std::process::id()));
+    std::fs::write(&tmp, doc.to_string())?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// Tables without a position are written after the table before them, so the new section's
+// subtables stay together wherever `[privacy]` sits in the file
+fn clear_positions(table: &mut toml_edit::Table) {
+    table.set_position(None);
+    for (_, item) in table.iter_mut() {
+        match item {
+            toml_edit::Item::Table(t) => clear_positions(t),
+            toml_edit::Item::ArrayOfTables(arr) => arr.iter_mut().for_each(clear_positions),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::event::ActivityOrigin;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Every given field has to match; a rule without any matches everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleMatch {
+    // RPC client id, which IPC and WebSocket activities carry as `application_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ActivityOrigin>,
+    // Regex on the executable the process scanner matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executable: Option<String>,
+    // Regex on the activity name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Allow,
+    Deny,
+    Redact,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Redaction {
+    #[serde(skip_serializing_if = "is_false")]
+    pub hide_details: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub hide_state: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_name: Option<String>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub strip_buttons: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub strip_secrets: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub remove_party: bool,
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl Redaction {
+    pub fn apply(&self, activity: &mut Value) {
+        let Some(obj) = activity.as_object_mut() else {
+            return;
+        };
+        let mut drop = |keys: &[&str]| {
+            for k in keys {
+                obj.remove(*k);
+            }
+        };
+        if self.hide_details {
+            drop(&["details", "details_url"]);
+        }
+        if self.hide_state {
+            drop(&["state", "state_url"]);
+        }
+        if self.strip_buttons {
+            drop(&["buttons"]);
+        }
+        if self.strip_secrets {
+            drop(&["secrets"]);
+        }
+        if self.remove_party {
+            drop(&["party"]);
+        }
+        // A replaced name would still leak through anything else naming the game: its
+        // application id, its art and hover text, and the executable the scanner recorded
+        if self.replace_name.is_some() {
+            drop(&["application_id", "assets"]);
+        }
+        if let Some(meta) = obj.get_mut("metadata").and_then(Value::as_object_mut) {
+            if self.strip_buttons {
+                meta.remove("button_urls");
+            }
+            if self.replace_name.is_some() {
+                meta.remove("executable");
+            }
+        }
+        if let Some(name) = &self.replace_name {
+            obj.insert("name".into(), Value::String(name.clone()));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyRule {
+    #[serde(default, rename = "match")]
+    pub matcher: RuleMatch,
+    pub action: RuleAction,
+    // Only used by `redact` rules
+    #[serde(default, skip_serializing_if = "is_default_redaction")]
+    pub redact: Redaction,
+}
+
+fn is_default_redaction(r: &Redaction) -> bool {
+    *r == Redaction::default()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CompiledRule {
+    rule: PrivacyRule,
+    name: Option<Regex>,
+    executable: Option<Regex>,
+}
+
+impl CompiledRule {
+    // The error names the offending field for REST's error list
+    pub(crate) fn new(rule: PrivacyRule) -> Result<Self, (&'static str, String)> {
+        let compile = |field, pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| (field, e.to_string()))
+        };
+        Ok(Self {
+            name: compile("match.name", &rule.matcher.name)?,
+            executable: compile("match.executable", &rule.matcher.executable)?,
+            rule,
+        })
+    }
+
+    pub(crate) fn rule(&self) -> &PrivacyRule {
+        &self.rule
+    }
+
+    pub(crate) fn matches(&self, origin: ActivityOrigin, activity: &Value) -> bool {
+        let m = &self.rule.matcher;
+        let str_field =
+            |v: &Value, key: &str| v.get(key).and_then(Value::as_str).map(str::to_owned);
+        let app_id = str_field(activity, "application_id");
+        let is_rpc = matches!(origin, ActivityOrigin::Ipc | ActivityOrigin::Ws);
+        let executable = activity
+            .get("metadata")
+            .and_then(|meta| str_field(meta, "executable"));
+        let name = str_field(activity, "name").unwrap_or_default();
+        m.client_id
+            .as_ref()
+            .is_none_or(|id| is_rpc && app_id.as_ref() == Some(id))
+            && m.application_id
+                .as_ref()
+                .is_none_or(|id| app_id.as_ref() == Some(id))
+            && m.origin.is_none_or(|o| o == origin)
+            && self
+                .executable
+                .as_ref()
+                .is_none_or(|re| executable.as_deref().is_some_and(|e| re.is_match(e)))
+            && self.name.as_ref().is_none_or(|re| re.is_match(&name))
+    }
+}
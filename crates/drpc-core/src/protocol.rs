@@ -1,5 +1,5 @@
+use crate::activity_validation::{ActivityValidationError, FieldError};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 // IPC op codes (subset for stage 0-4)
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -12,39 +12,524 @@ pub enum IpcOp {
     Pong = 4,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
-pub enum RpcCommand {
-    Dispatch,
-    SetActivity,
-    InviteBrowser,
-    GuildTemplateBrowser,
-    DeepLink,
-    ConnectionsCallback,
+// Wire names are SCREAMING_SNAKE_CASE; anything unrecognised is kept verbatim in `Unknown`
+macro_rules! wire_enum {
+    ($name:ident { $($variant:ident => $wire:literal,)* }) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($variant,)*
+            Unknown(String),
+        }
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $($name::$variant => $wire,)*
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+
+        // Case-insensitive, matching what clients have historically gotten away with
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                let upper = s.to_ascii_uppercase();
+                match upper.as_str() {
+                    $($wire => $name::$variant,)*
+                    _ => $name::Unknown(upper),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                let s = std::borrow::Cow::<'de, str>::deserialize(d)?;
+                Ok($name::from(s.as_ref()))
+            }
+        }
+    };
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+wire_enum!(RpcCommand {
+    Dispatch => "DISPATCH",
+    Authorize => "AUTHORIZE",
+    Authenticate => "AUTHENTICATE",
+    GetGuild => "GET_GUILD",
+    GetGuilds => "GET_GUILDS",
+    GetChannel => "GET_CHANNEL",
+    GetChannels => "GET_CHANNELS",
+    Subscribe => "SUBSCRIBE",
+    Unsubscribe => "UNSUBSCRIBE",
+    SetUserVoiceSettings => "SET_USER_VOICE_SETTINGS",
+    SelectVoiceChannel => "SELECT_VOICE_CHANNEL",
+    GetSelectedVoiceChannel => "GET_SELECTED_VOICE_CHANNEL",
+    SelectTextChannel => "SELECT_TEXT_CHANNEL",
+    GetVoiceSettings => "GET_VOICE_SETTINGS",
+    SetVoiceSettings => "SET_VOICE_SETTINGS",
+    SetCertifiedDevices => "SET_CERTIFIED_DEVICES",
+    SetActivity => "SET_ACTIVITY",
+    SendActivityJoinInvite => "SEND_ACTIVITY_JOIN_INVITE",
+    CloseActivityRequest => "CLOSE_ACTIVITY_REQUEST",
+    InviteBrowser => "INVITE_BROWSER",
+    GuildTemplateBrowser => "GUILD_TEMPLATE_BROWSER",
+    DeepLink => "DEEP_LINK",
+    ConnectionsCallback => "CONNECTIONS_CALLBACK",
+    Ping => "PING",
+});
+
+impl Default for RpcCommand {
+    fn default() -> Self {
+        RpcCommand::Unknown(String::new())
+    }
+}
+
+wire_enum!(RpcEvent {
+    Ready => "READY",
+    Error => "ERROR",
+    GuildStatus => "GUILD_STATUS",
+    GuildCreate => "GUILD_CREATE",
+    ChannelCreate => "CHANNEL_CREATE",
+    VoiceChannelSelect => "VOICE_CHANNEL_SELECT",
+    VoiceStateCreate => "VOICE_STATE_CREATE",
+    VoiceStateUpdate => "VOICE_STATE_UPDATE",
+    VoiceStateDelete => "VOICE_STATE_DELETE",
+    VoiceSettingsUpdate => "VOICE_SETTINGS_UPDATE",
+    VoiceConnectionStatus => "VOICE_CONNECTION_STATUS",
+    SpeakingStart => "SPEAKING_START",
+    SpeakingStop => "SPEAKING_STOP",
+    MessageCreate => "MESSAGE_CREATE",
+    MessageUpdate => "MESSAGE_UPDATE",
+    MessageDelete => "MESSAGE_DELETE",
+    NotificationCreate => "NOTIFICATION_CREATE",
+    ActivityJoin => "ACTIVITY_JOIN",
+    ActivitySpectate => "ACTIVITY_SPECTATE",
+    ActivityJoinRequest => "ACTIVITY_JOIN_REQUEST",
+    // drpc-specific replies
+    ActivityUpdate => "ACTIVITY_UPDATE",
+    Ack => "ACK",
+    Pong => "PONG",
+});
+
+impl RpcEvent {
+    // Events a client may SUBSCRIBE to; the rest are replies drpc sends on its own
+    pub fn is_subscribable(&self) -> bool {
+        !matches!(
+            self,
+            RpcEvent::Ready
+                | RpcEvent::Error
+                | RpcEvent::ActivityUpdate
+                | RpcEvent::Ack
+                | RpcEvent::Pong
+                | RpcEvent::Unknown(_)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IncomingFrame {
+    #[serde(default)]
     pub cmd: RpcCommand,
     #[serde(default)]
     pub args: serde_json::Value,
-    #[serde(default)]
-    pub nonce: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evt: Option<RpcEvent>,
+    #[serde(default, deserialize_with = "lenient_nonce")]
+    pub nonce: Option<String>,
+}
+
+impl IncomingFrame {
+    // Never fails: a malformed frame becomes an Unknown command so it can still be answered
+    pub fn from_value(v: serde_json::Value) -> Self {
+        let nonce = lenient_nonce(v.get("nonce").cloned().unwrap_or_default()).unwrap_or(None);
+        serde_json::from_value(v).unwrap_or(IncomingFrame {
+            nonce,
+            ..Default::default()
+        })
+    }
+
+    // ERROR reply to this frame, echoing its cmd and nonce
+    pub fn error(&self, code: RpcErrorCode, message: impl Into<String>) -> OutgoingFrame {
+        OutgoingFrame::error(self.cmd.clone(), code, message, self.nonce.clone())
+    }
+
+    pub fn args<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        match &self.args {
+            serde_json::Value::Null => serde_json::from_value(serde_json::json!({})),
+            a => T::deserialize(a),
+        }
+    }
+}
+
+// Clients send string nonces, but numbers turn up too and are echoed back as strings
+fn lenient_nonce<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match serde_json::Value::deserialize(d)? {
+        serde_json::Value::String(s) => Some(s),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutgoingFrame {
     pub cmd: RpcCommand,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub evt: Option<String>,
+    pub evt: Option<RpcEvent>,
     #[serde(default)]
     pub data: serde_json::Value,
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub nonce: Option<Uuid>,
+    pub nonce: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pid: Option<u32>,
 }
 
+impl OutgoingFrame {
+    pub fn dispatch(evt: RpcEvent, data: impl Serialize) -> Self {
+        Self {
+            cmd: RpcCommand::Dispatch,
+            evt: Some(evt),
+            data: serde_json::to_value(data).unwrap_or_default(),
+            nonce: None,
+            pid: None,
+        }
+    }
+
+    // Command response without an event, e.g. the SET_ACTIVITY echo
+    pub fn reply(cmd: RpcCommand, data: impl Serialize, nonce: Option<String>) -> Self {
+        Self {
+            cmd,
+            evt: None,
+            data: serde_json::to_value(data).unwrap_or_default(),
+            nonce,
+            pid: None,
+        }
+    }
+
+    pub fn error(
+        cmd: RpcCommand,
+        code: RpcErrorCode,
+        message: impl Into<String>,
+        nonce: Option<String>,
+    ) -> Self {
+        Self {
+            evt: Some(RpcEvent::Error),
+            ..Self::reply(cmd, ErrorData::new(code, message), nonce)
+        }
+    }
+
+    // INVALID_PAYLOAD error listing each offending activity field
+    pub fn invalid_activity(
+        cmd: RpcCommand,
+        err: &ActivityValidationError,
+        nonce: Option<String>,
+    ) -> Self {
+        let data = ErrorData {
+            errors: err.errors.clone(),
+            ..ErrorData::new(
+                RpcErrorCode::InvalidPayload,
+                format!("Invalid payload: {err}"),
+            )
+        };
+        Self {
+            evt: Some(RpcEvent::Error),
+            ..Self::reply(cmd, data, nonce)
+        }
+    }
+
+    pub fn with_evt(mut self, evt: RpcEvent) -> Self {
+        self.evt = Some(evt);
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: Option<String>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    pub fn with_pid(mut self, pid: Option<u32>) -> Self {
+        self.pid = pid;
+        self
+    }
+
+    pub fn to_value(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+// RPC error codes, sent as `data.code` on ERROR frames. Numbered as in Discord's table
+// (https://discord.com/developers/docs/topics/opcodes-and-status-codes#rpc-rpc-error-codes)
+// rather than the shorthand drpc used before, since clients written against Discord match on
+// these: an unknown command is 4002 (was 4000), a payload Discord would reject is 4000 (the
+// button limit was 4002), and 4006 is InvalidPermissions, which Discord also sends for
+// commands that need an authenticated connection; there is no separate "not authenticated".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
+#[repr(u32)]
+pub enum RpcErrorCode {
+    UnknownError = 1000,
+    InvalidPayload = 4000,
+    InvalidCommand = 4002,
+    InvalidGuild = 4003,
+    InvalidEvent = 4004,
+    InvalidChannel = 4005,
+    InvalidPermissions = 4006,
+    InvalidClientId = 4007,
+    InvalidOrigin = 4008,
+    InvalidToken = 4009,
+    InvalidUser = 4010,
+    OAuth2Error = 5000,
+    SelectChannelTimedOut = 5001,
+    GetGuildTimedOut = 5002,
+    SelectVoiceForceRequired = 5003,
+    CaptureShortcutAlreadyListening = 5004,
+}
+
+impl From<RpcErrorCode> for u32 {
+    fn from(c: RpcErrorCode) -> u32 {
+        c as u32
+    }
+}
+
+impl TryFrom<u32> for RpcErrorCode {
+    type Error = String;
+
+    fn try_from(v: u32) -> Result<Self, Self::Error> {
+        use RpcErrorCode::*;
+        Ok(match v {
+            1000 => UnknownError,
+            4000 => InvalidPayload,
+            4002 => InvalidCommand,
+            4003 => InvalidGuild,
+            4004 => InvalidEvent,
+            4005 => InvalidChannel,
+            4006 => InvalidPermissions,
+            4007 => InvalidClientId,
+            4008 => InvalidOrigin,
+            4009 => InvalidToken,
+            4010 => InvalidUser,
+            5000 => OAuth2Error,
+            5001 => SelectChannelTimedOut,
+            5002 => GetGuildTimedOut,
+            5003 => SelectVoiceForceRequired,
+            5004 => CaptureShortcutAlreadyListening,
+            other => return Err(format!("unknown rpc error code {other}")),
+        })
+    }
+}
+
+// WebSocket close codes Discord uses when it drops an RPC connection, 4000-4005 as in
+// https://discord.com/developers/docs/topics/opcodes-and-status-codes#rpc-rpc-close-event-codes.
+// Rejected handshakes are upgraded and closed with one of these instead of an HTTP error status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u16", try_from = "u16")]
+#[repr(u16)]
+pub enum RpcCloseCode {
+    InvalidClientId = 4000,
+    InvalidOrigin = 4001,
+    RateLimited = 4002,
+    TokenRevoked = 4003,
+    InvalidVersion = 4004,
+    InvalidEncoding = 4005,
+}
+
+impl RpcCloseCode {
+    pub fn reason(self) -> &'static str {
+        match self {
+            RpcCloseCode::InvalidClientId => "Invalid Client ID",
+            RpcCloseCode::InvalidOrigin => "Invalid Origin",
+            RpcCloseCode::RateLimited => "Rate Limited",
+            RpcCloseCode::TokenRevoked => "Token Revoked",
+            RpcCloseCode::InvalidVersion => "Invalid Version",
+            RpcCloseCode::InvalidEncoding => "Invalid Encoding",
+        }
+    }
+}
+
+impl From<RpcCloseCode> for u16 {
+    fn from(c: RpcCloseCode) -> u16 {
+        c as u16
+    }
+}
+
+impl TryFrom<u16> for RpcCloseCode {
+    type Error = String;
+
+    fn try_from(v: u16) -> Result<Self, Self::Error> {
+        use RpcCloseCode::*;
+        Ok(match v {
+            4000 => InvalidClientId,
+            4001 => InvalidOrigin,
+            4002 => RateLimited,
+            4003 => TokenRevoked,
+            4004 => InvalidVersion,
+            4005 => InvalidEncoding,
+            other => return Err(format!("unknown rpc close code {other}")),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ErrorData {
+    pub code: RpcErrorCode,
+    pub message: String,
+    // Field-level detail for payload validation failures
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl ErrorData {
+    pub fn new(code: RpcErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+// ---------------- Command Args ----------------
+// Fields Discord marks required are still Options here so transports can report what's missing.
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthorizeArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthenticateArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetGuildArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetChannelArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GetChannelsArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SubscribeArgs {
+    // drpc clients name the event here; Discord proper sends it as the frame's `evt`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<RpcEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SelectVoiceChannelArgs {
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub navigate: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SelectTextChannelArgs {
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetUserVoiceSettingsArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pan: Option<serde_json::Value>,
+}
+
+// Voice settings are a deep tree drpc never interprets; kept as raw JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VoiceSettingsArgs {
+    #[serde(flatten)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetCertifiedDevicesArgs {
+    #[serde(default)]
+    pub devices: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetActivityArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    // None clears the activity
+    #[serde(default)]
+    pub activity: Option<Activity>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityUserArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+}
+
+pub type SendActivityJoinInviteArgs = ActivityUserArgs;
+pub type CloseActivityRequestArgs = ActivityUserArgs;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InviteCodeArgs {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+pub type InviteBrowserArgs = InviteCodeArgs;
+pub type GuildTemplateBrowserArgs = InviteCodeArgs;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeepLinkArgs {
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReadyEvent {
     pub v: u8,
@@ -92,6 +577,70 @@ impl Default for MockUser {
 
 // ---------------- Activity Models ----------------
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+#[repr(u8)]
+pub enum ActivityType {
+    Playing = 0,
+    Streaming = 1,
+    Listening = 2,
+    Watching = 3,
+    Custom = 4,
+    Competing = 5,
+}
+
+impl From<ActivityType> for u8 {
+    fn from(t: ActivityType) -> u8 {
+        t as u8
+    }
+}
+
+impl TryFrom<u8> for ActivityType {
+    type Error = String;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        use ActivityType::*;
+        Ok(match v {
+            0 => Playing,
+            1 => Streaming,
+            2 => Listening,
+            3 => Watching,
+            4 => Custom,
+            5 => Competing,
+            other => return Err(format!("unknown activity type {other}")),
+        })
+    }
+}
+
+// Which field the member list shows after "Listening to" / "Playing"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+#[repr(u8)]
+pub enum StatusDisplayType {
+    Name = 0,
+    State = 1,
+    Details = 2,
+}
+
+impl From<StatusDisplayType> for u8 {
+    fn from(t: StatusDisplayType) -> u8 {
+        t as u8
+    }
+}
+
+impl TryFrom<u8> for StatusDisplayType {
+    type Error = String;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => StatusDisplayType::Name,
+            1 => StatusDisplayType::State,
+            2 => StatusDisplayType::Details,
+            other => return Err(format!("unknown status display type {other}")),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ActivityTimestamps {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -110,6 +659,11 @@ pub struct ActivityAssets {
     pub small_image: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub small_text: Option<String>,
+    // Click-through links for the images
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub large_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub small_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -140,10 +694,24 @@ pub struct ActivityButton {
 pub struct Activity {
     #[serde(default)]
     pub name: String,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ActivityType>,
+    // Set from the RPC client's client_id; Discord ignores what the client sends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_display_type: Option<StatusDisplayType>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub details: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details_url: Option<String>,
+    // Unix ms; filled in on normalize when the client didn't send it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub timestamps: Option<ActivityTimestamps>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -164,48 +732,11 @@ pub struct Activity {
 }
 
 impl Activity {
-    pub fn normalize(mut self) -> Self {
-        // Convert timestamps heuristically to ms (if appears to be seconds)
-        if let Some(ts) = self.timestamps.as_mut() {
-            let now_ms = current_millis();
-            if let Some(start) = ts.start.as_mut() {
-                maybe_seconds_to_ms(start, now_ms);
-            }
-            if let Some(end) = ts.end.as_mut() {
-                maybe_seconds_to_ms(end, now_ms);
-            }
-        }
-        // instance flag -> flags bit 0
-        if let Some(inst) = self.instance
-            && inst
-        {
-            self.flags = Some(self.flags.unwrap_or(0) | 1);
-        }
-        if let Some(btns) = self.buttons.as_ref() {
-            let labels: Vec<String> = btns.iter().map(|b| b.label.clone()).collect();
-            let urls: Vec<String> = btns.iter().map(|b| b.url.clone()).collect();
-            let meta = serde_json::json!({ "button_urls": urls });
-            self.extra.insert("metadata".into(), meta);
-            self.extra
-                .insert("buttons".into(), serde_json::to_value(labels).unwrap());
-            // Clear typed buttons so wire format uses label list
-            self.buttons = None;
+    // The presence belongs to the connecting application whatever the payload claims
+    pub fn with_application_id(mut self, client_id: Option<&str>) -> Self {
+        if let Some(id) = client_id {
+            self.application_id = Some(id.to_string());
         }
         self
     }
 }
-
-fn maybe_seconds_to_ms(v: &mut u64, now_ms: u64) {
-    // If value seems like seconds (significantly smaller magnitude) convert.
-    if *v < now_ms / 100 {
-        *v *= 1000;
-    }
-}
-
-fn current_millis() -> u64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as u64
-}
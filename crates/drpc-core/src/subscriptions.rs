@@ -0,0 +1,48 @@
+use crate::event::EventKind;
+use crate::protocol::{
+    IncomingFrame, OutgoingFrame, RpcCommand, RpcErrorCode, RpcEvent, SubscribeArgs,
+};
+use serde_json::json;
+use std::collections::HashSet;
+
+// Events one RPC connection has subscribed to; shared by the WebSocket and IPC transports
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    events: HashSet<RpcEvent>,
+}
+
+impl Subscriptions {
+    pub fn contains(&self, evt: &RpcEvent) -> bool {
+        self.events.contains(evt)
+    }
+
+    // ACK or ERROR reply to a SUBSCRIBE/UNSUBSCRIBE frame
+    pub fn handle(&mut self, frame: &IncomingFrame) -> OutgoingFrame {
+        let args: SubscribeArgs = frame.args().unwrap_or_default();
+        match args.event {
+            None => frame.error(
+                RpcErrorCode::InvalidPayload,
+                "Invalid payload: missing args.event",
+            ),
+            Some(evt) if !evt.is_subscribable() => {
+                frame.error(RpcErrorCode::InvalidEvent, "Invalid event")
+            }
+            Some(evt) => {
+                let data = json!({"evt": evt});
+                if frame.cmd == RpcCommand::Subscribe {
+                    self.events.insert(evt);
+                } else {
+                    self.events.remove(&evt);
+                }
+                OutgoingFrame::reply(frame.cmd.clone(), data, frame.nonce.clone())
+                    .with_evt(RpcEvent::Ack)
+            }
+        }
+    }
+
+    // DISPATCH frame for a bus event addressed to `socket_id`, if it subscribed to it
+    pub fn dispatch(&self, socket_id: &str, evt: &EventKind) -> Option<OutgoingFrame> {
+        let (target, evt, data) = evt.rpc_dispatch()?;
+        (target == socket_id && self.contains(&evt)).then(|| OutgoingFrame::dispatch(evt, data))
+    }
+}
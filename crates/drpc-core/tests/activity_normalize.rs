@@ -1,4 +1,7 @@
-use drpc_core::{Activity, ActivityButton};
+use drpc_core::{
+    Activity, ActivityButton, ActivityType, FLAG_INSTANCE, NORMALIZE_STEPS, StatusDisplayType,
+    seconds_to_ms,
+};
 
 #[test]
 fn buttons_normalize() {
@@ -15,3 +18,133 @@ fn buttons_normalize() {
     let val = serde_json::to_value(&norm).unwrap();
     assert!(val.get("metadata").is_some());
 }
+
+#[test]
+fn listening_presence_keeps_type_and_new_fields() {
+    let raw = serde_json::json!({
+        "name": "Player",
+        "type": 2,
+        "status_display_type": 1,
+        "details": "Song",
+        "details_url": "https://music.example/song",
+        "state": "Artist",
+        "state_url": "https://music.example/artist",
+        "assets": {"large_image": "cover", "large_url": "https://music.example/album"},
+        "application_id": "999",
+        "created_at": 1_700_000_000,
+    });
+    let act: Activity = serde_json::from_value(raw).unwrap();
+    assert!(act.extra.is_empty());
+    let norm = act.with_application_id(Some("123")).normalize();
+    assert_eq!(norm.kind, Some(ActivityType::Listening));
+    assert_eq!(norm.status_display_type, Some(StatusDisplayType::State));
+    assert_eq!(norm.application_id.as_deref(), Some("123"));
+    // Seconds are promoted to ms like the timestamps
+    assert_eq!(norm.created_at, Some(1_700_000_000_000));
+    let val = serde_json::to_value(&norm).unwrap();
+    assert_eq!(val["type"], 2);
+    assert_eq!(val["status_display_type"], 1);
+    assert_eq!(val["assets"]["large_url"], "https://music.example/album");
+}
+
+#[test]
+fn untyped_activity_defaults_to_playing() {
+    let norm = Activity {
+        name: "Game".into(),
+        ..Default::default()
+    }
+    .with_application_id(None)
+    .normalize();
+    assert_eq!(norm.kind, Some(ActivityType::Playing));
+    assert!(norm.application_id.is_none());
+    assert!(norm.created_at.is_some());
+}
+
+const NOW_MS: u64 = 1_750_000_000_000;
+
+fn button_activity() -> Activity {
+    serde_json::from_value(serde_json::json!({
+        "name": "Game",
+        "instance": true,
+        "timestamps": {"start": 1_700_000_000},
+        "buttons": [
+            {"label": "Site", "url": "https://example.com"},
+            {"label": "Docs", "url": "https://example.com/docs"}
+        ],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn steps_are_named_and_individually_idempotent() {
+    let names: Vec<_> = NORMALIZE_STEPS.iter().map(|(n, _)| *n).collect();
+    assert_eq!(
+        names,
+        [
+            "default_type",
+            "created_at",
+            "timestamps_to_ms",
+            "instance_flag"
+        ]
+    );
+    for (name, step) in NORMALIZE_STEPS {
+        let mut once = button_activity();
+        step(&mut once, NOW_MS);
+        let mut twice = once.clone();
+        step(&mut twice, NOW_MS + 1);
+        assert_eq!(
+            serde_json::to_value(&once).unwrap(),
+            serde_json::to_value(&twice).unwrap(),
+            "{name} is not idempotent"
+        );
+    }
+}
+
+#[test]
+fn normalize_at_is_deterministic() {
+    let a = button_activity().normalize_at(NOW_MS);
+    let b = button_activity().normalize_at(NOW_MS);
+    assert_eq!(a.created_at, Some(NOW_MS));
+    assert_eq!(
+        a.timestamps.as_ref().unwrap().start,
+        Some(1_700_000_000_000)
+    );
+    assert_eq!(a.flags, Some(FLAG_INSTANCE));
+    assert_eq!(
+        serde_json::to_value(&a).unwrap(),
+        serde_json::to_value(&b).unwrap()
+    );
+    assert_eq!(seconds_to_ms(1_700_000_000_000), 1_700_000_000_000);
+}
+
+#[test]
+fn wire_form_roundtrips_to_typed_buttons() {
+    let wire = button_activity().normalize_at(NOW_MS);
+    let val = serde_json::to_value(&wire).unwrap();
+    assert_eq!(val["buttons"], serde_json::json!(["Site", "Docs"]));
+    assert_eq!(
+        val["metadata"]["button_urls"][1],
+        "https://example.com/docs"
+    );
+    let back = Activity::from_value(val.clone()).unwrap();
+    let buttons = back.buttons.as_ref().unwrap();
+    assert_eq!(buttons[1].label, "Docs");
+    assert_eq!(buttons[1].url, "https://example.com/docs");
+    assert!(!back.extra.contains_key("metadata"));
+    // Normalizing the wire form again changes nothing
+    let again = back.normalize_at(NOW_MS + 1);
+    assert_eq!(serde_json::to_value(&again).unwrap(), val);
+}
+
+#[test]
+fn unrelated_metadata_survives_roundtrip() {
+    let mut act = button_activity();
+    act.extra
+        .insert("metadata".into(), serde_json::json!({"album_id": "x"}));
+    let wire = act.normalize_at(NOW_MS);
+    let meta = wire.metadata.as_ref().unwrap();
+    assert_eq!(meta.extra["album_id"], "x");
+    assert_eq!(meta.button_urls.len(), 2);
+    let back = wire.into_activity();
+    assert_eq!(back.extra["metadata"], serde_json::json!({"album_id": "x"}));
+}
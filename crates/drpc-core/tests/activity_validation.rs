@@ -0,0 +1,132 @@
+use drpc_core::validate_activity;
+use serde_json::json;
+
+fn fields(activity: serde_json::Value) -> Vec<String> {
+    match validate_activity(&activity) {
+        Ok(()) => Vec::new(),
+        Err(e) => e.errors.into_iter().map(|f| f.field).collect(),
+    }
+}
+
+#[test]
+fn accepts_full_activity() {
+    let act = json!({
+        "type": 0,
+        "state": "In a group",
+        "details": "Competitive",
+        "timestamps": {"start": 1_700_000_000, "end": 1_700_000_600},
+        "assets": {"large_image": "map", "large_text": "Map name", "small_text": null},
+        "party": {"id": "party-1", "size": [1, 4]},
+        "secrets": {"join": "j", "spectate": "s"},
+    });
+    assert_eq!(fields(act), Vec::<String>::new());
+    assert!(validate_activity(&json!({"name": "x", "state": null})).is_ok());
+}
+
+#[test]
+fn text_lengths() {
+    let long = "x".repeat(129);
+    assert_eq!(
+        fields(json!({"state": "a", "details": long, "assets": {"large_text": 5}})),
+        vec!["state", "details", "assets.large_text"]
+    );
+    // Counted in characters, not bytes
+    assert!(validate_activity(&json!({"state": "é".repeat(128)})).is_ok());
+}
+
+#[test]
+fn buttons() {
+    let label = "l".repeat(33);
+    let act = json!({"buttons": [
+        {"label": label, "url": "https://example.com"},
+        {"label": "Ok", "url": "javascript:alert(1)"},
+        {"label": "Third", "url": "https://example.com"},
+    ]});
+    assert_eq!(
+        fields(act),
+        vec!["buttons", "buttons[0].label", "buttons[1].url"]
+    );
+    assert_eq!(
+        fields(json!({"buttons": [{"url": "https://a.b"}]})),
+        vec!["buttons[0].label"]
+    );
+    assert_eq!(
+        fields(json!({"buttons": [{"label": "x", "url": "https://a.b"}]})),
+        vec!["buttons[0].label"]
+    );
+}
+
+#[test]
+fn buttons_exclude_instance_and_secrets() {
+    let btn = json!([{"label": "Site", "url": "https://example.com"}]);
+    assert_eq!(
+        fields(json!({"buttons": btn, "instance": true})),
+        vec!["buttons"]
+    );
+    assert_eq!(
+        fields(json!({"buttons": btn, "party": {"id": "p1"}, "secrets": {"join": "j"}})),
+        vec!["buttons"]
+    );
+    assert!(validate_activity(&json!({"buttons": btn, "instance": false})).is_ok());
+}
+
+#[test]
+fn timestamps_party_secrets_and_type() {
+    assert_eq!(
+        fields(json!({"timestamps": {"start": 10, "end": 5}})),
+        vec!["timestamps"]
+    );
+    // Seconds and milliseconds are compared on one scale
+    assert!(
+        validate_activity(
+            &json!({"timestamps": {"start": 1_700_000_000_000u64, "end": 1_700_000_600}})
+        )
+        .is_ok()
+    );
+    assert_eq!(
+        fields(json!({"timestamps": {"start": 1_700_000_600, "end": 1_700_000_000_000u64}})),
+        vec!["timestamps"]
+    );
+    assert_eq!(
+        fields(json!({"party": {"id": "p1", "size": [5, 4]}})),
+        vec!["party.size"]
+    );
+    assert_eq!(
+        fields(json!({"party": {"id": "p1", "size": [1]}})),
+        vec!["party.size"]
+    );
+    assert_eq!(fields(json!({"secrets": {"join": "j"}})), vec!["party.id"]);
+    assert_eq!(fields(json!({"type": 1})), vec!["type"]);
+    assert!(validate_activity(&json!({"type": 5})).is_ok());
+}
+
+#[test]
+fn error_message_lists_fields() {
+    let err = validate_activity(&json!({"state": "a"})).unwrap_err();
+    assert_eq!(err.to_string(), "state: must be 2-128 characters");
+}
+
+#[test]
+fn newer_fields() {
+    assert_eq!(
+        fields(json!({
+            "status_display_type": 3,
+            "details_url": "ftp://example.com",
+            "state_url": "https://example.com",
+            "assets": {"small_url": "not a url"},
+            "created_at": -1,
+            "application_id": "abc",
+        })),
+        vec![
+            "status_display_type",
+            "details_url",
+            "created_at",
+            "application_id",
+            "assets.small_url"
+        ]
+    );
+    assert!(
+        validate_activity(&json!({"type": 2, "status_display_type": 2, "application_id": "123"}))
+            .is_ok()
+    );
+}
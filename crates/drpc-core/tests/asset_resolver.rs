@@ -0,0 +1,244 @@
+use drpc_core::{AppAsset, AssetResolver, ImageRef, ReadyConfig};
+use serde_json::json;
+
+fn temp_dir(tag: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "drpc-assets-{tag}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn asset(id: &str, name: &str) -> AppAsset {
+    AppAsset {
+        id: id.into(),
+        name: name.into(),
+        kind: 1,
+    }
+}
+
+#[test]
+fn classifies_image_references() {
+    assert_eq!(
+        ImageRef::parse("mp:external/abc/https/x.png"),
+        ImageRef::MediaProxy("external/abc/https/x.png")
+    );
+    assert_eq!(
+        ImageRef::parse("https://example.com/a.png"),
+        ImageRef::Url("https://example.com/a.png")
+    );
+    assert_eq!(ImageRef::parse("logo"), ImageRef::AssetKey("logo"));
+}
+
+#[test]
+fn resolves_keys_with_the_configured_cdn_host() {
+    let resolver = AssetResolver::new(&ReadyConfig {
+        cdn_host: "cdn.example".into(),
+        ..Default::default()
+    });
+    resolver.insert("123", vec![asset("555", "logo")]);
+    assert_eq!(
+        resolver.resolve(Some("123"), "logo").as_deref(),
+        Some("https://cdn.example/app-assets/123/555.png")
+    );
+    // Snowflakes resolve without a list; unknown names and missing apps don't
+    assert_eq!(
+        resolver.resolve(Some("123"), "777").as_deref(),
+        Some("https://cdn.example/app-assets/123/777.png")
+    );
+    assert!(resolver.resolve(Some("123"), "missing").is_none());
+    assert!(resolver.resolve(None, "logo").is_none());
+    // Proxy paths and URLs pass through without an application
+    assert_eq!(
+        resolver.resolve(None, "mp:external/abc/x.png").as_deref(),
+        Some("https://media.discordapp.net/external/abc/x.png")
+    );
+    assert_eq!(
+        resolver
+            .resolve(None, "https://example.com/a.png")
+            .as_deref(),
+        Some("https://example.com/a.png")
+    );
+}
+
+#[tokio::test]
+async fn loads_asset_lists_from_the_disk_cache() {
+    let dir = temp_dir("disk");
+    std::fs::write(
+        dir.join("42.json"),
+        serde_json::to_vec(&vec![asset("900", "cover")]).unwrap(),
+    )
+    .unwrap();
+    let resolver = AssetResolver::default().with_cache_dir(&dir);
+    let mut activity = json!({
+        "application_id": "42",
+        "assets": {"large_image": "cover", "small_image": "mp:external/x/y.png"}
+    });
+    // Nothing is loaded while annotating; the caller is told which list to load
+    assert_eq!(resolver.annotate(&mut activity).as_deref(), Some("42"));
+    assert!(activity["assets"].get("large_image_url").is_none());
+    resolver.load("42").await;
+    assert_eq!(resolver.annotate(&mut activity), None);
+    assert_eq!(
+        activity["assets"]["large_image_url"],
+        "https://cdn.discordapp.com/app-assets/42/900.png"
+    );
+    assert_eq!(
+        activity["assets"]["small_image_url"],
+        "https://media.discordapp.net/external/x/y.png"
+    );
+    assert_eq!(resolver.cached("42").unwrap().len(), 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn annotate_drops_stale_urls_for_unresolvable_images() {
+    let resolver = AssetResolver::default();
+    let mut activity = json!({
+        "assets": {"large_image": "logo", "large_image_url": "https://spoofed.example"}
+    });
+    assert_eq!(resolver.annotate(&mut activity), None);
+    assert!(activity["assets"].get("large_image_url").is_none());
+}
+
+#[tokio::test]
+async fn failed_loads_are_not_kept() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let dir = temp_dir("retry");
+    // Nothing listens on the discard port, so a fetch fails at once with `network` enabled
+    let resolver = AssetResolver::default()
+        .with_cache_dir(&dir)
+        .with_source_url("http://127.0.0.1:9/{application_id}");
+    // No cache file and no network: empty for now, but nothing is cached
+    assert!(resolver.load("77").await.is_empty());
+    assert!(resolver.cached("77").is_none());
+    std::fs::write(
+        dir.join("77.json"),
+        serde_json::to_vec(&vec![asset("901", "icon")]).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(resolver.load("77").await.len(), 1);
+    assert_eq!(resolver.cached("77").unwrap()[0].id, "901");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "network")]
+mod http {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Serves one asset for application 55 and 404 for anything else, counting requests
+    async fn source() -> (String, Arc<AtomicUsize>) {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/{{application_id}}",
+            listener.local_addr().unwrap()
+        );
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut sock, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match sock.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(k) => buf.extend_from_slice(&chunk[..k]),
+                    }
+                }
+                seen.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = if buf.starts_with(b"GET /55 ") {
+                    ("200 OK", r#"[{"id":"902","name":"fresh","type":1}]"#)
+                } else {
+                    ("404 Not Found", "")
+                };
+                let resp = format!(
+                    "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+                let _ = sock.shutdown().await;
+            }
+        });
+        (url, hits)
+    }
+
+    fn write_list(dir: &std::path::Path, app: &str, age: Duration) {
+        let path = dir.join(format!("{app}.json"));
+        std::fs::write(
+            &path,
+            serde_json::to_vec(&vec![asset("901", "old")]).unwrap(),
+        )
+        .unwrap();
+        std::fs::File::options()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_disk_lists_are_fetched_again() {
+        let dir = temp_dir("ttl");
+        let (url, hits) = source().await;
+        let resolver = || {
+            AssetResolver::default()
+                .with_cache_dir(&dir)
+                .with_source_url(&url)
+        };
+        // Fresh copies are used without asking the network
+        write_list(&dir, "55", Duration::from_secs(60));
+        assert_eq!(resolver().load("55").await[0].id, "901");
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+
+        let old = Duration::from_secs(3 * 24 * 3600);
+        write_list(&dir, "55", old);
+        assert_eq!(resolver().load("55").await[0].id, "902");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+        assert!(
+            std::fs::read_to_string(dir.join("55.json"))
+                .unwrap()
+                .contains("902")
+        );
+
+        // A stale copy still beats nothing when the fetch fails
+        write_list(&dir, "58", old);
+        assert_eq!(resolver().load("58").await[0].id, "901");
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn background_loads_are_not_repeated() {
+        let (url, hits) = source().await;
+        let resolver = Arc::new(AssetResolver::default().with_source_url(&url));
+        for _ in 0..5 {
+            resolver.spawn_load("55");
+            resolver.spawn_load("66");
+        }
+        for _ in 0..100 {
+            if resolver.cached("55").is_some() && hits.load(Ordering::SeqCst) == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(resolver.cached("55").unwrap()[0].id, "902");
+        // 66 has no list; it isn't asked for again until the retry delay has passed
+        resolver.spawn_load("66");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert!(resolver.cached("66").is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use drpc_core::{AuthConfig, is_local_host, load_or_create_token};
+
+#[test]
+fn only_loopback_hosts_are_local() {
+    for host in [
+        "localhost",
+        "localhost:1337",
+        "LOCALHOST.",
+        "app.localhost:80",
+        "127.0.0.1",
+        "127.0.0.1:8080",
+        "127.1.2.3",
+        "[::1]:1337",
+    ] {
+        assert!(is_local_host(host), "{host}");
+    }
+    for host in [
+        "",
+        "evil.example",
+        "evil.example:1337",
+        "localhost.evil.example",
+        "10.0.0.1:1337",
+        "[::2]:1337",
+    ] {
+        assert!(!is_local_host(host), "{host}");
+    }
+}
+
+#[test]
+fn tokens_and_origins() {
+    let open = AuthConfig::default();
+    assert!(open.check_bearer(None));
+    let auth = AuthConfig::default().with_token("s3cret");
+    assert!(auth.check_bearer(Some("Bearer s3cret")));
+    assert!(!auth.check_bearer(Some("Bearer s3cre")));
+    assert!(!auth.check_bearer(Some("s3cret")));
+    assert!(!auth.check_bearer(None));
+    assert!(auth.check_token(Some("s3cret")));
+
+    assert!(auth.origin_allowed(None));
+    assert!(auth.origin_allowed(Some("https://discord.com")));
+    assert!(auth.origin_allowed(Some("https://canary.discord.com")));
+    assert!(auth.origin_allowed(Some("http://localhost:5173")));
+    assert!(!auth.origin_allowed(Some("https://evil.example")));
+    assert!(!auth.origin_allowed(Some("https://discord.com.evil.example")));
+
+    // File pages (OBS overlays) send `null`; only allowed when listed
+    assert!(!auth.origin_allowed(Some("null")));
+    let mut overlay = auth.clone();
+    overlay.bridge_origins.push("null".into());
+    assert!(overlay.origin_allowed(Some("null")));
+}
+
+#[test]
+fn token_file_is_created_once_and_private() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("drpc-auth-{nanos}"));
+    let path = dir.join("token");
+    let token = load_or_create_token(&path).unwrap();
+    assert_eq!(token.len(), 64);
+    assert!(token.bytes().all(|b| b.is_ascii_hexdigit()));
+    assert_eq!(load_or_create_token(&path).unwrap(), token);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,122 @@
+use drpc_core::{
+    CacheError, DETECTABLES_CACHE_VERSION, DetectableEntry, DetectablesCache, backup_path,
+};
+
+fn temp_dir(tag: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "drpc-cache-{tag}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn entry(name: &str) -> DetectableEntry {
+    DetectableEntry {
+        id: Some(name.to_lowercase()),
+        name: name.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn legacy_array_is_migrated() {
+    let c = DetectablesCache::parse(r#"[{"id":"1","name":"Legacy"}]"#).unwrap();
+    assert_eq!(c.version, DETECTABLES_CACHE_VERSION);
+    assert_eq!(c.entries.len(), 1);
+    assert!(c.etag.is_none());
+}
+
+#[test]
+fn envelope_roundtrip_keeps_validators() {
+    let c = DetectablesCache {
+        etag: Some("\"abc\"".into()),
+        last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+        ..DetectablesCache::new(vec![entry("A")])
+    };
+    let back = DetectablesCache::parse(&serde_json::to_string(&c).unwrap()).unwrap();
+    assert_eq!(back.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(back.last_modified, c.last_modified);
+    assert_eq!(back.entries[0].name, "A");
+}
+
+#[test]
+fn newer_version_is_rejected() {
+    let err = DetectablesCache::parse(r#"{"version":99,"entries":[]}"#).unwrap_err();
+    assert!(matches!(err, CacheError::UnsupportedVersion(99)));
+}
+
+#[test]
+fn atomic_write_keeps_previous_copy() {
+    let dir = temp_dir("write");
+    let path = dir.join("detectables.json");
+    DetectablesCache::new(vec![entry("Old")])
+        .write_atomic(&path)
+        .unwrap();
+    DetectablesCache::new(vec![entry("New")])
+        .write_atomic(&path)
+        .unwrap();
+    assert_eq!(
+        DetectablesCache::read(&path).unwrap().entries[0].name,
+        "New"
+    );
+    let bak = DetectablesCache::read(&backup_path(&path)).unwrap();
+    assert_eq!(bak.entries[0].name, "Old");
+    // No temp files left behind
+    let names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(names.len(), 2, "{names:?}");
+}
+
+#[test]
+fn corrupt_file_does_not_clobber_backup() {
+    let dir = temp_dir("corrupt");
+    let path = dir.join("detectables.json");
+    DetectablesCache::new(vec![entry("Good")])
+        .write_atomic(&path)
+        .unwrap();
+    DetectablesCache::new(vec![entry("Newer")])
+        .write_atomic(&path)
+        .unwrap();
+    std::fs::write(&path, "{\"version\":1,\"entries\":[").unwrap();
+    let c = DetectablesCache::read_with_fallback(&path).unwrap();
+    assert_eq!(c.entries[0].name, "Good");
+    // Rewriting over a corrupt file must keep the good backup
+    DetectablesCache::new(vec![entry("Fresh")])
+        .write_atomic(&path)
+        .unwrap();
+    let bak = DetectablesCache::read(&backup_path(&path)).unwrap();
+    assert_eq!(bak.entries[0].name, "Good");
+}
+
+#[test]
+fn concurrent_writes_leave_readable_files() {
+    let dir = temp_dir("concurrent");
+    let path = dir.join("detectables.json");
+    std::thread::scope(|s| {
+        for i in 0..8 {
+            let path = &path;
+            s.spawn(move || {
+                for _ in 0..10 {
+                    DetectablesCache::new(vec![entry(&format!("Writer{i}"))])
+                        .write_atomic(path)
+                        .unwrap();
+                }
+            });
+        }
+    });
+    assert!(DetectablesCache::read(&path).is_ok());
+    assert!(DetectablesCache::read(&backup_path(&path)).is_ok());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+}
+
+#[test]
+fn missing_cache_reads_as_none() {
+    let dir = temp_dir("missing");
+    assert!(DetectablesCache::read_with_fallback(&dir.join("detectables.json")).is_none());
+}
@@ -0,0 +1,70 @@
+use drpc_core::{ActivityType, DetectableEntry, DetectableOs};
+
+// Trimmed entry as served by https://discord.com/api/v9/applications/detectable
+const SAMPLE: &str = r#"{
+    "aliases": ["Minecraft Java Edition"],
+    "executables": [
+        {"is_launcher": false, "name": "javaw.exe", "os": "win32", "arguments": "net.minecraft.client.main.Main"},
+        {"is_launcher": false, "name": "java", "os": "linux"}
+    ],
+    "hook": true,
+    "icon_hash": "166fbad351ecdd02d11a3b464748f66b",
+    "id": "356875570916753438",
+    "name": "Minecraft",
+    "overlay": false,
+    "themes": ["Sandbox", "Survival"],
+    "type": 1
+}"#;
+
+#[test]
+fn parses_extended_fields() {
+    let entry: DetectableEntry = serde_json::from_str(SAMPLE).unwrap();
+    assert_eq!(entry.aliases, vec!["Minecraft Java Edition"]);
+    assert_eq!(entry.themes.len(), 2);
+    assert_eq!(entry.app_type, Some(1));
+    assert_eq!(entry.executables[0].os, Some(DetectableOs::Win32));
+    assert_eq!(entry.executables[1].os, Some(DetectableOs::Linux));
+    assert_eq!(
+        entry.executables[0].arguments.as_deref(),
+        Some("net.minecraft.client.main.Main")
+    );
+    assert_eq!(entry.executables[1].arguments, None);
+}
+
+#[test]
+fn detected_activity_carries_app_metadata() {
+    let entry: DetectableEntry = serde_json::from_str(SAMPLE).unwrap();
+    let act = entry.to_activity("cdn.discordapp.com", 1_700_000_000_000);
+    let val = serde_json::to_value(&act).unwrap();
+    assert_eq!(val["name"], "Minecraft");
+    assert_eq!(val["application_id"], "356875570916753438");
+    assert_eq!(val["type"], 0);
+    assert_eq!(val["timestamps"]["start"], 1_700_000_000_000u64);
+    assert_eq!(
+        val["assets"]["large_image"],
+        "https://cdn.discordapp.com/app-icons/356875570916753438/166fbad351ecdd02d11a3b464748f66b.png"
+    );
+    assert_eq!(val["assets"]["large_text"], "Minecraft");
+}
+
+#[test]
+fn music_apps_are_listening_and_iconless_entries_skip_assets() {
+    let entry = DetectableEntry {
+        id: Some("1".into()),
+        name: "Player".into(),
+        app_type: Some(2),
+        ..Default::default()
+    };
+    let act = entry.to_activity("cdn.discordapp.com", 1);
+    assert!(act.assets.is_none());
+    assert_eq!(act.kind, Some(ActivityType::Listening));
+}
+
+#[test]
+fn unknown_os_is_tolerated() {
+    let entry: DetectableEntry = serde_json::from_str(
+        r#"{"id":"1","name":"X","executables":[{"name":"x","is_launcher":false,"os":"haiku"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(entry.executables[0].os, Some(DetectableOs::Unknown));
+}
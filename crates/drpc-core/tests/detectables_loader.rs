@@ -48,8 +48,10 @@ async fn ttl_and_fresh_load_behavior() {
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
     let d2 = drpc_core::load_detectables_async(false, 0).await.unwrap();
     let list2 = d2.list();
-    // With network feature disabled, stale path returns empty set
-    assert_eq!(list2.len(), 0);
+    // Nothing to refresh from offline, so the stale cache is still used
+    assert_eq!(list2.len(), 1);
+    let d3 = drpc_core::load_detectables_async(true, 1000).await.unwrap();
+    assert_eq!(d3.list().len(), 1);
 }
 
 #[tokio::test]
@@ -90,3 +92,48 @@ async fn parse_fallback_on_invalid_json() {
     let list = d.list();
     assert_eq!(list.len(), 0);
 }
+
+#[tokio::test]
+async fn falls_back_to_backup_copy() {
+    let _g = ENV_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let base = std::env::temp_dir().join(format!(
+        "drpc-detectables-backup-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(base.join(".drpc")).unwrap();
+
+    #[cfg(windows)]
+    unsafe {
+        std::env::set_var("USERPROFILE", &base);
+    }
+
+    #[cfg(not(windows))]
+    unsafe {
+        std::env::set_var("HOME", &base);
+    }
+
+    let path = base.join(".drpc").join("detectables.json");
+    std::fs::write(
+        base.join(".drpc").join("detectables.json.bak"),
+        r#"{"version":1,"etag":"\"v1\"","entries":[{"id":"app1","name":"App1"}]}"#,
+    )
+    .unwrap();
+    // Simulates a write torn by a crash
+    std::fs::write(&path, r#"{"version":1,"entries":[{"id":"ap"#).unwrap();
+
+    unsafe {
+        std::env::set_var("DRPC_OFFLINE", "1");
+    }
+    let d = drpc_core::load_detectables_async(false, 1000)
+        .await
+        .unwrap();
+    let list = d.list();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].name, "App1");
+}
@@ -0,0 +1,129 @@
+use drpc_core::{DetectableEntry, LocalDetectables};
+use std::sync::OnceLock;
+
+static ENV_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+const LOCAL: &str = r#"
+[[entries]]
+id = "local-studio"
+name = "Studio Tool"
+large_image = "https://example.com/studio.png"
+executables = [{ name = "studio-tool", os = "linux" }]
+
+[overrides."356875570916753438"]
+name = "Minecraft (modded)"
+large_image = "https://example.com/mc.png"
+
+[blocklist]
+ids = ["1124049195335426139"]
+executables = ["GameClient.exe"]
+"#;
+
+fn downloaded() -> Vec<DetectableEntry> {
+    serde_json::from_str(
+        r#"[
+        {"id":"356875570916753438","name":"Minecraft","icon_hash":"abc","executables":[{"name":"minecraft.exe","is_launcher":false}]},
+        {"id":"1124049195335426139","name":"Star Trek Online","executables":[{"name":"gameclient.exe","is_launcher":false}]},
+        {"id":"356877880938070016","name":"Neverwinter","executables":[{"name":"gameclient.exe","is_launcher":false},{"name":"bin/GameClient.exe","is_launcher":false},{"name":"gameclient_launcher/setup.exe","is_launcher":false},{"name":"neverwinter.exe","is_launcher":false}]}
+    ]"#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn overlay_adds_overrides_and_blocks() {
+    let local = LocalDetectables::from_toml(LOCAL).unwrap();
+    let mut list = downloaded();
+    local.apply(&mut list);
+    let names: Vec<&str> = list.iter().map(|d| d.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["Minecraft (modded)", "Neverwinter", "Studio Tool"]
+    );
+    let mc = &list[0];
+    let act = mc.to_activity("cdn.discordapp.com", 1);
+    assert_eq!(
+        act.assets.unwrap().large_image.as_deref(),
+        Some("https://example.com/mc.png")
+    );
+    // blocked executable is gone, including under a directory; the rest of the entry stays
+    let nw = &list[1];
+    let exes: Vec<&str> = nw.executables.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        exes,
+        vec!["gameclient_launcher/setup.exe", "neverwinter.exe"]
+    );
+    assert_eq!(list[2].executables[0].name, "studio-tool");
+}
+
+#[test]
+fn local_entry_replaces_downloaded_entry_with_same_id() {
+    let local = LocalDetectables::from_toml(
+        r#"
+[[entries]]
+id = "356875570916753438"
+name = "Minecraft Local"
+executables = [{ name = "mc" }]
+"#,
+    )
+    .unwrap();
+    let mut list = downloaded();
+    local.apply(&mut list);
+    let mc: Vec<_> = list
+        .iter()
+        .filter(|d| d.id.as_deref() == Some("356875570916753438"))
+        .collect();
+    assert_eq!(mc.len(), 1);
+    assert_eq!(mc[0].name, "Minecraft Local");
+}
+
+#[test]
+fn invalid_toml_is_an_error() {
+    assert!(LocalDetectables::from_toml("entries = 3").is_err());
+    assert!(LocalDetectables::from_toml("").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn loader_merges_local_file() {
+    let _g = ENV_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let base = std::env::temp_dir().join(format!(
+        "drpc-detectables-local-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(base.join(".drpc")).unwrap();
+
+    #[cfg(windows)]
+    unsafe {
+        std::env::set_var("USERPROFILE", &base);
+    }
+
+    #[cfg(not(windows))]
+    unsafe {
+        std::env::set_var("HOME", &base);
+    }
+    unsafe {
+        std::env::set_var("DRPC_OFFLINE", "1");
+    }
+
+    let cache = base.join(".drpc").join("detectables.json");
+    std::fs::write(&cache, serde_json::to_string(&downloaded()).unwrap()).unwrap();
+    std::fs::write(base.join(".drpc").join("detectables.local.toml"), LOCAL).unwrap();
+
+    let d = drpc_core::load_detectables_async(false, 1000)
+        .await
+        .unwrap();
+    let list = d.list();
+    assert_eq!(list.len(), 3);
+    assert!(list.iter().any(|e| e.name == "Studio Tool"));
+    // the on-disk cache keeps the pristine downloaded list
+    let cached: Vec<DetectableEntry> =
+        serde_json::from_str(&std::fs::read_to_string(&cache).unwrap()).unwrap();
+    assert_eq!(cached.len(), 3);
+    assert!(cached.iter().any(|e| e.name == "Star Trek Online"));
+}
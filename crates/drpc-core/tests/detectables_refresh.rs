@@ -0,0 +1,102 @@
+use drpc_core::{
+    Detectables, DetectablesOptions, DetectablesRefresher, DetectablesSource, EventBus, EventKind,
+};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static ENV_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+fn temp_home(tag: &str) -> PathBuf {
+    let base = std::env::temp_dir().join(format!(
+        "drpc-refresh-{tag}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(base.join(".drpc")).unwrap();
+    unsafe {
+        std::env::set_var("HOME", &base);
+        std::env::set_var("USERPROFILE", &base);
+    }
+    base
+}
+
+fn options(base: &std::path::Path, mirror: PathBuf) -> DetectablesOptions {
+    DetectablesOptions {
+        sources: vec![DetectablesSource::File(mirror)],
+        cache_path: Some(base.join(".drpc").join("detectables.json")),
+        ..Default::default()
+    }
+}
+
+// File sources have no validators, so every tick publishes; wait for the expected count
+async fn wait_for_count(rx: &mut tokio::sync::mpsc::UnboundedReceiver<EventKind>, want: usize) {
+    tokio::time::timeout(Duration::from_secs(3), async {
+        loop {
+            if let Some(EventKind::DetectablesUpdated { count }) = rx.recv().await
+                && count == want
+            {
+                return;
+            }
+        }
+    })
+    .await
+    .expect("detectables update")
+}
+
+#[tokio::test]
+async fn refresh_swaps_list_and_publishes() {
+    let _g = ENV_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let base = temp_home("swap");
+    let mirror = base.join("mirror.json");
+    std::fs::write(&mirror, r#"[{"id":"1","name":"A"}]"#).unwrap();
+    let det = Detectables::default();
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let handle =
+        DetectablesRefresher::new(det.clone(), bus.clone(), options(&base, mirror.clone()))
+            .with_interval(Duration::from_millis(50))
+            .with_backoff(Duration::from_millis(50))
+            .spawn();
+    wait_for_count(&mut rx, 1).await;
+    assert_eq!(det.list()[0].name, "A");
+    let generation = det.generation();
+    std::fs::write(&mirror, r#"[{"id":"1","name":"A"},{"id":"2","name":"B"}]"#).unwrap();
+    wait_for_count(&mut rx, 2).await;
+    assert_eq!(det.list().len(), 2);
+    assert!(det.generation() > generation);
+    handle.abort();
+}
+
+#[tokio::test]
+async fn failed_refresh_backs_off_and_recovers() {
+    let _g = ENV_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let base = temp_home("backoff");
+    let mirror = base.join("mirror.json");
+    let det = Detectables::default();
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    // Long interval: only the failure backoff can bring the next attempt forward
+    let handle =
+        DetectablesRefresher::new(det.clone(), bus.clone(), options(&base, mirror.clone()))
+            .with_interval(Duration::from_secs(60))
+            .with_backoff(Duration::from_millis(20))
+            .spawn();
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(det.list().is_empty());
+    std::fs::write(&mirror, r#"[{"id":"1","name":"Recovered"}]"#).unwrap();
+    // A few doublings of the backoff at most, far short of the interval
+    let started = std::time::Instant::now();
+    wait_for_count(&mut rx, 1).await;
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(det.list()[0].name, "Recovered");
+    handle.abort();
+}
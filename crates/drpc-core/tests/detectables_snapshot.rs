@@ -0,0 +1,54 @@
+use drpc_core::{DetectableEntry, Detectables};
+use std::sync::Arc;
+
+fn entry(name: &str) -> DetectableEntry {
+    DetectableEntry {
+        name: name.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn snapshots_survive_replace() {
+    let det = Detectables::new(vec![entry("Old")]);
+    let before = det.list();
+    let generation = det.generation();
+    det.replace(vec![entry("New"), entry("Other")]);
+    assert_eq!(before[0].name, "Old");
+    assert_eq!(det.list()[0].name, "New");
+    assert_eq!(det.len(), 2);
+    assert_eq!(det.generation(), generation + 1);
+}
+
+#[test]
+fn list_shares_the_snapshot() {
+    let det = Detectables::new(vec![entry("A")]);
+    assert!(Arc::ptr_eq(&det.list(), &det.list()));
+    // Clones share the same slot, so a refresh through one is seen by all
+    let other = det.clone();
+    let shared = Arc::new(vec![entry("B")]);
+    other.replace(shared.clone());
+    assert!(Arc::ptr_eq(&det.list(), &shared));
+}
+
+#[test]
+fn concurrent_readers_see_whole_lists() {
+    let det = Detectables::new(vec![entry("x"); 10]);
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let det = det.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    let len = det.list().len();
+                    assert!(len == 10 || len == 20);
+                }
+            })
+        })
+        .collect();
+    for i in 0..100 {
+        det.replace(vec![entry("x"); if i % 2 == 0 { 20 } else { 10 }]);
+    }
+    for r in readers {
+        r.join().unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+use drpc_core::{DetectablesOptions, DetectablesSource};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static ENV_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+fn temp_home(tag: &str) -> PathBuf {
+    let base = std::env::temp_dir().join(format!(
+        "drpc-source-{tag}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(base.join(".drpc")).unwrap();
+    // Keeps a real ~/.drpc/detectables.local.toml out of the results
+    unsafe {
+        std::env::set_var("HOME", &base);
+        std::env::set_var("USERPROFILE", &base);
+        std::env::remove_var("DRPC_OFFLINE");
+    }
+    base
+}
+
+fn options(base: &std::path::Path, sources: Vec<DetectablesSource>) -> DetectablesOptions {
+    DetectablesOptions {
+        sources,
+        force_refresh: true,
+        retries: 3,
+        cache_path: Some(base.join(".drpc").join("detectables.json")),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn parses_source_strings() {
+    assert_eq!(
+        "https://mirror.lan/detectable"
+            .parse::<DetectablesSource>()
+            .unwrap(),
+        DetectablesSource::Http("https://mirror.lan/detectable".into())
+    );
+    let file: DetectablesSource = "file:///srv/detectables.json".parse().unwrap();
+    assert_eq!(
+        file,
+        DetectablesSource::File(PathBuf::from("/srv/detectables.json"))
+    );
+    assert_eq!(file.to_string(), "file:///srv/detectables.json");
+    assert!("ftp://mirror.lan".parse::<DetectablesSource>().is_err());
+}
+
+#[tokio::test]
+async fn file_source_populates_cache() {
+    let _g = ENV_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let base = temp_home("file");
+    let mirror = base.join("mirror.json");
+    std::fs::write(&mirror, r#"[{"id":"1","name":"Mirrored"},{"bogus":true}]"#).unwrap();
+    let opts = options(&base, vec![DetectablesSource::File(mirror)]);
+    let d = drpc_core::load_detectables_with(&opts).await.unwrap();
+    assert_eq!(d.list().len(), 1);
+    let cache = drpc_core::DetectablesCache::read(opts.cache_path.as_ref().unwrap()).unwrap();
+    assert_eq!(cache.entries[0].name, "Mirrored");
+    assert!(cache.source.unwrap().starts_with("file://"));
+}
+
+#[cfg(feature = "network")]
+mod http {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    struct Stub {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    // Serves the scripted responses in order, repeating the last one
+    async fn stub(responses: Vec<(u16, &'static str, &'static str)>) -> Stub {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/detectable", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            let mut n = 0;
+            loop {
+                let Ok((mut sock, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match sock.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(k) => buf.extend_from_slice(&chunk[..k]),
+                    }
+                }
+                seen.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf).to_lowercase());
+                let (status, headers, body) = responses[n.min(responses.len() - 1)];
+                n += 1;
+                let resp = format!(
+                    "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+                let _ = sock.shutdown().await;
+            }
+        });
+        Stub { url, requests }
+    }
+
+    impl Stub {
+        fn source(&self) -> DetectablesSource {
+            DetectablesSource::Http(self.url.clone())
+        }
+        fn count(&self) -> usize {
+            self.requests.lock().unwrap().len()
+        }
+    }
+
+    const BODY: &str = r#"[{"id":"1","name":"Remote"}]"#;
+
+    #[tokio::test]
+    async fn conditional_refresh_reuses_cache() {
+        let _g = ENV_LOCK
+            .get_or_init(|| tokio::sync::Mutex::new(()))
+            .lock()
+            .await;
+        let base = temp_home("etag");
+        let s = stub(vec![
+            (200, "ETag: \"v1\"\r\n", BODY),
+            (304, "ETag: \"v1\"\r\n", ""),
+        ])
+        .await;
+        let opts = options(&base, vec![s.source()]);
+        let d = drpc_core::load_detectables_with(&opts).await.unwrap();
+        assert_eq!(d.list()[0].name, "Remote");
+        let d = drpc_core::load_detectables_with(&opts).await.unwrap();
+        assert_eq!(d.list()[0].name, "Remote");
+        let reqs = s.requests.lock().unwrap();
+        assert_eq!(reqs.len(), 2);
+        assert!(!reqs[0].contains("if-none-match"));
+        assert!(reqs[1].contains("if-none-match: \"v1\""), "{}", reqs[1]);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let _g = ENV_LOCK
+            .get_or_init(|| tokio::sync::Mutex::new(()))
+            .lock()
+            .await;
+        let base = temp_home("retry");
+        let s = stub(vec![(503, "", ""), (200, "", BODY)]).await;
+        let d = drpc_core::load_detectables_with(&options(&base, vec![s.source()]))
+            .await
+            .unwrap();
+        assert_eq!(d.list().len(), 1);
+        assert_eq!(s.count(), 2);
+    }
+
+    #[tokio::test]
+    async fn non_2xx_falls_through_to_next_source() {
+        let _g = ENV_LOCK
+            .get_or_init(|| tokio::sync::Mutex::new(()))
+            .lock()
+            .await;
+        let base = temp_home("fallback");
+        let s = stub(vec![(404, "", "not found")]).await;
+        let mirror = base.join("mirror.json");
+        std::fs::write(&mirror, r#"[{"id":"2","name":"Mirror"}]"#).unwrap();
+        let opts = options(&base, vec![s.source(), DetectablesSource::File(mirror)]);
+        let d = drpc_core::load_detectables_with(&opts).await.unwrap();
+        assert_eq!(d.list()[0].name, "Mirror");
+        // Client errors are not retried
+        assert_eq!(s.count(), 1);
+    }
+
+    #[tokio::test]
+    async fn malformed_json_keeps_cached_copy() {
+        let _g = ENV_LOCK
+            .get_or_init(|| tokio::sync::Mutex::new(()))
+            .lock()
+            .await;
+        let base = temp_home("malformed");
+        let opts = options(&base, vec![]);
+        drpc_core::DetectablesCache::new(vec![drpc_core::DetectableEntry {
+            name: "Cached".into(),
+            ..Default::default()
+        }])
+        .write_atomic(opts.cache_path.as_ref().unwrap())
+        .unwrap();
+        let s = stub(vec![(200, "", "{\"not\": ")]).await;
+        let opts = DetectablesOptions {
+            sources: vec![s.source()],
+            ..opts
+        };
+        let d = drpc_core::load_detectables_with(&opts).await.unwrap();
+        assert_eq!(d.list()[0].name, "Cached");
+        assert_eq!(s.count(), 1);
+        let cache = drpc_core::DetectablesCache::read(opts.cache_path.as_ref().unwrap()).unwrap();
+        assert_eq!(cache.entries[0].name, "Cached");
+    }
+}
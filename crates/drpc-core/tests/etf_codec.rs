@@ -0,0 +1,142 @@
+#![cfg(feature = "etf")]
+
+use drpc_core::etf::{EtfError, decode, encode};
+use proptest::prelude::*;
+use serde_json::{Value, json};
+
+// Expected bytes are erlpack.pack() output for the equivalent Python/JS values
+#[test]
+fn matches_erlpack_bytes() {
+    let cases: Vec<(Value, Vec<u8>)> = vec![
+        (json!(null), vec![131, 115, 3, b'n', b'i', b'l']),
+        (json!(true), vec![131, 115, 4, b't', b'r', b'u', b'e']),
+        (
+            json!(false),
+            vec![131, 115, 5, b'f', b'a', b'l', b's', b'e'],
+        ),
+        (json!(7), vec![131, 97, 7]),
+        (json!(256), vec![131, 98, 0, 0, 1, 0]),
+        (json!(-1), vec![131, 98, 255, 255, 255, 255]),
+        (json!(1u64 << 40), vec![131, 110, 6, 0, 0, 0, 0, 0, 0, 1]),
+        (json!(-(1i64 << 40)), vec![131, 110, 6, 1, 0, 0, 0, 0, 0, 1]),
+        (json!(1.5), vec![131, 70, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]),
+        (json!(""), vec![131, 109, 0, 0, 0, 0]),
+        (json!([]), vec![131, 106]),
+        (
+            json!([1, "ab"]),
+            vec![
+                131, 108, 0, 0, 0, 2, 97, 1, 109, 0, 0, 0, 2, b'a', b'b', 106,
+            ],
+        ),
+        (
+            json!({"a": 1}),
+            vec![131, 116, 0, 0, 0, 1, 109, 0, 0, 0, 1, b'a', 97, 1],
+        ),
+    ];
+    for (value, bytes) in cases {
+        assert_eq!(encode(&value).unwrap(), bytes, "encoding {value}");
+        assert_eq!(decode(&bytes).unwrap(), value, "decoding {value}");
+    }
+}
+
+#[test]
+fn decodes_terms_erlpack_clients_send() {
+    use eetf::{Atom, BigInteger, FixInteger, Map, Term, Tuple};
+    use std::collections::HashMap;
+    let mut m = HashMap::new();
+    m.insert(
+        Term::from(Atom::from("cmd")),
+        Term::from(eetf::Binary::from(&b"PING"[..])),
+    );
+    m.insert(
+        Term::from(Atom::from("nonce")),
+        Term::from(Atom::from("nil")),
+    );
+    m.insert(
+        Term::from(Atom::from("args")),
+        Term::from(Tuple::from(vec![
+            Term::from(FixInteger::from(1)),
+            Term::from(BigInteger::from(u64::MAX)),
+        ])),
+    );
+    let mut buf = Vec::new();
+    Term::from(Map::from(m)).encode(&mut buf).unwrap();
+    assert_eq!(
+        decode(&buf).unwrap(),
+        json!({"cmd": "PING", "nonce": null, "args": [1, u64::MAX]})
+    );
+}
+
+#[test]
+fn big_integers_beyond_u64_become_strings() {
+    // 2^64 as SMALL_BIG_EXT, positive and negative
+    let two_64 = [131, 110, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    assert_eq!(decode(&two_64).unwrap(), json!("18446744073709551616"));
+    let mut neg = two_64;
+    neg[3] = 1;
+    assert_eq!(decode(&neg).unwrap(), json!("-18446744073709551616"));
+    assert_eq!(
+        decode(&[131, 110, 8, 1, 0, 0, 0, 0, 0, 0, 0, 0x80]).unwrap(),
+        json!(i64::MIN)
+    );
+}
+
+#[test]
+fn rejects_malformed_input() {
+    assert_eq!(decode(&[]), Err(EtfError::UnexpectedEof));
+    assert_eq!(decode(&[130, 106]), Err(EtfError::BadVersion(130)));
+    assert_eq!(decode(&[131, 98, 0, 0]), Err(EtfError::UnexpectedEof));
+    assert_eq!(decode(&[131, 106, 106]), Err(EtfError::TrailingData(1)));
+    assert_eq!(decode(&[131, 102]), Err(EtfError::UnsupportedTag(102)));
+    assert_eq!(
+        decode(&[131, 108, 0, 0, 0, 1, 97, 1, 97, 2]),
+        Err(EtfError::ImproperList)
+    );
+    // A huge element count with no data must fail, not allocate
+    assert_eq!(
+        decode(&[131, 108, 255, 255, 255, 255]),
+        Err(EtfError::UnexpectedEof)
+    );
+    let mut deep = vec![131];
+    for _ in 0..1000 {
+        deep.extend_from_slice(&[108, 0, 0, 0, 1]);
+    }
+    assert_eq!(decode(&deep), Err(EtfError::TooDeep));
+}
+
+fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter("finite", |f| f.is_finite())
+            .prop_map(Value::from),
+        ".*".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+            prop::collection::btree_map(".*", inner, 0..8)
+                .prop_map(|m| Value::Object(m.into_iter().collect())),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn round_trips(v in arb_json()) {
+        let bin = encode(&v).unwrap();
+        prop_assert_eq!(decode(&bin).unwrap(), v);
+        // Output stays valid for other ETF implementations
+        prop_assert!(eetf::Term::decode(&bin[..]).is_ok());
+    }
+
+    #[test]
+    fn arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+        let mut bin = vec![131];
+        bin.extend(bytes);
+        let _ = decode(&bin);
+    }
+}
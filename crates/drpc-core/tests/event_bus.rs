@@ -1,4 +1,4 @@
-use drpc_core::{EventBus, EventKind};
+use drpc_core::{ActivityOrigin, EventBus, EventKind};
 use serde_json::json;
 
 #[test]
@@ -8,6 +8,7 @@ fn bus_publish_subscribe_and_metrics() {
     let mut rx2 = bus.subscribe();
     bus.publish(EventKind::ActivityUpdate {
         socket_id: "s1".into(),
+        origin: ActivityOrigin::Ws,
         payload: json!({"name":"X"}),
     });
     let e1 = rx1.try_recv().expect("rx1 evt");
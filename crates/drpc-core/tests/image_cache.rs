@@ -0,0 +1,299 @@
+use drpc_core::{ImageCache, ImageCacheOptions, check_public_url, is_public_ip, url_hash};
+
+fn temp_dir(tag: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "drpc-images-{tag}-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// The stub servers below are plain http on loopback
+fn options(dir: &std::path::Path, max_bytes: u64) -> ImageCacheOptions {
+    ImageCacheOptions {
+        dir: dir.to_path_buf(),
+        max_bytes,
+        allow_local: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn url_hash_is_stable() {
+    assert_eq!(
+        url_hash("https://example.com/a.png"),
+        url_hash("https://example.com/a.png")
+    );
+    assert_ne!(
+        url_hash("https://example.com/a.png"),
+        url_hash("https://example.com/b.png")
+    );
+    assert_eq!(url_hash(""), "e3b0c44298fc1c149afbf4c8996fb924");
+}
+
+#[test]
+fn reopens_existing_files_and_serves_them() {
+    let dir = temp_dir("reopen");
+    let hash = url_hash("https://example.com/a.png");
+    std::fs::write(dir.join(format!("{hash}.png")), b"png-bytes").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+    let cache = ImageCache::open(options(&dir, 1024)).unwrap();
+    assert_eq!(cache.total_bytes(), 9);
+    let img = cache.get(&hash).unwrap();
+    assert_eq!(img.content_type, "image/png");
+    assert_eq!(img.bytes, b"png-bytes");
+    assert!(cache.get("../../etc/passwd").is_none());
+    assert!(cache.local_url(&hash).is_none());
+    cache.set_base_url("http://127.0.0.1:9/");
+    assert_eq!(
+        cache.local_url(&hash).unwrap(),
+        format!("http://127.0.0.1:9/assets/{hash}")
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn open_evicts_oldest_files_over_the_limit() {
+    let dir = temp_dir("evict-open");
+    let old = url_hash("old");
+    let new = url_hash("new");
+    std::fs::write(dir.join(format!("{old}.png")), [0u8; 60]).unwrap();
+    let past = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .append(true)
+        .open(dir.join(format!("{old}.png")))
+        .unwrap()
+        .set_modified(past)
+        .unwrap();
+    std::fs::write(dir.join(format!("{new}.jpg")), [0u8; 60]).unwrap();
+    let cache = ImageCache::open(options(&dir, 100)).unwrap();
+    assert!(!cache.contains(&old));
+    assert!(cache.contains(&new));
+    assert!(!dir.join(format!("{old}.png")).exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn only_https_urls_on_public_hosts_are_fetched() {
+    let check = |url: &str| check_public_url(&url.parse().unwrap()).is_ok();
+    assert!(check("https://example.com/a.png"));
+    assert!(check("https://93.184.216.34/a.png"));
+    assert!(check("https://[2606:2800:220:1::1]/a.png"));
+    for url in [
+        "http://example.com/a.png",
+        "ftp://example.com/a.png",
+        "https://127.0.0.1/a.png",
+        "https://10.1.2.3/a.png",
+        "https://192.168.0.10:8443/a.png",
+        "https://169.254.169.254/latest",
+        "https://100.64.0.1/a.png",
+        "https://0.0.0.0/a.png",
+        "https://[::1]/a.png",
+        "https://[fd00::1]/a.png",
+        "https://[fe80::1]/a.png",
+        "https://[::ffff:127.0.0.1]/a.png",
+    ] {
+        assert!(!check(url), "{url}");
+    }
+    assert!(is_public_ip("8.8.8.8".parse().unwrap()));
+    assert!(!is_public_ip("172.16.0.1".parse().unwrap()));
+
+    let dir = temp_dir("accepts");
+    let cache = ImageCache::open(ImageCacheOptions {
+        dir: dir.clone(),
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(cache.accepts("https://example.com/a.png"));
+    assert!(!cache.accepts("http://127.0.0.1:3020/a.png"));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn localize_cached_uses_cached_images_and_reports_the_rest() {
+    let dir = temp_dir("cached");
+    let cached = "https://example.com/cached.png";
+    std::fs::write(dir.join(format!("{}.png", url_hash(cached))), b"png").unwrap();
+    let cache = std::sync::Arc::new(ImageCache::open(options(&dir, 1024)).unwrap());
+    let resolver = drpc_core::AssetResolver::default().with_image_cache(cache.clone());
+    let mut activity = serde_json::json!({"assets": {"large_image": cached}});
+    resolver.annotate(&mut activity);
+    // Nothing serves the cache yet, so there is nothing to point at
+    assert!(!resolver.localize_cached(&mut activity));
+    assert_eq!(activity["assets"]["large_image_url"], cached);
+
+    cache.set_base_url("http://127.0.0.1:3020");
+    assert!(!resolver.localize_cached(&mut activity));
+    assert_eq!(
+        activity["assets"]["large_image_url"],
+        format!("http://127.0.0.1:3020/assets/{}", url_hash(cached))
+    );
+    let mut activity = serde_json::json!({"assets": {
+        "large_image": cached,
+        "small_image": "https://example.com/new.png",
+    }});
+    resolver.annotate(&mut activity);
+    assert!(resolver.localize_cached(&mut activity));
+    assert_eq!(
+        activity["assets"]["small_image_url"],
+        "https://example.com/new.png"
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[cfg(feature = "network")]
+mod http {
+    use super::*;
+    use drpc_core::{AssetResolver, ImageCacheError};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // Serves `size` bytes of `content_type` for any path and counts requests
+    async fn cdn(content_type: &'static str, size: usize) -> (String, Arc<AtomicUsize>) {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = hits.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut sock, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match sock.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(k) => buf.extend_from_slice(&chunk[..k]),
+                    }
+                }
+                seen.fetch_add(1, Ordering::SeqCst);
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\nContent-Length: {size}\r\nConnection: close\r\n\r\n"
+                );
+                let _ = sock.write_all(head.as_bytes()).await;
+                let _ = sock.write_all(&vec![7u8; size]).await;
+                let _ = sock.shutdown().await;
+            }
+        });
+        (base, hits)
+    }
+
+    #[tokio::test]
+    async fn downloads_once_and_evicts_least_recently_used() {
+        let dir = temp_dir("lru");
+        let (base, hits) = cdn("image/png", 40).await;
+        let cache = ImageCache::open(options(&dir, 100)).unwrap();
+        let a = cache.fetch(&format!("{base}/a.png")).await.unwrap();
+        let b = cache.fetch(&format!("{base}/b.png")).await.unwrap();
+        assert_eq!(cache.fetch(&format!("{base}/a.png")).await.unwrap(), a);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        // `a` was used last, so `b` goes when `c` pushes the total past 100
+        let c = cache.fetch(&format!("{base}/c.png")).await.unwrap();
+        assert!(cache.contains(&a) && cache.contains(&c));
+        assert!(!cache.contains(&b));
+        assert_eq!(cache.total_bytes(), 80);
+        assert_eq!(cache.get(&c).unwrap().bytes, vec![7u8; 40]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn concurrent_fetches_of_one_url_store_one_complete_file() {
+        let dir = temp_dir("concurrent");
+        let (base, _) = cdn("image/png", 4096).await;
+        let cache = Arc::new(ImageCache::open(options(&dir, 1 << 20)).unwrap());
+        let url = format!("{base}/a.png");
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (cache, url) = (cache.clone(), url.clone());
+                tokio::spawn(async move { cache.fetch(&url).await.unwrap() })
+            })
+            .collect();
+        for t in tasks {
+            assert_eq!(t.await.unwrap(), url_hash(&url));
+        }
+        assert_eq!(cache.total_bytes(), 4096);
+        assert_eq!(cache.get(&url_hash(&url)).unwrap().bytes, vec![7u8; 4096]);
+        // No temp files left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn rejects_non_images_and_oversized_files() {
+        let dir = temp_dir("reject");
+        let (html, _) = cdn("text/html", 10).await;
+        let (big, _) = cdn("image/png", 64).await;
+        let cache = ImageCache::open(ImageCacheOptions {
+            max_image_bytes: 32,
+            ..options(&dir, 1024)
+        })
+        .unwrap();
+        assert!(matches!(
+            cache.fetch(&format!("{html}/x")).await,
+            Err(ImageCacheError::ContentType(_))
+        ));
+        assert!(matches!(
+            cache.fetch(&format!("{big}/x.png")).await,
+            Err(ImageCacheError::TooLarge(32))
+        ));
+        assert_eq!(cache.total_bytes(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn refuses_local_targets_by_default() {
+        let dir = temp_dir("ssrf");
+        let (base, hits) = cdn("image/png", 8).await;
+        let cache = ImageCache::open(ImageCacheOptions {
+            dir: dir.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let port = base.rsplit(':').next().unwrap();
+        for url in [
+            format!("{base}/a.png"),
+            format!("https://127.0.0.1:{port}/a.png"),
+        ] {
+            assert!(matches!(
+                cache.fetch(&url).await,
+                Err(ImageCacheError::Blocked(_))
+            ));
+        }
+        // Names are checked once resolved
+        assert!(
+            cache
+                .fetch(&format!("https://localhost:{port}/a.png"))
+                .await
+                .is_err()
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn resolver_points_image_urls_at_the_local_cache() {
+        let dir = temp_dir("localize");
+        let (base, _) = cdn("image/webp", 16).await;
+        let cache = Arc::new(ImageCache::open(options(&dir, 1024)).unwrap());
+        cache.set_base_url("http://127.0.0.1:3020");
+        let resolver = AssetResolver::default().with_image_cache(cache.clone());
+        let remote = format!("{base}/cover.webp");
+        let mut activity = serde_json::json!({"assets": {"large_image": remote}});
+        resolver.annotate(&mut activity);
+        resolver.localize(&mut activity).await;
+        let hash = url_hash(&remote);
+        assert_eq!(
+            activity["assets"]["large_image_url"],
+            format!("http://127.0.0.1:3020/assets/{hash}")
+        );
+        assert_eq!(cache.get(&hash).unwrap().content_type, "image/webp");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
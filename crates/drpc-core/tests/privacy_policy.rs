@@ -0,0 +1,379 @@
+use drpc_core::{
+    ActivityOrigin, EventBus, EventKind, PrivacyError, PrivacyPolicy, PrivacySettings,
+    load_privacy, save_privacy,
+};
+use serde_json::json;
+
+fn update(socket_id: &str, origin: ActivityOrigin, name: &str) -> EventKind {
+    EventKind::ActivityUpdate {
+        socket_id: socket_id.into(),
+        origin,
+        payload: json!({"name": name}),
+    }
+}
+
+fn drain(rx: &mut tokio::sync::mpsc::UnboundedReceiver<EventKind>) -> Vec<String> {
+    let mut out = Vec::new();
+    while let Ok(evt) = rx.try_recv() {
+        out.push(match evt {
+            EventKind::ActivityUpdate { socket_id, .. } => format!("update {socket_id}"),
+            EventKind::Clear { socket_id } => format!("clear {socket_id}"),
+            EventKind::PrivacyRefresh => "refresh".into(),
+            other => format!("{other:?}"),
+        });
+    }
+    out
+}
+
+fn lists(allow: Option<&[&str]>, deny: Option<&[&str]>) -> PrivacySettings {
+    let own = |l: &[&str]| l.iter().map(|s| s.to_string()).collect();
+    PrivacySettings {
+        allow: allow.map(own),
+        deny: deny.map(own),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn lists_match_case_insensitive_substrings_and_deny_wins() {
+    let l = lists(Some(&["craft"]), Some(&["secret"]));
+    assert!(l.allows("Minecraft"));
+    assert!(!l.allows("Secret Craft"));
+    assert!(!l.allows("Other Game"));
+    assert!(PrivacySettings::default().allows("Anything"));
+}
+
+#[test]
+fn denied_activities_are_dropped_for_every_origin() {
+    let bus = EventBus::with_privacy(PrivacyPolicy::new(lists(None, Some(&["hidden"]))).unwrap());
+    let mut rx = bus.subscribe();
+    for (i, origin) in [
+        ActivityOrigin::Ipc,
+        ActivityOrigin::Ws,
+        ActivityOrigin::Rest,
+        ActivityOrigin::Process,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        bus.publish(update(&format!("h{i}"), origin, "Hidden Game"));
+    }
+    bus.publish(update("ok", ActivityOrigin::Ipc, "Fine Game"));
+    assert_eq!(drain(&mut rx), ["update ok"]);
+}
+
+#[test]
+fn refresh_clears_activities_that_are_now_denied() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    bus.publish(update("a", ActivityOrigin::Ipc, "Alpha"));
+    bus.publish(update("b", ActivityOrigin::Process, "Beta"));
+    bus.publish(update("c", ActivityOrigin::Ws, "Gamma"));
+    bus.publish(EventKind::Clear {
+        socket_id: "c".into(),
+    });
+    drain(&mut rx);
+
+    bus.privacy()
+        .set(lists(None, Some(&["beta", "gamma"])))
+        .unwrap();
+    bus.publish(EventKind::PrivacyRefresh);
+    // Already cleared sockets aren't cleared again
+    assert_eq!(drain(&mut rx), ["clear b", "refresh"]);
+
+    // A visible socket switching to a denied activity is cleared instead of updated
+    bus.publish(update("a", ActivityOrigin::Ipc, "Beta"));
+    bus.publish(update("a", ActivityOrigin::Ipc, "Beta"));
+    assert_eq!(drain(&mut rx), ["clear a"]);
+}
+
+#[test]
+fn set_persists_lists_without_touching_other_settings() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("drpc-privacy-{nanos}"));
+    let path = dir.join("config.toml");
+    assert_eq!(load_privacy(&path).unwrap(), PrivacySettings::default());
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&path, "bridge_port = 1400\n").unwrap();
+    let policy = PrivacyPolicy::default().with_config_path(&path);
+    let wanted = lists(Some(&["Alpha"]), Some(&[]));
+    policy.set(wanted.clone()).unwrap();
+    assert_eq!(load_privacy(&path).unwrap(), wanted);
+    let raw: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(raw["bridge_port"].as_integer(), Some(1400));
+
+    save_privacy(&path, &PrivacySettings::default()).unwrap();
+    assert_eq!(load_privacy(&path).unwrap(), PrivacySettings::default());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn save_keeps_comments_and_layout_outside_the_privacy_section() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("drpc-privacy-edit-{nanos}"));
+    let path = dir.join("config.toml");
+    std::fs::create_dir_all(&dir).unwrap();
+    let original = "\
+# drpc settings
+bridge_port = 1400 # moved off 1337
+
+# Hide work stuff
+[privacy]
+deny = [\"old\"]
+
+[extra]
+# keep me
+key = \"value\"
+";
+    std::fs::write(&path, original).unwrap();
+    let wanted = PrivacySettings {
+        deny: Some(vec!["secret".into()]),
+        rules: serde_json::from_value(json!([
+            {"match": {"client_id": "7"}, "action": "redact", "redact": {"hide_details": true}},
+        ]))
+        .unwrap(),
+        ..Default::default()
+    };
+    save_privacy(&path, &wanted).unwrap();
+    assert_eq!(load_privacy(&path).unwrap(), wanted);
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert!(written.starts_with("# drpc settings\nbridge_port = 1400 # moved off 1337\n"));
+    assert!(written.contains("# Hide work stuff\n[privacy]\n"));
+    assert!(!written.contains("old"));
+    // The new section takes the old one's place, ahead of `[extra]`
+    let privacy = written.find("[privacy]").unwrap();
+    let rule = written.find("[[privacy.rules]]").unwrap();
+    let extra = written.find("[extra]\n# keep me\nkey = \"value\"").unwrap();
+    assert!(privacy < rule && rule < extra, "{written}");
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn rules(json: serde_json::Value) -> PrivacySettings {
+    PrivacySettings {
+        rules: serde_json::from_value(json).unwrap(),
+        ..Default::default()
+    }
+}
+
+fn rich(name: &str, app: &str) -> serde_json::Value {
+    json!({
+        "name": name,
+        "application_id": app,
+        "details": "In a ranked match",
+        "details_url": "https://example.com/match",
+        "state": "Solo",
+        "party": {"id": "p1", "size": [1, 4]},
+        "secrets": {"join": "abc"},
+        "buttons": ["Watch"],
+        "metadata": {"button_urls": ["https://example.com/watch"]},
+    })
+}
+
+#[test]
+fn first_matching_rule_decides_before_the_lists() {
+    let mut settings = rules(json!([
+        {"match": {"client_id": "111"}, "action": "allow"},
+        {"match": {"name": "(?i)^secret"}, "action": "deny"},
+        {"match": {"origin": "process", "executable": "^steam"}, "action": "deny"},
+    ]));
+    settings.deny = Some(vec!["game".into()]);
+    let policy = PrivacyPolicy::new(settings).unwrap();
+    let bus = EventBus::with_privacy(policy);
+    let mut rx = bus.subscribe();
+    let publish = |sid: &str, origin, payload| {
+        bus.publish(EventKind::ActivityUpdate {
+            socket_id: sid.into(),
+            origin,
+            payload,
+        })
+    };
+    // Allowed by client id even though the deny list matches the name
+    publish("rpc", ActivityOrigin::Ipc, rich("Secret Game", "111"));
+    // A REST activity's application_id isn't an RPC client id
+    publish("rest", ActivityOrigin::Rest, rich("Secret Plan", "111"));
+    publish(
+        "proc",
+        ActivityOrigin::Process,
+        json!({"name": "Tool", "metadata": {"executable": "steamwebhelper"}}),
+    );
+    publish("other", ActivityOrigin::Ws, rich("Other", "222"));
+    publish("listed", ActivityOrigin::Ws, rich("Some Game", "222"));
+    assert_eq!(drain(&mut rx), ["update rpc", "update other"]);
+
+    let verdicts = bus.privacy().preview(None).unwrap();
+    let summary: Vec<_> = verdicts
+        .iter()
+        .map(|v| (v.socket_id.as_str(), v.rule, v.activity.is_some()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("listed", None, false),
+            ("other", None, true),
+            ("proc", Some(2), false),
+            ("rest", Some(1), false),
+            ("rpc", Some(0), true),
+        ]
+    );
+}
+
+#[test]
+fn redact_rules_mask_fields() {
+    let bus = EventBus::with_privacy(
+        PrivacyPolicy::new(rules(json!([{
+            "match": {"application_id": "42"},
+            "action": "redact",
+            "redact": {
+                "hide_details": true,
+                "hide_state": true,
+                "replace_name": "Playing a game",
+                "strip_buttons": true,
+                "strip_secrets": true,
+                "remove_party": true,
+            },
+        }])))
+        .unwrap(),
+    );
+    let mut rx = bus.subscribe();
+    bus.publish(update("plain", ActivityOrigin::Ipc, "Untouched"));
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "a".into(),
+        origin: ActivityOrigin::Ws,
+        payload: rich("Real Name", "42"),
+    });
+    let _ = rx.try_recv();
+    let Ok(EventKind::ActivityUpdate { payload, .. }) = rx.try_recv() else {
+        panic!("expected an update");
+    };
+    assert_eq!(payload, json!({"name": "Playing a game", "metadata": {}}));
+}
+
+#[test]
+fn replaced_names_take_the_game_art_and_executable_along() {
+    let bus = EventBus::with_privacy(
+        PrivacyPolicy::new(rules(json!([{
+            "match": {"executable": "^hl2"},
+            "action": "redact",
+            "redact": {"replace_name": "Playing a game"},
+        }])))
+        .unwrap(),
+    );
+    let mut rx = bus.subscribe();
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "proc-1".into(),
+        origin: ActivityOrigin::Process,
+        payload: json!({
+            "name": "Half-Life 2",
+            "type": 0,
+            "application_id": "356870004441153536",
+            "timestamps": {"start": 1_700_000_000_000u64},
+            "assets": {"large_image": "https://cdn.example.com/hl2.png", "large_text": "Half-Life 2"},
+            "metadata": {"executable": "hl2_linux"},
+        }),
+    });
+    let Ok(EventKind::ActivityUpdate { payload, .. }) = rx.try_recv() else {
+        panic!("expected an update");
+    };
+    assert_eq!(
+        payload,
+        json!({
+            "name": "Playing a game",
+            "type": 0,
+            "timestamps": {"start": 1_700_000_000_000u64},
+            "metadata": {},
+        })
+    );
+}
+
+#[test]
+fn refresh_reapplies_rules_to_current_activities() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "a".into(),
+        origin: ActivityOrigin::Ipc,
+        payload: rich("Alpha", "1"),
+    });
+    bus.publish(update("b", ActivityOrigin::Ws, "Beta"));
+    drain(&mut rx);
+
+    let redact =
+        json!([{"match": {"name": "Alpha"}, "action": "redact", "redact": {"hide_details": true}}]);
+    let redact_and_deny = json!([
+        {"match": {"name": "Alpha"}, "action": "redact", "redact": {"hide_details": true}},
+        {"match": {"name": "Beta"}, "action": "deny"},
+    ]);
+    bus.privacy().set(rules(redact_and_deny.clone())).unwrap();
+    bus.publish(EventKind::PrivacyRefresh);
+    let mut events = Vec::new();
+    while let Ok(evt) = rx.try_recv() {
+        events.push(evt);
+    }
+    match &events[..] {
+        [
+            EventKind::ActivityUpdate {
+                socket_id, payload, ..
+            },
+            EventKind::Clear { socket_id: cleared },
+            EventKind::PrivacyRefresh,
+        ] => {
+            assert_eq!(socket_id, "a");
+            assert!(payload.get("details").is_none());
+            assert_eq!(cleared, "b");
+        }
+        other => panic!("unexpected events {other:?}"),
+    }
+
+    // The original activity is kept, so relaxing the rules brings it back in full
+    bus.privacy().set(rules(redact)).unwrap();
+    bus.publish(EventKind::PrivacyRefresh);
+    assert_eq!(drain(&mut rx), ["update b", "refresh"]);
+
+    // A dry run with other settings doesn't change anything
+    let preview = bus.privacy().preview(Some(rules(redact_and_deny))).unwrap();
+    assert!(preview[1].activity.is_none());
+    assert_eq!(drain(&mut rx), Vec::<String>::new());
+}
+
+#[test]
+fn invalid_rules_are_rejected_without_changes() {
+    let policy = PrivacyPolicy::new(lists(None, Some(&["x"]))).unwrap();
+    let err = policy
+        .set(rules(json!([
+            {"match": {"name": "ok"}, "action": "allow"},
+            {"match": {"executable": "("}, "action": "deny"},
+        ])))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        PrivacyError::InvalidRule {
+            index: 1,
+            field: "match.executable",
+            ..
+        }
+    ));
+    assert_eq!(policy.settings().deny, Some(vec!["x".to_string()]));
+}
+
+#[test]
+fn rules_round_trip_through_the_config_file() {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let path = std::env::temp_dir().join(format!("drpc-privacy-rules-{nanos}.toml"));
+    let settings = rules(json!([
+        {"match": {"origin": "ipc", "name": "^Work"}, "action": "redact", "redact": {"hide_details": true}},
+        {"action": "allow"},
+    ]));
+    save_privacy(&path, &settings).unwrap();
+    assert_eq!(load_privacy(&path).unwrap(), settings);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,111 @@
+use drpc_core::{
+    AuthorizeArgs, IncomingFrame, OutgoingFrame, RpcCloseCode, RpcCommand, RpcErrorCode, RpcEvent,
+    SetActivityArgs, SubscribeArgs,
+};
+use serde_json::json;
+
+#[test]
+fn commands_roundtrip_and_keep_unknown() {
+    for (wire, cmd) in [
+        ("AUTHORIZE", RpcCommand::Authorize),
+        ("SUBSCRIBE", RpcCommand::Subscribe),
+        (
+            "GET_SELECTED_VOICE_CHANNEL",
+            RpcCommand::GetSelectedVoiceChannel,
+        ),
+        ("SET_ACTIVITY", RpcCommand::SetActivity),
+    ] {
+        let parsed: RpcCommand = serde_json::from_value(json!(wire)).unwrap();
+        assert_eq!(parsed, cmd);
+        assert_eq!(serde_json::to_value(&cmd).unwrap(), json!(wire));
+    }
+    assert_eq!(RpcCommand::from("set_activity"), RpcCommand::SetActivity);
+    let unknown: RpcCommand = serde_json::from_value(json!("FROBNICATE")).unwrap();
+    assert_eq!(unknown, RpcCommand::Unknown("FROBNICATE".into()));
+    assert_eq!(serde_json::to_value(&unknown).unwrap(), json!("FROBNICATE"));
+}
+
+#[test]
+fn subscribable_events() {
+    assert!(RpcEvent::GuildStatus.is_subscribable());
+    assert!(RpcEvent::ActivityJoinRequest.is_subscribable());
+    assert!(!RpcEvent::Ready.is_subscribable());
+    assert!(!RpcEvent::from("XYZ").is_subscribable());
+}
+
+#[test]
+fn incoming_frame_is_lenient() {
+    let f = IncomingFrame::from_value(json!({"cmd": "PING", "nonce": 7}));
+    assert_eq!(f.cmd, RpcCommand::Ping);
+    assert_eq!(f.nonce.as_deref(), Some("7"));
+
+    // cmd of the wrong type still yields an answerable frame
+    let f = IncomingFrame::from_value(json!({"cmd": 5, "nonce": "n"}));
+    assert_eq!(f.cmd, RpcCommand::Unknown(String::new()));
+    assert_eq!(f.nonce.as_deref(), Some("n"));
+}
+
+#[test]
+fn typed_args() {
+    let f = IncomingFrame::from_value(json!({
+        "cmd": "SET_ACTIVITY",
+        "args": {"pid": 12, "activity": {"name": "Game", "state": "Lobby"}},
+    }));
+    let args: SetActivityArgs = f.args().unwrap();
+    assert_eq!(args.pid, Some(12));
+    assert_eq!(args.activity.unwrap().state.as_deref(), Some("Lobby"));
+
+    let f = IncomingFrame::from_value(json!({"cmd": "AUTHORIZE"}));
+    let args: AuthorizeArgs = f.args().unwrap();
+    assert!(args.client_id.is_none() && args.scopes.is_none());
+
+    let f = IncomingFrame::from_value(
+        json!({"cmd": "SUBSCRIBE", "args": {"event": "VOICE_STATE_UPDATE", "channel_id": "1"}}),
+    );
+    let args: SubscribeArgs = f.args().unwrap();
+    assert_eq!(args.event, Some(RpcEvent::VoiceStateUpdate));
+}
+
+#[test]
+fn outgoing_error_shape() {
+    let v = OutgoingFrame::error(
+        RpcCommand::Subscribe,
+        RpcErrorCode::InvalidPayload,
+        "bad",
+        Some("n1".into()),
+    )
+    .to_value();
+    assert_eq!(
+        v,
+        json!({"cmd": "SUBSCRIBE", "evt": "ERROR", "data": {"code": 4000, "message": "bad"}, "nonce": "n1"})
+    );
+}
+
+#[test]
+fn error_codes_use_discord_numbers() {
+    assert_eq!(
+        serde_json::to_value(RpcErrorCode::InvalidCommand).unwrap(),
+        json!(4002)
+    );
+    assert_eq!(
+        serde_json::from_value::<RpcErrorCode>(json!(4004)).unwrap(),
+        RpcErrorCode::InvalidEvent
+    );
+    assert!(serde_json::from_value::<RpcErrorCode>(json!(4001)).is_err());
+    assert_eq!(u16::from(RpcCloseCode::InvalidEncoding), 4005);
+    assert_eq!(
+        RpcCloseCode::try_from(4001),
+        Ok(RpcCloseCode::InvalidOrigin)
+    );
+}
+
+#[test]
+fn error_reply_echoes_nonce() {
+    let f = IncomingFrame::from_value(json!({"cmd": "GET_GUILD", "nonce": "g1"}));
+    let out = f.error(RpcErrorCode::InvalidGuild, "Invalid guild");
+    assert_eq!(out.cmd, RpcCommand::GetGuild);
+    assert_eq!(out.evt, Some(RpcEvent::Error));
+    assert_eq!(out.nonce.as_deref(), Some("g1"));
+    let data: drpc_core::ErrorData = serde_json::from_value(out.data).unwrap();
+    assert_eq!(data.code, RpcErrorCode::InvalidGuild);
+}
@@ -0,0 +1,58 @@
+use drpc_core::{
+    CodecError, JsonCodec, OutgoingFrame, RpcEvent, WireCodec, WireMessage, negotiate_codec,
+};
+use serde_json::json;
+
+#[test]
+fn json_codec_roundtrips_frames() {
+    let codec = JsonCodec;
+    let frame = OutgoingFrame::dispatch(RpcEvent::Ready, json!({"v": 1}));
+    let msg = codec.encode_frame(&frame).unwrap();
+    let WireMessage::Text(txt) = &msg else {
+        panic!("json codec produced binary");
+    };
+    assert!(txt.contains("\"READY\""));
+    let back = codec.decode_value(&msg).unwrap();
+    assert_eq!(back["data"]["v"], 1);
+}
+
+#[test]
+fn json_codec_rejects_binary() {
+    let err = JsonCodec
+        .decode_value(&WireMessage::Binary(vec![131, 106]))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        CodecError::UnexpectedMessage { codec: "json", .. }
+    ));
+}
+
+#[test]
+fn negotiates_known_encodings() {
+    assert_eq!(negotiate_codec("json").unwrap().name(), "json");
+    assert_eq!(negotiate_codec("JSON").unwrap().name(), "json");
+    assert!(matches!(
+        negotiate_codec("msgpack"),
+        Err(CodecError::UnsupportedEncoding(_))
+    ));
+    #[cfg(feature = "etf")]
+    assert_eq!(negotiate_codec("etf").unwrap().name(), "etf");
+    #[cfg(not(feature = "etf"))]
+    assert!(negotiate_codec("etf").is_err());
+}
+
+#[cfg(feature = "etf")]
+#[test]
+fn etf_codec_roundtrips_and_rejects_text() {
+    let codec = negotiate_codec("etf").unwrap();
+    let v = json!({"cmd": "SET_ACTIVITY", "args": {"pid": 42}, "nonce": "6f1c1d4e-0c9a-4c47-9f2e-7f1f2c6d8a10"});
+    let msg = codec.encode_value(&v).unwrap();
+    assert!(matches!(msg, WireMessage::Binary(_)));
+    assert_eq!(codec.decode_value(&msg).unwrap(), v);
+    let frame = codec.decode_frame(&msg).unwrap();
+    assert_eq!(
+        frame.nonce.as_deref(),
+        Some("6f1c1d4e-0c9a-4c47-9f2e-7f1f2c6d8a10")
+    );
+    assert!(codec.decode_value(&WireMessage::Text("{}".into())).is_err());
+}
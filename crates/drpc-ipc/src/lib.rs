@@ -1,11 +1,13 @@
 use drpc_core::{
-    Activity, EventBus, EventKind, IpcOp, MockUser, ReadyConfig, ReadyEvent, decode_frame,
-    encode_frame,
+    ActivityOrigin, EventBus, EventKind, IncomingFrame, IpcOp, MockUser, OutgoingFrame, RawFrame,
+    ReadyConfig, ReadyEvent, RpcCommand, RpcErrorCode, RpcEvent, SetActivityArgs, Subscriptions,
+    decode_frame, encode_frame, validate_activity,
 };
 use serde_json::json;
 use thiserror::Error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tracing::{debug, info, info_span, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::{Instrument, debug, info, info_span, warn};
 
 #[derive(Debug, Error)]
 pub enum IpcServerError {
@@ -73,7 +75,7 @@ impl IpcServer {
                         Ok(()) => {
                             debug!(path=%name, "ipc client connected (win)");
                             let bus = bus_clone.clone();
-                            tokio::spawn(handle_client_windows(server, bus));
+                            tokio::spawn(handle_client(server, bus));
                             // continue loop with a fresh instance next iteration
                             next = None;
                         }
@@ -94,217 +96,181 @@ impl IpcServer {
     }
 }
 
-async fn handle_client(mut stream: tokio::net::UnixStream, bus: EventBus) {
-    let mut handshook = false;
+// Serves one client over a Unix socket or a Windows named pipe
+async fn handle_client<S>(stream: S, bus: EventBus)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
     let socket_id = uuid::Uuid::new_v4().to_string();
-    drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     let span = info_span!("ipc_connection", %socket_id);
-    let _enter = span.enter();
+    drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    serve_frames(stream, &bus, &socket_id)
+        .instrument(span)
+        .await;
+    bus.publish(EventKind::Clear { socket_id });
+    drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+}
+
+async fn serve_frames<S>(stream: S, bus: &EventBus, socket_id: &str)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let (mut frames, reader) = spawn_reader(reader);
+    let mut events = bus.subscribe();
+    let mut handshook = false;
+    let mut client_id: Option<String> = None;
+    let mut subscriptions = Subscriptions::default();
     loop {
-        let mut header = [0u8; 8];
-        if let Err(e) = stream.read_exact(&mut header).await {
-            debug!(error=?e, "client closed");
-            break;
-        }
-        let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
-        let mut body = vec![0u8; len];
-        if let Err(e) = stream.read_exact(&mut body).await {
-            debug!(error=?e, "client closed mid-body");
-            break;
-        }
-        if len > 64 * 1024 {
-            debug!("payload too large; closing");
-            break;
-        }
-        let mut full = Vec::from(header);
-        full.extend_from_slice(&body);
-        match decode_frame(&full) {
-            Ok(frame) => {
-                if !handshook {
-                    if frame.op != IpcOp::Handshake {
-                        debug!("expected handshake first");
-                        break;
-                    }
-                    handshook = true;
-                    debug!("handshake accepted");
-                    // respond with READY DISPATCH full payload
-                    let ready_data = ReadyEvent {
-                        v: 1,
-                        config: ReadyConfig::default(),
-                        user: MockUser::default(),
-                    };
-                    let ready = json!({"cmd":"DISPATCH","evt":"READY","data": ready_data});
-                    let buf = encode_frame(IpcOp::Frame, &ready);
-                    let _ = stream.write_all(&buf).await;
-                } else {
-                    debug!(op=?frame.op, body=?frame.body, "frame");
-                    match frame.op {
-                        IpcOp::Ping => {
-                            let buf = encode_frame(IpcOp::Pong, &json!({}));
-                            let _ = stream.write_all(&buf).await;
-                        }
-                        IpcOp::Frame => {
-                            if let Some(cmd) = frame.body.get("cmd").and_then(|c| c.as_str())
-                                && cmd.eq_ignore_ascii_case("SET_ACTIVITY")
-                            {
-                                // Validation
-                                if frame.body.get("args").is_none() {
-                                    let out = json!({"cmd":"SET_ACTIVITY","evt":"ERROR","data":{"code":4000,"message":"Invalid payload: missing args"}});
-                                    let _ =
-                                        stream.write_all(&encode_frame(IpcOp::Frame, &out)).await;
-                                    continue;
-                                }
-                                let args_root = frame.body.get("args").unwrap();
-                                if let Some(btns) = args_root
-                                    .get("activity")
-                                    .and_then(|a| a.get("buttons"))
-                                    .and_then(|b| b.as_array())
-                                    && btns.len() > 2
-                                {
-                                    let out = json!({"cmd":"SET_ACTIVITY","evt":"ERROR","data":{"code":4002,"message":"Invalid payload: max 2 buttons"}});
-                                    let _ =
-                                        stream.write_all(&encode_frame(IpcOp::Frame, &out)).await;
-                                    continue;
-                                }
-                                if let Some(act_json) = args_root.get("activity")
-                                    && let Ok(activity) =
-                                        serde_json::from_value::<Activity>(act_json.clone())
-                                {
-                                    let norm = activity.normalize();
-                                    let pid = args_root
-                                        .get("pid")
-                                        .and_then(|p| p.as_u64())
-                                        .map(|p| p as u32);
-                                    let nonce = frame.body.get("nonce").cloned();
-                                    let out = json!({
-                                        "cmd":"DISPATCH",
-                                        "evt":"ACTIVITY_UPDATE",
-                                        "data": {"activity": norm},
-                                        "pid": pid,
-                                        "nonce": nonce,
-                                    });
-                                    let buf = encode_frame(IpcOp::Frame, &out);
-                                    let _ = stream.write_all(&buf).await;
-                                    bus.publish(EventKind::ActivityUpdate {
-                                        socket_id: socket_id.clone(),
-                                        payload: serde_json::to_value(norm).unwrap_or(json!({})),
-                                    });
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
+        let frame = tokio::select! {
+            frame = frames.recv() => match frame {
+                Some(f) => f,
+                None => break,
+            },
+            Some(evt) = events.recv() => {
+                if let Some(out) = subscriptions.dispatch(socket_id, &evt) {
+                    let buf = encode_frame(IpcOp::Frame, &out.to_value());
+                    let _ = writer.write_all(&buf).await;
                 }
+                continue;
             }
-            Err(e) => {
-                debug!(error=?e, "decode error");
+        };
+        if !handshook {
+            if frame.op != IpcOp::Handshake {
+                debug!("expected handshake first");
                 break;
             }
+            handshook = true;
+            client_id = frame
+                .body
+                .get("client_id")
+                .and_then(|c| c.as_str())
+                .map(str::to_owned);
+            debug!(?client_id, "handshake accepted");
+            // respond with READY DISPATCH full payload
+            let ready_data = ReadyEvent {
+                v: 1,
+                config: ReadyConfig::default(),
+                user: MockUser::default(),
+            };
+            let ready = OutgoingFrame::dispatch(RpcEvent::Ready, ready_data);
+            let buf = encode_frame(IpcOp::Frame, &ready.to_value());
+            let _ = writer.write_all(&buf).await;
+        } else {
+            debug!(op=?frame.op, body=?frame.body, "frame");
+            match frame.op {
+                IpcOp::Ping => {
+                    let buf = encode_frame(IpcOp::Pong, &json!({}));
+                    let _ = writer.write_all(&buf).await;
+                }
+                IpcOp::Frame => {
+                    let req = IncomingFrame::from_value(frame.body);
+                    let out = match req.cmd {
+                        RpcCommand::SetActivity => match set_activity(&req, client_id.as_deref()) {
+                            Ok((out, payload)) => {
+                                let buf = encode_frame(IpcOp::Frame, &out.to_value());
+                                let _ = writer.write_all(&buf).await;
+                                bus.publish(EventKind::ActivityUpdate {
+                                    socket_id: socket_id.to_string(),
+                                    origin: ActivityOrigin::Ipc,
+                                    payload,
+                                });
+                                continue;
+                            }
+                            Err(out) => out,
+                        },
+                        RpcCommand::Subscribe | RpcCommand::Unsubscribe => {
+                            subscriptions.handle(&req)
+                        }
+                        _ => req.error(RpcErrorCode::InvalidCommand, "Invalid command"),
+                    };
+                    let buf = encode_frame(IpcOp::Frame, &out.to_value());
+                    let _ = writer.write_all(&buf).await;
+                }
+                _ => {}
+            }
         }
     }
-    bus.publish(EventKind::Clear { socket_id });
-    drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    reader.abort();
 }
 
-#[cfg(windows)]
-async fn handle_client_windows(
-    mut stream: tokio::net::windows::named_pipe::NamedPipeServer,
-    bus: EventBus,
-) {
-    let mut handshook = false;
-    let socket_id = uuid::Uuid::new_v4().to_string();
-    let span = info_span!("ipc_connection", %socket_id);
-    let _enter = span.enter();
-    loop {
-        let mut header = [0u8; 8];
-        if let Err(e) = stream.read_exact(&mut header).await {
-            debug!(error=?e, "client closed");
-            break;
-        }
-        let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
-        let mut body = vec![0u8; len];
-        if let Err(e) = stream.read_exact(&mut body).await {
-            debug!(error=?e, "client closed mid-body");
-            break;
-        }
-        if len > 64 * 1024 {
-            debug!("payload too large; closing");
-            break;
-        }
-        let mut full = Vec::from(header);
-        full.extend_from_slice(&body);
-        match decode_frame(&full) {
-            Ok(frame) => {
-                if !handshook {
-                    if frame.op != IpcOp::Handshake {
-                        debug!("expected handshake first");
-                        break;
-                    }
-                    handshook = true;
-                    debug!("handshake accepted");
-                    // respond with READY DISPATCH full payload
-                    let ready_data = ReadyEvent {
-                        v: 1,
-                        config: ReadyConfig::default(),
-                        user: MockUser::default(),
-                    };
-                    let ready = json!({"cmd":"DISPATCH","evt":"READY","data": ready_data});
-                    let buf = encode_frame(IpcOp::Frame, &ready);
-                    let _ = stream.write_all(&buf).await;
-                } else {
-                    debug!(op=?frame.op, body=?frame.body, "frame");
-                    match frame.op {
-                        IpcOp::Ping => {
-                            let buf = encode_frame(IpcOp::Pong, &json!({}));
-                            let _ = stream.write_all(&buf).await;
-                        }
-                        IpcOp::Frame => {
-                            if let Some(cmd) = frame.body.get("cmd").and_then(|c| c.as_str()) {
-                                if cmd.eq_ignore_ascii_case("SET_ACTIVITY") {
-                                    if let Some(args) =
-                                        frame.body.get("args").and_then(|a| a.get("activity"))
-                                    {
-                                        if let Ok(activity) =
-                                            serde_json::from_value::<Activity>(args.clone())
-                                        {
-                                            let norm = activity.normalize();
-                                            let pid = frame
-                                                .body
-                                                .get("args")
-                                                .and_then(|a| a.get("pid"))
-                                                .and_then(|p| p.as_u64())
-                                                .map(|p| p as u32);
-                                            let nonce = frame.body.get("nonce").cloned();
-                                            let out = json!({
-                                                "cmd":"DISPATCH",
-                                                "evt":"ACTIVITY_UPDATE",
-                                                "data":{"activity":norm},
-                                                "pid": pid,
-                                                "nonce": nonce,
-                                            });
-                                            let buf = encode_frame(IpcOp::Frame, &out);
-                                            let _ = stream.write_all(&buf).await;
-                                            bus.publish(EventKind::ActivityUpdate {
-                                                socket_id: socket_id.clone(),
-                                                payload: serde_json::to_value(norm)
-                                                    .unwrap_or(json!({})),
-                                            });
-                                        }
-                                    }
-                                }
-                            }
+// Frames are read on their own task so a connection can wait on bus events at the same time;
+// the channel closes once the client is gone or sends something unreadable
+fn spawn_reader<R>(mut reader: R) -> (mpsc::Receiver<RawFrame>, tokio::task::JoinHandle<()>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(16);
+    let task = tokio::spawn(
+        async move {
+            loop {
+                let mut header = [0u8; 8];
+                if let Err(e) = reader.read_exact(&mut header).await {
+                    debug!(error=?e, "client closed");
+                    break;
+                }
+                let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+                if len > 64 * 1024 {
+                    debug!("payload too large; closing");
+                    break;
+                }
+                let mut body = vec![0u8; len];
+                if let Err(e) = reader.read_exact(&mut body).await {
+                    debug!(error=?e, "client closed mid-body");
+                    break;
+                }
+                let mut full = Vec::from(header);
+                full.extend_from_slice(&body);
+                match decode_frame(&full) {
+                    Ok(frame) => {
+                        if tx.send(frame).await.is_err() {
+                            break;
                         }
-                        _ => {}
+                    }
+                    Err(e) => {
+                        debug!(error=?e, "decode error");
+                        break;
                     }
                 }
             }
-            Err(e) => {
-                debug!(error=?e, "decode error");
-                break;
-            }
         }
+        .in_current_span(),
+    );
+    (rx, task)
+}
+
+// SET_ACTIVITY reply frame plus the normalized activity to publish, or the ERROR frame
+fn set_activity(
+    req: &IncomingFrame,
+    client_id: Option<&str>,
+) -> Result<(OutgoingFrame, serde_json::Value), OutgoingFrame> {
+    let err = |message| req.error(RpcErrorCode::InvalidPayload, message);
+    if req.args.is_null() {
+        return Err(err("Invalid payload: missing args"));
     }
-    bus.publish(EventKind::Clear { socket_id });
+    let Some(act) = req.args.get("activity") else {
+        return Err(err("Invalid payload: missing activity"));
+    };
+    if let Err(e) = validate_activity(act) {
+        return Err(OutgoingFrame::invalid_activity(
+            req.cmd.clone(),
+            &e,
+            req.nonce.clone(),
+        ));
+    }
+    let Ok(SetActivityArgs {
+        pid,
+        activity: Some(activity),
+    }) = req.args::<SetActivityArgs>()
+    else {
+        return Err(err("Invalid payload: invalid activity"));
+    };
+    let norm = activity.with_application_id(client_id).normalize();
+    let payload = serde_json::to_value(&norm).unwrap_or(json!({}));
+    let out = OutgoingFrame::dispatch(RpcEvent::ActivityUpdate, json!({"activity": norm}))
+        .with_nonce(req.nonce.clone())
+        .with_pid(pid);
+    Ok((out, payload))
 }
 
 #[cfg(unix)]
@@ -539,7 +505,7 @@ mod tests {
                 .get("data")
                 .and_then(|d| d.get("code"))
                 .and_then(|c| c.as_i64()),
-            Some(4000)
+            Some(RpcErrorCode::InvalidPayload as i64)
         );
     }
 
@@ -602,7 +568,146 @@ mod tests {
                 .get("data")
                 .and_then(|d| d.get("code"))
                 .and_then(|c| c.as_i64()),
-            Some(4002)
+            Some(RpcErrorCode::InvalidPayload as i64)
         );
     }
+
+    #[tokio::test]
+    async fn subscribe_then_receive_activity_join() {
+        async fn read(client: &mut tokio::net::UnixStream) -> serde_json::Value {
+            let mut header = [0u8; 8];
+            client.read_exact(&mut header).await.unwrap();
+            let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+            let mut body = vec![0u8; len];
+            client.read_exact(&mut body).await.unwrap();
+            let mut full = Vec::from(header);
+            full.extend_from_slice(&body);
+            decode_frame(&full).unwrap().body
+        }
+        let test_dir = format!(
+            "/tmp/drpc-test-{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        );
+        std::fs::create_dir_all(&test_dir).unwrap();
+        unsafe {
+            std::env::set_var("XDG_RUNTIME_DIR", &test_dir);
+        }
+        let bus = drpc_core::EventBus::new();
+        let mut rx = bus.subscribe();
+        let server = IpcServer::bind_with_bus(bus.clone()).await.expect("bind");
+        let mut client = tokio::net::UnixStream::connect(server.path())
+            .await
+            .expect("connect");
+        let hs = json!({"v":1,"client_id":"123"});
+        client
+            .write_all(&encode_frame(IpcOp::Handshake, &hs))
+            .await
+            .unwrap();
+        let _ = read(&mut client).await; // READY
+        let set = json!({"cmd":"SET_ACTIVITY","args":{"activity":{"name":"G"}}});
+        client
+            .write_all(&encode_frame(IpcOp::Frame, &set))
+            .await
+            .unwrap();
+        let _ = read(&mut client).await;
+        let socket_id = loop {
+            if let Some(EventKind::ActivityUpdate { socket_id, .. }) = rx.recv().await {
+                break socket_id;
+            }
+        };
+
+        let sub = json!({"cmd":"SUBSCRIBE","nonce":"s","args":{"event":"ACTIVITY_JOIN"}});
+        client
+            .write_all(&encode_frame(IpcOp::Frame, &sub))
+            .await
+            .unwrap();
+        let ack = read(&mut client).await;
+        assert_eq!(ack["evt"], "ACK");
+        assert_eq!(ack["nonce"], "s");
+        assert_eq!(ack["data"]["evt"], "ACTIVITY_JOIN");
+
+        // Only this connection's events are forwarded
+        bus.publish(EventKind::ActivityJoin {
+            socket_id: "someone-else".into(),
+            secret: "other".into(),
+        });
+        bus.publish(EventKind::ActivityJoin {
+            socket_id: socket_id.clone(),
+            secret: "s1".into(),
+        });
+        let join = read(&mut client).await;
+        assert_eq!(join["cmd"], "DISPATCH");
+        assert_eq!(join["evt"], "ACTIVITY_JOIN");
+        assert_eq!(join["data"]["secret"], "s1");
+
+        let unsub = json!({"cmd":"UNSUBSCRIBE","nonce":"u","args":{"event":"ACTIVITY_JOIN"}});
+        client
+            .write_all(&encode_frame(IpcOp::Frame, &unsub))
+            .await
+            .unwrap();
+        assert_eq!(read(&mut client).await["cmd"], "UNSUBSCRIBE");
+        bus.publish(EventKind::ActivityJoin {
+            socket_id,
+            secret: "late".into(),
+        });
+        client
+            .write_all(&encode_frame(IpcOp::Ping, &json!({})))
+            .await
+            .unwrap();
+        let mut header = [0u8; 8];
+        client.read_exact(&mut header).await.unwrap();
+        assert_eq!(
+            i32::from_le_bytes(header[0..4].try_into().unwrap()),
+            IpcOp::Pong as i32
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_command_errors() {
+        let test_dir = format!(
+            "/tmp/drpc-test-{}",
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        );
+        std::fs::create_dir_all(&test_dir).unwrap();
+        unsafe {
+            std::env::set_var("XDG_RUNTIME_DIR", &test_dir);
+        }
+        let server = IpcServer::bind_with_bus(drpc_core::EventBus::new())
+            .await
+            .expect("bind");
+        let mut client = tokio::net::UnixStream::connect(server.path())
+            .await
+            .expect("connect");
+        let hs = json!({"v":1,"client_id":"123"});
+        client
+            .write_all(&encode_frame(IpcOp::Handshake, &hs))
+            .await
+            .unwrap();
+        let mut header = [0u8; 8];
+        client.read_exact(&mut header).await.unwrap();
+        let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let mut body = vec![0u8; len];
+        client.read_exact(&mut body).await.unwrap(); // READY
+        let cmd = json!({"cmd":"GET_GUILDS","nonce":"n1"});
+        client
+            .write_all(&encode_frame(IpcOp::Frame, &cmd))
+            .await
+            .unwrap();
+        client.read_exact(&mut header).await.unwrap();
+        let len = i32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let mut body = vec![0u8; len];
+        client.read_exact(&mut body).await.unwrap();
+        let mut full = Vec::from(header);
+        full.extend_from_slice(&body);
+        let reply = decode_frame(&full).unwrap().body;
+        assert_eq!(reply["evt"], "ERROR");
+        assert_eq!(reply["nonce"], "n1");
+        assert_eq!(reply["data"]["code"], RpcErrorCode::InvalidCommand as i64);
+    }
 }
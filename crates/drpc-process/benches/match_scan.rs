@@ -0,0 +1,133 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use drpc_core::{DetectableEntry, DetectableExecutable, DetectableOs};
+use drpc_process::{DetectablesIndex, ProcessInfo, detect_process};
+use std::hint::black_box;
+use std::sync::Arc;
+
+// Roughly the shape of Discord's detectable list: ~25k entries, mostly win32 executables,
+// some with path suffixes, linux builds and argument-disambiguated shared binaries.
+fn detectables(n: usize) -> Vec<DetectableEntry> {
+    let mut list: Vec<DetectableEntry> =
+        serde_json::from_str(include_str!("../tests/fixtures/detectables_sample.json")).unwrap();
+    for i in 0..n {
+        let slug = format!("game{i:05}");
+        let mut executables = vec![DetectableExecutable {
+            name: format!("{slug}.exe"),
+            os: Some(DetectableOs::Win32),
+            ..Default::default()
+        }];
+        if i % 4 == 0 {
+            executables.push(DetectableExecutable {
+                name: format!("{slug}/binaries/win64/{slug}-win64-shipping.exe"),
+                os: Some(DetectableOs::Win32),
+                ..Default::default()
+            });
+        }
+        if i % 7 == 0 {
+            executables.push(DetectableExecutable {
+                name: slug.clone(),
+                os: Some(DetectableOs::Linux),
+                ..Default::default()
+            });
+        }
+        if i % 50 == 0 {
+            executables.push(DetectableExecutable {
+                name: "gameclient.exe".into(),
+                os: Some(DetectableOs::Win32),
+                arguments: Some(format!("-{slug}")),
+                ..Default::default()
+            });
+        }
+        if i % 25 == 0 {
+            executables.push(DetectableExecutable {
+                name: format!("{slug}launcher.exe"),
+                is_launcher: true,
+                os: Some(DetectableOs::Win32),
+                ..Default::default()
+            });
+        }
+        list.push(DetectableEntry {
+            id: Some((100_000_000_000_000_000u64 + i as u64).to_string()),
+            name: format!("Game {i}"),
+            executables,
+            ..Default::default()
+        });
+    }
+    list
+}
+
+// A desktop session: mostly system and desktop processes, a few games
+fn processes() -> Vec<ProcessInfo> {
+    const SYSTEM: &[&str] = &[
+        "/usr/lib/systemd/systemd",
+        "/usr/bin/bash",
+        "/usr/bin/zsh",
+        "/usr/bin/pipewire",
+        "/usr/bin/wireplumber",
+        "/usr/bin/dbus-broker",
+        "/usr/lib/firefox/firefox",
+        "/usr/bin/gnome-shell",
+        "/usr/lib/xorg/Xorg",
+        "/usr/bin/kitty",
+        "/usr/bin/nvim",
+        "/opt/discord/Discord",
+        "/usr/lib/electron/electron",
+        "/usr/bin/python3.12",
+        "/usr/bin/ssh-agent",
+    ];
+    let mut out = Vec::new();
+    for i in 0..400u32 {
+        let exe = SYSTEM[i as usize % SYSTEM.len()];
+        out.push(ProcessInfo {
+            pid: 1000 + i,
+            exe: exe.into(),
+            cmdline: format!("{exe} --instance {i}"),
+            ..Default::default()
+        });
+    }
+    out.push(ProcessInfo {
+        pid: 9001,
+        exe: "/home/me/games/game12000/game12000".into(),
+        cmdline: "game12000".into(),
+        ..Default::default()
+    });
+    out.push(ProcessInfo {
+        pid: 9002,
+        exe: "/usr/bin/wine64-preloader".into(),
+        cmdline: "Z:\\games\\game24000\\binaries\\win64\\game24000-win64-shipping.exe".into(),
+        ..Default::default()
+    });
+    out
+}
+
+fn bench(c: &mut Criterion) {
+    let list = detectables(25_000);
+    let procs = processes();
+    let shared = Arc::new(list.clone());
+    let index = DetectablesIndex::new(shared.clone());
+    let mut group = c.benchmark_group("scan_tick");
+    group.sample_size(10);
+    group.bench_function("linear", |b| {
+        b.iter(|| {
+            procs
+                .iter()
+                .filter(|p| detect_process(black_box(p), &list).is_some())
+                .count()
+        })
+    });
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            procs
+                .iter()
+                .filter(|p| index.detect(black_box(p)).is_some())
+                .count()
+        })
+    });
+    group.finish();
+    c.bench_function("index_build", |b| {
+        b.iter(|| DetectablesIndex::new(black_box(shared.clone())))
+    });
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
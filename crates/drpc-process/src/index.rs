@@ -0,0 +1,83 @@
+use crate::{ProcessInfo, base_lower, detect_with, match_slots, steam_app_ids, strip_ext};
+use drpc_core::{DetectableEntry, DetectableOs};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Precomputed lookup over a detectables list. Executables are keyed on their normalized
+// basename (lowercase, extension stripped), so a process only has to be checked against
+// the handful of executables sharing its name instead of the whole list.
+pub struct DetectablesIndex {
+    list: Arc<Vec<DetectableEntry>>,
+    by_name: HashMap<String, Vec<(usize, usize)>>,
+    by_steam_app: HashMap<String, usize>,
+}
+
+impl DetectablesIndex {
+    pub fn new(list: Arc<Vec<DetectableEntry>>) -> Self {
+        let mut by_name: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        let mut by_steam_app = HashMap::new();
+        for (di, d) in list.iter().enumerate() {
+            for (ei, exe) in d.executables.iter().enumerate() {
+                if exe.is_launcher {
+                    continue;
+                }
+                let name = exe.name.strip_prefix('>').unwrap_or(&exe.name);
+                let base = base_lower(name);
+                if base.is_empty() {
+                    continue;
+                }
+                by_name.entry(strip_ext(&base)).or_default().push((di, ei));
+            }
+            for id in steam_app_ids(d) {
+                by_steam_app.entry(id.to_string()).or_insert(di);
+            }
+        }
+        Self {
+            list,
+            by_name,
+            by_steam_app,
+        }
+    }
+
+    pub fn entries(&self) -> &Arc<Vec<DetectableEntry>> {
+        &self.list
+    }
+
+    pub fn detect(&self, p: &ProcessInfo) -> Option<&DetectableEntry> {
+        detect_with(
+            p,
+            |p, os| self.match_process_for(p, os),
+            |id| self.match_steam_app(id),
+        )
+    }
+
+    pub fn match_process_for(&self, p: &ProcessInfo, os: DetectableOs) -> Option<&DetectableEntry> {
+        match_slots(p, &self.list, os, |candidates| self.lookup(candidates))
+    }
+
+    pub fn match_steam_app(&self, app_id: &str) -> Option<&DetectableEntry> {
+        self.by_steam_app.get(app_id).map(|&di| &self.list[di])
+    }
+
+    // Executables whose name could match any of the process' path candidates. Matching
+    // tolerates one extension on either side, so the candidate is looked up as-is and
+    // with one or two extensions stripped.
+    fn lookup(&self, candidates: &[String]) -> Vec<(usize, usize)> {
+        let mut keys: Vec<String> = Vec::new();
+        for c in candidates {
+            let base = c.rsplit('/').next().unwrap_or(c);
+            let once = strip_ext(base);
+            let twice = strip_ext(&once);
+            for k in [base.to_string(), once, twice] {
+                if !keys.contains(&k) {
+                    keys.push(k);
+                }
+            }
+        }
+        keys.iter()
+            .filter_map(|k| self.by_name.get(k))
+            .flatten()
+            .copied()
+            .collect()
+    }
+}
@@ -1,14 +1,26 @@
-use drpc_core::{Activity, DetectableEntry, Detectables, EventBus, EventKind};
+use drpc_core::{
+    ActivityOrigin, DetectableEntry, DetectableExecutable, DetectableOs, Detectables, EventBus,
+    EventKind, ReadyConfig,
+};
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::time::{Duration, interval};
 
-#[derive(Default, Clone)]
+mod index;
+mod wine;
+
+pub use index::DetectablesIndex;
+pub use wine::{WineTarget, wine_target};
+
+#[derive(Default, Clone, Debug)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub exe: String,
     pub cmdline: String,
+    // Only populated for Wine/Proton processes
+    pub environ: HashMap<String, String>,
 }
 
 #[async_trait::async_trait]
@@ -16,32 +28,113 @@ pub trait ProcessBackend: Send + Sync {
     async fn list(&self) -> Vec<ProcessInfo>;
 }
 
-pub struct LinuxBackend;
+pub struct LinuxBackend {
+    root: PathBuf,
+}
+
+impl Default for LinuxBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinuxBackend {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+    // Read processes from a procfs-shaped directory (used with fixtures in tests)
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
 #[async_trait::async_trait]
 impl ProcessBackend for LinuxBackend {
     async fn list(&self) -> Vec<ProcessInfo> {
-        // placeholder minimal scanning of /proc limited for now
-        Vec::new()
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || read_procfs(&root))
+            .await
+            .unwrap_or_default()
     }
 }
 
+fn read_procfs(root: &Path) -> Vec<ProcessInfo> {
+    let Ok(dir) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut out = Vec::new();
+    for ent in dir.flatten() {
+        let Some(pid) = ent.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+            continue;
+        };
+        let base = ent.path();
+        // exe is unreadable for other users' processes; those can't be games we report anyway
+        let Ok(exe) = std::fs::read_link(base.join("exe")) else {
+            continue;
+        };
+        let exe = exe.to_string_lossy();
+        let exe = exe.strip_suffix(" (deleted)").unwrap_or(&exe).to_string();
+        let cmdline = std::fs::read(base.join("cmdline"))
+            .map(|raw| split_nul(&raw).join(" "))
+            .unwrap_or_default();
+        let mut info = ProcessInfo {
+            pid,
+            exe,
+            cmdline,
+            ..Default::default()
+        };
+        if wine::is_wine_loader(&info.exe)
+            && let Ok(raw) = std::fs::read(base.join("environ"))
+        {
+            info.environ = split_nul(&raw)
+                .into_iter()
+                .filter_map(|kv| {
+                    kv.split_once('=')
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                })
+                .collect();
+        }
+        out.push(info);
+    }
+    out
+}
+
+fn split_nul(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
+
 pub struct Scanner<B: ProcessBackend + 'static> {
     backend: B,
     detectables: Detectables,
     bus: EventBus,
+    cdn_host: String,
     #[allow(dead_code)] // for now
     seen: Arc<RwLock<HashSet<u32>>>,
 }
 
+// Tracks what a pid was matched to and when it was first seen
+struct Detected {
+    name: String,
+    start_ms: u64,
+}
+
 impl<B: ProcessBackend + 'static> Scanner<B> {
     pub fn new(backend: B, detectables: Detectables, bus: EventBus) -> Self {
         Self {
             backend,
             detectables,
             bus,
+            cdn_host: ReadyConfig::default().cdn_host,
             seen: Arc::new(RwLock::new(HashSet::new())),
         }
     }
+    pub fn with_cdn_host(mut self, cdn_host: impl Into<String>) -> Self {
+        self.cdn_host = cdn_host.into();
+        self
+    }
     pub fn spawn(self) {
         tokio::spawn(async move {
             self.run().await;
@@ -49,7 +142,8 @@ impl<B: ProcessBackend + 'static> Scanner<B> {
     }
     async fn run(self) {
         let mut tick = interval(Duration::from_secs(5));
-        let mut last_map: HashMap<u32, String> = HashMap::new();
+        let mut last_map: HashMap<u32, Detected> = HashMap::new();
+        let mut index: Option<(u64, DetectablesIndex)> = None;
         loop {
             tick.tick().await;
             let procs = self.backend.list().await;
@@ -58,35 +152,153 @@ impl<B: ProcessBackend + 'static> Scanner<B> {
                 current.insert(p.pid);
             }
             // removed
-            for pid in last_map.keys() {
-                if !current.contains(pid) {
-                    self.bus.publish(EventKind::Clear {
-                        socket_id: format!("proc-{}", pid),
-                    });
+            last_map.retain(|pid, _| {
+                if current.contains(pid) {
+                    return true;
                 }
+                self.bus.publish(EventKind::Clear {
+                    socket_id: format!("proc-{}", pid),
+                });
+                false
+            });
+            // rebuild the index only when the detectables list was replaced
+            let generation = self.detectables.generation();
+            if index.as_ref().is_none_or(|(g, _)| *g != generation) {
+                index = Some((generation, DetectablesIndex::new(self.detectables.list())));
             }
-            let detectables = self.detectables.list();
+            let Some((_, index)) = index.as_ref() else {
+                continue;
+            };
             for p in procs {
-                if let Some(d) = match_process(&p, &detectables) {
-                    let act = Activity {
-                        name: d.name.clone(),
-                        ..Default::default()
+                if let Some(d) = index.detect(&p) {
+                    // keep the original start time while the same game keeps running
+                    let start_ms = match last_map.get(&p.pid) {
+                        Some(prev) if prev.name == d.name => prev.start_ms,
+                        _ => current_millis(),
                     };
+                    let mut act = d.to_activity(&self.cdn_host, start_ms);
+                    // Lets privacy rules match on the executable
+                    act.extra.insert(
+                        "metadata".into(),
+                        serde_json::json!({ "executable": executable_name(&p.exe) }),
+                    );
                     drpc_core::metrics::PROCESSES_DETECTED
                         .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                     self.bus.publish(EventKind::ActivityUpdate {
                         socket_id: format!("proc-{}", p.pid),
+                        origin: ActivityOrigin::Process,
                         payload: serde_json::to_value(act).unwrap(),
                     });
-                    last_map.insert(p.pid, d.name.clone());
+                    last_map.insert(
+                        p.pid,
+                        Detected {
+                            name: d.name.clone(),
+                            start_ms,
+                        },
+                    );
                 }
             }
         }
     }
 }
 
-fn match_process<'a>(p: &ProcessInfo, list: &'a [DetectableEntry]) -> Option<&'a DetectableEntry> {
-    let exe_base = base_lower(&p.exe);
+fn executable_name(exe: &str) -> &str {
+    exe.rsplit(['/', '\\']).next().unwrap_or(exe)
+}
+
+fn current_millis() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+// Resolve the game a process belongs to, looking through Wine/Proton to the Windows executable
+pub fn detect_process<'a>(
+    p: &ProcessInfo,
+    list: &'a [DetectableEntry],
+) -> Option<&'a DetectableEntry> {
+    detect_with(
+        p,
+        |p, os| match_process_for(p, list, os),
+        |id| match_steam_app(id, list),
+    )
+}
+
+pub(crate) fn detect_with<'a>(
+    p: &ProcessInfo,
+    by_exe: impl Fn(&ProcessInfo, DetectableOs) -> Option<&'a DetectableEntry>,
+    by_steam_app: impl Fn(&str) -> Option<&'a DetectableEntry>,
+) -> Option<&'a DetectableEntry> {
+    match wine_target(p) {
+        Some(target) => {
+            let found = target.exe.as_ref().and_then(|exe| {
+                let win = ProcessInfo {
+                    pid: p.pid,
+                    exe: exe.clone(),
+                    cmdline: p.cmdline.clone(),
+                    ..Default::default()
+                };
+                by_exe(&win, DetectableOs::Win32)
+            });
+            found.or_else(|| target.steam_app_id.as_deref().and_then(by_steam_app))
+        }
+        None if wine::is_wine_loader(&p.exe) => None,
+        None => by_exe(p, DetectableOs::current()),
+    }
+}
+
+pub fn match_steam_app<'a>(
+    app_id: &str,
+    list: &'a [DetectableEntry],
+) -> Option<&'a DetectableEntry> {
+    list.iter()
+        .find(|d| steam_app_ids(d).any(|id| id == app_id))
+}
+
+pub(crate) fn steam_app_ids(d: &DetectableEntry) -> impl Iterator<Item = &str> {
+    d.third_party_skus
+        .iter()
+        .filter(|sku| sku.distributor == "steam")
+        .flat_map(|sku| [sku.id.as_deref(), sku.sku.as_deref()])
+        .flatten()
+}
+
+pub fn match_process<'a>(
+    p: &ProcessInfo,
+    list: &'a [DetectableEntry],
+) -> Option<&'a DetectableEntry> {
+    match_process_for(p, list, DetectableOs::current())
+}
+
+// Match against detectables as if the process ran on `os`. Executables tagged with
+// that os win over untagged ones, which win over executables for other platforms.
+pub fn match_process_for<'a>(
+    p: &ProcessInfo,
+    list: &'a [DetectableEntry],
+    os: DetectableOs,
+) -> Option<&'a DetectableEntry> {
+    match_slots(p, list, os, |_| {
+        list.iter()
+            .enumerate()
+            .flat_map(|(di, d)| (0..d.executables.len()).map(move |ei| (di, ei)))
+    })
+}
+
+// Shared matcher: `slots` yields (entry, executable) positions worth checking for the
+// process' path candidates; the linear matcher yields all of them, the index only a few.
+pub(crate) fn match_slots<'a, I>(
+    p: &ProcessInfo,
+    list: &'a [DetectableEntry],
+    os: DetectableOs,
+    slots: impl FnOnce(&[String]) -> I,
+) -> Option<&'a DetectableEntry>
+where
+    I: IntoIterator<Item = (usize, usize)>,
+{
+    let candidates = path_candidates(&p.exe);
+    let exe_base = candidates.first()?.clone();
     let cmd_lower = p.cmdline.to_lowercase();
     // Skip common launchers explicitly
     const LAUNCHERS: &[&str] = &[
@@ -101,30 +313,44 @@ fn match_process<'a>(p: &ProcessInfo, list: &'a [DetectableEntry]) -> Option<&'a
     if LAUNCHERS.contains(&exe_base.as_str()) {
         return None;
     }
-    // Try direct executable name match first, skipping detectable launchers
-    for d in list {
-        for exe in &d.executables {
-            if exe.is_launcher {
-                continue;
-            }
-            let det_base = base_lower(&exe.name);
-            if names_match(&exe_base, &det_base) {
-                return Some(d);
-            }
+    // Try executable name / path suffix match first, skipping detectable launchers.
+    // Best os rank wins, then list order.
+    let mut best: Option<(u8, usize)> = None;
+    for (di, ei) in slots(&candidates) {
+        let exe = &list[di].executables[ei];
+        if exe.is_launcher
+            || !executable_matches(&exe.name, &candidates)
+            || !arguments_match(exe, &cmd_lower)
+        {
+            continue;
+        }
+        let key = (os_rank(exe.os, os), di);
+        if best.is_none_or(|b| key < b) {
+            best = Some(key);
         }
     }
-    // Heuristic: Java-based processes — match on JAR or app name in cmdline
-    if matches!(
-        exe_base.as_str(),
-        "java" | "javaw" | "java.exe" | "javaw.exe"
-    ) {
+    if let Some((_, di)) = best {
+        return Some(&list[di]);
+    }
+    // Heuristic: Java-based processes — match on JAR, main class arguments or app name in cmdline
+    if is_java(&exe_base) {
         for d in list {
             // prefer executable names if present
             for exe in &d.executables {
                 if exe.is_launcher {
                     continue;
                 }
+                if is_java(&base_lower(&exe.name)) {
+                    // a generic java executable only identifies the game through its arguments
+                    if exe.arguments.is_some() && arguments_match(exe, &cmd_lower) {
+                        return Some(d);
+                    }
+                    continue;
+                }
                 let det_base = base_lower(&exe.name);
+                if det_base.is_empty() || !arguments_match(exe, &cmd_lower) {
+                    continue;
+                }
                 if cmd_lower.contains(&det_base) || cmd_lower.contains(&strip_ext(&det_base)) {
                     return Some(d);
                 }
@@ -139,18 +365,97 @@ fn match_process<'a>(p: &ProcessInfo, list: &'a [DetectableEntry]) -> Option<&'a
     None
 }
 
-fn base_lower(s: &str) -> String {
-    std::path::Path::new(s)
+fn os_rank(exe_os: Option<DetectableOs>, os: DetectableOs) -> u8 {
+    match exe_os {
+        Some(o) if o == os => 0,
+        None => 1,
+        Some(_) => 2,
+    }
+}
+
+fn arguments_match(exe: &DetectableExecutable, cmd_lower: &str) -> bool {
+    match exe.arguments.as_deref() {
+        Some(args) if !args.is_empty() => cmd_lower.contains(&args.to_lowercase()),
+        _ => true,
+    }
+}
+
+fn is_java(base: &str) -> bool {
+    matches!(base, "java" | "javaw" | "java.exe" | "javaw.exe")
+}
+
+// Lowercased path suffixes of `exe`, shortest first: `game.exe`, `bin/game.exe`, ...
+// followed by the same suffixes with a 64-bit marker removed from the end of the file stem.
+fn path_candidates(exe: &str) -> Vec<String> {
+    let norm = exe.to_lowercase().replace('\\', "/");
+    let parts: Vec<&str> = norm.split('/').filter(|s| !s.is_empty()).collect();
+    let mut out: Vec<String> = (1..=parts.len())
+        .map(|i| parts[parts.len() - i..].join("/"))
+        .collect();
+    let Some(base) = parts.last().and_then(|b| strip_64_marker(b)) else {
+        return out;
+    };
+    for i in 0..out.len() {
+        let variant = match out[i].rsplit_once('/') {
+            Some((dir, _)) => format!("{dir}/{base}"),
+            None => base.clone(),
+        };
+        out.push(variant);
+    }
+    out
+}
+
+// Longest first, so `game_x64` loses `_x64` rather than just `64`
+const MARKERS_64: &[&str] = &["_x64", ".x64", "-x64", "x64", "_64", "-64", "64"];
+
+// `game64.exe`, `game_x64.exe` and `game.x64.exe` -> `game.exe`. Directories and markers
+// inside the name are left alone, as is a stem that would end in a digit (`game2064`).
+fn strip_64_marker(base: &str) -> Option<String> {
+    let (stem, ext) = match base.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (base, None),
+    };
+    let rest = MARKERS_64.iter().find_map(|m| stem.strip_suffix(m))?;
+    if rest.is_empty() || rest.ends_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(match ext {
+        Some(ext) => format!("{rest}.{ext}"),
+        None => rest.to_string(),
+    })
+}
+
+fn executable_matches(name: &str, candidates: &[String]) -> bool {
+    let name = name.to_lowercase().replace('\\', "/");
+    // `>` marks names that must equal the process basename exactly
+    if let Some(exact) = name.strip_prefix('>') {
+        return candidates.first().is_some_and(|c| c == exact);
+    }
+    if name.is_empty() {
+        return false;
+    }
+    if name.contains('/') {
+        return candidates.iter().any(|c| names_match(c, &name));
+    }
+    candidates
+        .iter()
+        .filter(|c| !c.contains('/'))
+        .any(|c| names_match(c, &name))
+}
+
+pub(crate) fn base_lower(s: &str) -> String {
+    let s = s.replace('\\', "/");
+    std::path::Path::new(&s)
         .file_name()
         .and_then(|o| o.to_str())
-        .unwrap_or(s)
+        .unwrap_or(&s)
         .to_lowercase()
 }
 
-fn strip_ext(name: &str) -> String {
+pub(crate) fn strip_ext(name: &str) -> String {
     match name.rsplit_once('.') {
-        Some((stem, _)) => stem.to_string(),
-        None => name.to_string(),
+        Some((stem, ext)) if !ext.contains('/') => stem.to_string(),
+        _ => name.to_string(),
     }
 }
 
@@ -169,7 +474,9 @@ mod tests {
             executables: vec![drpc_core::DetectableExecutable {
                 name: exe.into(),
                 is_launcher,
+                ..Default::default()
             }],
+            ..Default::default()
         }
     }
 
@@ -180,6 +487,7 @@ mod tests {
             pid: 1,
             exe: "/usr/bin/coolgame".into(),
             cmdline: "coolgame".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_some());
@@ -193,6 +501,7 @@ mod tests {
             pid: 2,
             exe: "/opt/launcher".into(),
             cmdline: "launcher".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_none());
@@ -205,6 +514,7 @@ mod tests {
             pid: 3,
             exe: "/usr/bin/java".into(),
             cmdline: "java -jar /home/user/JarGame.jar".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_some());
@@ -219,6 +529,7 @@ mod tests {
             pid: 4,
             exe: "coolgame".into(),
             cmdline: "coolgame".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_some());
@@ -233,6 +544,7 @@ mod tests {
             pid: 5,
             exe: "steam".into(), // explicit launcher in constant list
             cmdline: "steam --run-game somegame".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_none());
@@ -246,9 +558,55 @@ mod tests {
             pid: 6,
             exe: "java".into(),
             cmdline: "java -Xmx2G -cp libs/* com.company.BlockWorldMain".into(),
+            ..Default::default()
         };
         let m = match_process(&p, &list);
         assert!(m.is_some());
         assert_eq!(m.unwrap().name, "BlockWorld");
     }
+
+    struct FixedBackend(Vec<ProcessInfo>);
+
+    #[async_trait::async_trait]
+    impl ProcessBackend for FixedBackend {
+        async fn list(&self) -> Vec<ProcessInfo> {
+            self.0.clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn scanner_publishes_rich_activity() {
+        let mut entry = det("CoolGame", "coolgame", false);
+        entry.id = Some("42".into());
+        entry.icon_hash = Some("abc".into());
+        let detectables = Detectables::new(vec![entry]);
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+        let backend = FixedBackend(vec![ProcessInfo {
+            pid: 7,
+            exe: "/usr/bin/coolgame".into(),
+            cmdline: "coolgame".into(),
+            ..Default::default()
+        }]);
+        Scanner::new(backend, detectables, bus.clone()).spawn();
+        match rx.recv().await.unwrap() {
+            EventKind::ActivityUpdate {
+                socket_id,
+                origin,
+                payload,
+            } => {
+                assert_eq!(socket_id, "proc-7");
+                assert_eq!(origin, ActivityOrigin::Process);
+                assert_eq!(payload["application_id"], "42");
+                assert_eq!(payload["metadata"]["executable"], "coolgame");
+                assert_eq!(payload["type"], 0);
+                assert!(payload["timestamps"]["start"].as_u64().unwrap() > 0);
+                assert_eq!(
+                    payload["assets"]["large_image"],
+                    "https://cdn.discordapp.com/app-icons/42/abc.png"
+                );
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
 }
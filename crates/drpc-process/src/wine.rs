@@ -0,0 +1,191 @@
+use crate::ProcessInfo;
+
+const WINE_LOADERS: &[&str] = &[
+    "wine",
+    "wine64",
+    "wine-preloader",
+    "wine64-preloader",
+    "wineserver",
+];
+
+// Processes every Wine prefix runs; never games
+const WINE_SYSTEM_EXES: &[&str] = &[
+    "conhost.exe",
+    "explorer.exe",
+    "plugplay.exe",
+    "rpcss.exe",
+    "rundll32.exe",
+    "services.exe",
+    "start.exe",
+    "steam.exe",
+    "svchost.exe",
+    "tabtip.exe",
+    "winedevice.exe",
+    "wineboot.exe",
+    "winemenubuilder.exe",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WineTarget {
+    // Windows path of the game executable, e.g. `Z:\home\me\Game\Game.exe`
+    pub exe: Option<String>,
+    pub steam_app_id: Option<String>,
+}
+
+pub(crate) fn is_wine_loader(exe: &str) -> bool {
+    let base = exe.rsplit(['/', '\\']).next().unwrap_or(exe);
+    WINE_LOADERS.contains(&base)
+}
+
+// Work out which Windows program a Wine/Proton process is running. Returns None for
+// native processes and for Wine's own helper processes.
+pub fn wine_target(p: &ProcessInfo) -> Option<WineTarget> {
+    if !is_wine_loader(&p.exe) {
+        return None;
+    }
+    let exe = windows_exe_from_cmdline(&p.cmdline);
+    if exe.as_deref().is_some_and(is_system_exe) || p.exe.ends_with("wineserver") {
+        return None;
+    }
+    let exe = exe.map(|e| match p.environ.get("STEAM_COMPAT_INSTALL_PATH") {
+        // Proton may start games by a path relative to the install dir
+        Some(install) if !is_absolute_windows(&e) => {
+            format!("{}/{}", install.trim_end_matches('/'), e.replace('\\', "/"))
+        }
+        _ => e,
+    });
+    let steam_app_id = ["SteamAppId", "STEAM_COMPAT_APP_ID", "SteamGameId"]
+        .iter()
+        .filter_map(|k| p.environ.get(*k))
+        .find(|v| !v.is_empty() && v.as_str() != "0")
+        .cloned();
+    if exe.is_none() && steam_app_id.is_none() {
+        return None;
+    }
+    Some(WineTarget { exe, steam_app_id })
+}
+
+fn is_system_exe(exe: &str) -> bool {
+    let base = exe.rsplit(['/', '\\']).next().unwrap_or(exe).to_lowercase();
+    WINE_SYSTEM_EXES.contains(&base.as_str())
+}
+
+fn is_absolute_windows(path: &str) -> bool {
+    let b = path.as_bytes();
+    b.len() > 2 && b[0].is_ascii_alphabetic() && b[1] == b':' && matches!(b[2], b'\\' | b'/')
+}
+
+// First `.exe` in the command line. Windows paths may contain spaces, so a drive-letter
+// path runs up to the first `.exe`; otherwise the first whitespace token ending in `.exe`.
+fn windows_exe_from_cmdline(cmdline: &str) -> Option<String> {
+    let lower = cmdline.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let mut search = 0;
+    while let Some(off) = lower[search..].find(".exe") {
+        let end = search + off + 4;
+        let boundary = bytes
+            .get(end)
+            .is_none_or(|c| c.is_ascii_whitespace() || *c == b'"');
+        if boundary {
+            let head = &cmdline[..end];
+            let start = drive_path_start(head)
+                .or_else(|| head.rfind(char::is_whitespace).map(|i| i + 1))
+                .unwrap_or(0);
+            let exe = head[start..].trim_start_matches('"');
+            if !exe.is_empty() {
+                return Some(exe.to_string());
+            }
+        }
+        search = end;
+    }
+    None
+}
+
+// Start of the last `X:\` / `X:/` drive path in `s`, if any
+fn drive_path_start(s: &str) -> Option<usize> {
+    let b = s.as_bytes();
+    (0..b.len().saturating_sub(2)).rev().find(|&i| {
+        b[i].is_ascii_alphabetic()
+            && b[i + 1] == b':'
+            && matches!(b[i + 2], b'\\' | b'/')
+            && (i == 0 || b[i - 1].is_ascii_whitespace() || b[i - 1] == b'"')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proc(exe: &str, cmdline: &str, env: &[(&str, &str)]) -> ProcessInfo {
+        ProcessInfo {
+            pid: 1,
+            exe: exe.into(),
+            cmdline: cmdline.into(),
+            environ: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn exe_with_spaces_from_cmdline() {
+        let p = proc(
+            "/usr/bin/wine64-preloader",
+            "Z:\\home\\me\\Games\\Stardew Valley\\Stardew Valley.exe --no-intro",
+            &[],
+        );
+        let t = wine_target(&p).unwrap();
+        assert_eq!(
+            t.exe.as_deref(),
+            Some("Z:\\home\\me\\Games\\Stardew Valley\\Stardew Valley.exe")
+        );
+    }
+
+    #[test]
+    fn relative_exe_joins_install_path() {
+        let p = proc(
+            "/opt/proton/files/bin/wine64-preloader",
+            "Game.exe -dx11",
+            &[
+                ("STEAM_COMPAT_INSTALL_PATH", "/games/common/Game/"),
+                ("SteamAppId", "123"),
+            ],
+        );
+        let t = wine_target(&p).unwrap();
+        assert_eq!(t.exe.as_deref(), Some("/games/common/Game/Game.exe"));
+        assert_eq!(t.steam_app_id.as_deref(), Some("123"));
+    }
+
+    #[test]
+    fn wine_helpers_and_native_processes_are_ignored() {
+        let shim = proc(
+            "/usr/bin/wine64-preloader",
+            "C:\\windows\\system32\\steam.exe Z:\\games\\Game.exe",
+            &[("SteamAppId", "123")],
+        );
+        assert_eq!(wine_target(&shim), None);
+        assert_eq!(wine_target(&proc("/usr/bin/wineserver", "", &[])), None);
+        assert_eq!(
+            wine_target(&proc(
+                "/usr/bin/vim",
+                "vim notes.exe",
+                &[("WINEPREFIX", "/w")]
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn quoted_exe_is_unquoted() {
+        let p = proc(
+            "/usr/bin/wine",
+            "\"C:\\Program Files\\Game\\game.exe\" -windowed",
+            &[],
+        );
+        assert_eq!(
+            wine_target(&p).unwrap().exe.as_deref(),
+            Some("C:\\Program Files\\Game\\game.exe")
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use drpc_core::{DetectableEntry, DetectableOs};
+use drpc_process::{DetectablesIndex, ProcessInfo, match_process_for};
+use std::sync::Arc;
+
+fn sample() -> Vec<DetectableEntry> {
+    serde_json::from_str(include_str!("fixtures/detectables_sample.json")).unwrap()
+}
+
+struct Case {
+    exe: &'static str,
+    cmdline: &'static str,
+    os: DetectableOs,
+    expect: Option<&'static str>,
+}
+
+const CASES: &[Case] = &[
+    // plain basename matches
+    Case {
+        exe: "C:\\Games\\Minecraft\\Minecraft.exe",
+        cmdline: "Minecraft.exe",
+        os: DetectableOs::Win32,
+        expect: Some("Minecraft"),
+    },
+    Case {
+        exe: "/home/u/.local/share/Terraria/Terraria.bin.x86_64",
+        cmdline: "./Terraria.bin.x86_64",
+        os: DetectableOs::Linux,
+        expect: Some("Terraria"),
+    },
+    Case {
+        exe: "/home/u/GOG Games/Stardew Valley/game/StardewValley",
+        cmdline: "StardewValley",
+        os: DetectableOs::Linux,
+        expect: Some("Stardew Valley"),
+    },
+    Case {
+        exe: "/opt/osu/osu!",
+        cmdline: "osu!",
+        os: DetectableOs::Linux,
+        expect: Some("osu!"),
+    },
+    // launchers never match
+    Case {
+        exe: "C:\\Program Files\\Minecraft Launcher\\MinecraftLauncher.exe",
+        cmdline: "MinecraftLauncher.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    Case {
+        exe: "C:\\Riot Games\\League of Legends\\LeagueClient.exe",
+        cmdline: "LeagueClient.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    // 64-bit markers are stripped from the end of the process' file stem
+    Case {
+        exe: "D:\\Epic\\Fortnite\\FortniteGame\\Binaries\\Win64\\FortniteClient-Win64-Shipping.exe",
+        cmdline: "FortniteClient-Win64-Shipping.exe -epicapp=Fortnite",
+        os: DetectableOs::Win32,
+        expect: Some("Fortnite"),
+    },
+    Case {
+        exe: "C:\\Games\\Minecraft\\Minecraft64.exe",
+        cmdline: "Minecraft64.exe",
+        os: DetectableOs::Win32,
+        expect: Some("Minecraft"),
+    },
+    Case {
+        exe: "C:\\Ubisoft\\Rainbow Six Siege\\RainbowSix_x64.exe",
+        cmdline: "RainbowSix_x64.exe",
+        os: DetectableOs::Win32,
+        expect: Some("Rainbow Six Siege"),
+    },
+    Case {
+        exe: "C:\\Games\\Terraria\\Terraria.x64.exe",
+        cmdline: "Terraria.x64.exe",
+        os: DetectableOs::Win32,
+        expect: Some("Terraria"),
+    },
+    // ...but not from directories or the middle of a name
+    Case {
+        exe: "C:\\Games\\Other\\game64\\eldenring.exe",
+        cmdline: "eldenring.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    Case {
+        exe: "C:\\Games\\port64al.exe",
+        cmdline: "port64al.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    Case {
+        exe: "C:\\Games\\Portal2064.exe",
+        cmdline: "Portal2064.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    // path suffix names
+    Case {
+        exe: "C:\\Program Files (x86)\\World of Warcraft\\_retail_\\Wow.exe",
+        cmdline: "Wow.exe",
+        os: DetectableOs::Win32,
+        expect: Some("World of Warcraft"),
+    },
+    Case {
+        exe: "C:\\Program Files (x86)\\World of Warcraft\\_classic_\\WowClassic.exe",
+        cmdline: "WowClassic.exe",
+        os: DetectableOs::Win32,
+        expect: Some("World of Warcraft"),
+    },
+    Case {
+        exe: "C:\\Somewhere\\Else\\Wow.exe",
+        cmdline: "Wow.exe",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    Case {
+        exe: "/home/u/.steam/steam/steamapps/common/Counter-Strike Global Offensive/game/bin/linuxsteamrt64/cs2",
+        cmdline: "cs2 -steam",
+        os: DetectableOs::Linux,
+        expect: Some("Counter-Strike 2"),
+    },
+    Case {
+        exe: "/home/u/bin/cs2",
+        cmdline: "cs2",
+        os: DetectableOs::Linux,
+        // only the basename matches, through the win32 `cs2.exe` entry
+        expect: Some("Counter-Strike 2"),
+    },
+    Case {
+        exe: "/home/u/factorio/bin/x64/factorio",
+        cmdline: "factorio",
+        os: DetectableOs::Linux,
+        expect: Some("Factorio"),
+    },
+    // `>` requires the exact basename
+    Case {
+        exe: "C:\\Riot Games\\League of Legends\\Game\\League of Legends.exe",
+        cmdline: "\"League of Legends.exe\"",
+        os: DetectableOs::Win32,
+        expect: Some("League of Legends"),
+    },
+    Case {
+        exe: "C:\\Riot Games\\League of Legends\\Game\\League of Legends",
+        cmdline: "League of Legends",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    // arguments disambiguate shared executables
+    Case {
+        exe: "C:\\Cryptic\\Star Trek Online\\Live\\GameClient.exe",
+        cmdline: "GameClient.exe -StarTrek -console",
+        os: DetectableOs::Win32,
+        expect: Some("Star Trek Online"),
+    },
+    Case {
+        exe: "C:\\Cryptic\\Neverwinter\\Live\\GameClient.exe",
+        cmdline: "GameClient.exe -Neverwinter",
+        os: DetectableOs::Win32,
+        expect: Some("Neverwinter"),
+    },
+    Case {
+        exe: "C:\\Cryptic\\Champions\\Live\\GameClient.exe",
+        cmdline: "GameClient.exe -Champions",
+        os: DetectableOs::Win32,
+        expect: None,
+    },
+    Case {
+        exe: "/home/u/.steam/steam/steamapps/common/GarrysMod/hl2_linux",
+        cmdline: "hl2_linux -game garrysmod -steam",
+        os: DetectableOs::Linux,
+        expect: Some("Garry's Mod"),
+    },
+    Case {
+        exe: "/home/u/.steam/steam/steamapps/common/Half-Life 2/hl2_linux",
+        cmdline: "hl2_linux -game hl2 -steam",
+        os: DetectableOs::Linux,
+        expect: Some("Half-Life 2"),
+    },
+    Case {
+        exe: "/home/u/.steam/steam/steamapps/common/Half-Life 2/hl2_linux",
+        cmdline: "hl2_linux -game episodic",
+        os: DetectableOs::Linux,
+        expect: None,
+    },
+    // java games are recognized through their main class
+    Case {
+        exe: "/usr/lib/jvm/java-21-openjdk/bin/java",
+        cmdline: "/usr/lib/jvm/java-21-openjdk/bin/java -Xmx4G -cp libs/* net.minecraft.client.main.Main --version 1.21",
+        os: DetectableOs::Linux,
+        expect: Some("Minecraft"),
+    },
+    Case {
+        exe: "C:\\Program Files\\Java\\bin\\javaw.exe",
+        cmdline: "javaw.exe -cp client.jar net.minecraft.client.main.Main",
+        os: DetectableOs::Win32,
+        expect: Some("Minecraft"),
+    },
+    Case {
+        exe: "/usr/bin/java",
+        cmdline: "java -jar /opt/tools/some-ide.jar",
+        os: DetectableOs::Linux,
+        expect: None,
+    },
+    // executables for the host os are preferred over other platforms
+    Case {
+        exe: "/usr/games/portal",
+        cmdline: "portal",
+        os: DetectableOs::Linux,
+        expect: Some("Portal Native"),
+    },
+    Case {
+        exe: "C:\\Games\\portal.exe",
+        cmdline: "portal.exe",
+        os: DetectableOs::Win32,
+        expect: Some("Portal Port"),
+    },
+    // unknown processes
+    Case {
+        exe: "/usr/bin/bash",
+        cmdline: "bash",
+        os: DetectableOs::Linux,
+        expect: None,
+    },
+    Case {
+        exe: "",
+        cmdline: "",
+        os: DetectableOs::Linux,
+        expect: None,
+    },
+];
+
+#[test]
+fn real_world_detectables_table() {
+    let list = sample();
+    let index = DetectablesIndex::new(Arc::new(list.clone()));
+    let mut failures = Vec::new();
+    for (i, case) in CASES.iter().enumerate() {
+        let p = ProcessInfo {
+            pid: i as u32,
+            exe: case.exe.into(),
+            cmdline: case.cmdline.into(),
+            ..Default::default()
+        };
+        let got = match_process_for(&p, &list, case.os).map(|d| d.name.as_str());
+        if got != case.expect {
+            failures.push(format!(
+                "#{i} {:?} ({:?}): expected {:?}, got {:?}",
+                case.exe, case.os, case.expect, got
+            ));
+        }
+        let indexed = index
+            .match_process_for(&p, case.os)
+            .map(|d| d.name.as_str());
+        if indexed != got {
+            failures.push(format!(
+                "#{i} {:?}: index returned {:?}, linear scan {:?}",
+                case.exe, indexed, got
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "mismatches:\n{}", failures.join("\n"));
+}
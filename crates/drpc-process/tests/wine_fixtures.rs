@@ -0,0 +1,59 @@
+use drpc_core::DetectableEntry;
+use drpc_process::{DetectablesIndex, LinuxBackend, ProcessBackend, detect_process};
+use std::sync::Arc;
+
+fn sample() -> Vec<DetectableEntry> {
+    serde_json::from_str(include_str!("fixtures/detectables_sample.json")).unwrap()
+}
+
+fn backend() -> LinuxBackend {
+    LinuxBackend::with_root(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/proc"))
+}
+
+#[tokio::test]
+async fn procfs_fixture_is_read() {
+    let mut procs = backend().list().await;
+    procs.sort_by_key(|p| p.pid);
+    let pids: Vec<u32> = procs.iter().map(|p| p.pid).collect();
+    assert_eq!(
+        pids,
+        vec![1001, 1002, 1003, 1004, 1005, 1006, 1007, 1008, 1009]
+    );
+    let hl2 = procs.iter().find(|p| p.pid == 1007).unwrap();
+    assert!(hl2.cmdline.ends_with("hl2.exe -game garrysmod -steam"));
+    assert_eq!(hl2.environ["WINEPREFIX"], "/home/me/.wine");
+    // environ is only read for wine processes
+    let native = procs.iter().find(|p| p.pid == 1009).unwrap();
+    assert!(native.environ.is_empty());
+}
+
+#[tokio::test]
+async fn wine_and_proton_games_are_detected() {
+    let list = sample();
+    let index = DetectablesIndex::new(Arc::new(list.clone()));
+    let procs = backend().list().await;
+    let detected = |pid: u32| {
+        let p = procs.iter().find(|p| p.pid == pid).unwrap();
+        let linear = detect_process(p, &list).map(|d| d.name.as_str());
+        let indexed = index.detect(p).map(|d| d.name.as_str());
+        assert_eq!(linear, indexed, "index disagrees for pid {pid}");
+        linear
+    };
+    // plain wine, Windows path with spaces in cmdline
+    assert_eq!(detected(1001), Some("Stardew Valley"));
+    // proton, exe relative to STEAM_COMPAT_INSTALL_PATH
+    assert_eq!(detected(1002), Some("Terraria"));
+    // proton's steam.exe shim is not the game
+    assert_eq!(detected(1003), None);
+    // unknown anti-cheat bootstrapper, resolved through SteamAppId
+    assert_eq!(detected(1004), Some("ELDEN RING"));
+    // wine infrastructure
+    assert_eq!(detected(1005), None);
+    assert_eq!(detected(1006), None);
+    // arguments from the wine cmdline still disambiguate
+    assert_eq!(detected(1007), Some("Garry's Mod"));
+    // a native shell inside a prefix is not a wine process
+    assert_eq!(detected(1008), None);
+    // native linux games keep matching their linux executables
+    assert_eq!(detected(1009), Some("Counter-Strike 2"));
+}
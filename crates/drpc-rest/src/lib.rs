@@ -1,10 +1,17 @@
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
 use axum::{
     extract::{Path, State},
     routing::{delete, get, post},
     Json, Router,
 };
-use drpc_core::{Activity, ActivityRegistry, Detectables, EventBus, EventKind};
-use parking_lot::RwLock;
+use drpc_core::{
+    is_local_host, validate_activity, Activity, ActivityOrigin, ActivityRegistry, AssetResolver,
+    AuthConfig, Detectables, DetectablesOptions, EventBus, EventKind, MockUser, PrivacyError,
+    PrivacyRule, PrivacySettings,
+};
 use serde::Deserialize;
 use std::sync::Arc;
 use tracing::info;
@@ -14,129 +21,269 @@ pub struct RestState {
     pub bus: EventBus,
     pub registry: Arc<ActivityRegistry>,
     pub detectables: Option<Detectables>,
-    pub detectables_ttl: u64,
-    pub allow: Arc<RwLock<Option<Vec<String>>>>,
-    pub deny: Arc<RwLock<Option<Vec<String>>>>,
+    pub detectables_options: DetectablesOptions,
+    pub assets: Arc<AssetResolver>,
 }
 
 #[derive(Deserialize)]
 struct SetActivityPayload {
     socket_id: Option<String>,
-    activity: Activity,
+    // Raw so already-normalized activities (label buttons + metadata) are accepted too
+    activity: serde_json::Value,
+}
+
+// Everything besides the bus and registry; `Default` gives an ephemeral port, no
+// detectables, an in-memory asset resolver and no authentication
+#[derive(Clone, Default)]
+pub struct RestOptions {
+    // 0 picks a free port
+    pub port: u16,
+    pub detectables: Option<Detectables>,
+    pub detectables_options: DetectablesOptions,
+    pub assets: Arc<AssetResolver>,
+    pub auth: AuthConfig,
 }
 
 pub async fn run_rest(
     bus: EventBus,
     registry: Arc<ActivityRegistry>,
-    detectables: Option<Detectables>,
-    detectables_ttl: u64,
-    port: u16,
+    options: RestOptions,
 ) -> anyhow::Result<u16> {
+    let RestOptions {
+        port,
+        detectables,
+        detectables_options,
+        assets,
+        auth,
+    } = options;
     let state = RestState {
         bus,
         registry,
         detectables,
-        detectables_ttl,
-        allow: Arc::new(RwLock::new(None)),
-        deny: Arc::new(RwLock::new(None)),
+        detectables_options,
+        assets,
     };
     let app_state = state.clone();
     let app = Router::new()
         .route("/health", get(health))
         .route("/activities", get(list_activities).post(set_activity))
         .route("/activities/{socket_id}", delete(clear_activity))
+        .route("/activities/{socket_id}/join", post(join_activity))
+        .route("/activities/{socket_id}/spectate", post(spectate_activity))
+        .route("/activities/{socket_id}/join-request", post(join_request))
         .route("/detectables/refresh", post(refresh_detectables))
+        .route("/detectables/local", get(local_detectables))
+        .route("/detectables/{id}", get(get_detectable))
+        .route("/assets/{hash}", get(cached_asset))
         .route("/metrics", get(metrics))
         .route("/privacy", get(get_privacy).post(set_privacy))
+        .route("/privacy/dry-run", post(privacy_dry_run))
+        .layer(middleware::from_fn_with_state(Arc::new(auth), require_auth))
         .with_state(app_state);
     // Internal subscription to update registry for standalone REST usage
-    let bus_clone = state.bus.clone();
+    // Subscribed before returning so events published right after startup aren't missed
+    let mut rx = state.bus.subscribe();
     let reg_clone = state.registry.clone();
     tokio::spawn(async move {
-        let mut rx = bus_clone.subscribe();
         while let Some(evt) = rx.recv().await {
             match evt {
-                EventKind::ActivityUpdate { socket_id, payload } => {
-                    reg_clone.set(socket_id, payload)
-                }
+                EventKind::ActivityUpdate {
+                    socket_id, payload, ..
+                } => reg_clone.set(socket_id, payload),
                 EventKind::Clear { socket_id } => reg_clone.clear(&socket_id),
-                EventKind::PrivacyRefresh => {}
+                _ => {}
             }
         }
     });
     let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
     let actual = listener.local_addr()?.port();
     info!(port = actual, "REST listening");
+    if let Some(images) = state.assets.image_cache() {
+        images.set_base_url(format!("http://127.0.0.1:{actual}"));
+    }
     tokio::spawn(async move {
         axum::serve(listener, app).await.ok();
     });
     Ok(actual)
 }
 
+async fn require_auth(State(auth): State<Arc<AuthConfig>>, req: Request, next: Next) -> Response {
+    match check_request(&auth, &req) {
+        Ok(()) => {}
+        Err(StatusCode::FORBIDDEN) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({"ok": false, "error": "invalid_host"})),
+            )
+                .into_response();
+        }
+        Err(status) => {
+            return (
+                status,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(serde_json::json!({"ok": false, "error": "unauthorized"})),
+            )
+                .into_response();
+        }
+    }
+    next.run(req).await
+}
+
+fn check_request(auth: &AuthConfig, req: &Request) -> Result<(), StatusCode> {
+    let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+    if !header(header::HOST).is_some_and(is_local_host) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    // Cached images are loaded by overlays as plain <img> tags, which can't send a token
+    let path = req.uri().path();
+    let public = path == "/health" || path.starts_with("/assets/");
+    if !public && !auth.check_bearer(header(header::AUTHORIZATION)) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(())
+}
+
 async fn health() -> &'static str {
     "ok"
 }
 
+async fn cached_asset(
+    State(s): State<RestState>,
+    Path(hash): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let image = s
+        .assets
+        .image_cache()
+        .and_then(|c| c.get(&hash))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Content is addressed by the source URL's hash, so it never changes under a hash
+    Ok((
+        [
+            (header::CONTENT_TYPE, image.content_type),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        image.bytes,
+    ))
+}
+
 async fn list_activities(State(s): State<RestState>) -> Json<serde_json::Value> {
-    let list = s.registry.non_null();
+    let mut list = s.registry.non_null();
+    for (_, activity) in list.iter_mut() {
+        // Lists still loading show up on a later request
+        if let Some(app) = s.assets.annotate(activity) {
+            s.assets.spawn_load(&app);
+        }
+    }
     Json(serde_json::json!({"activities": list}))
 }
 
 async fn set_activity(
     State(s): State<RestState>,
     Json(body): Json<SetActivityPayload>,
-) -> Json<serde_json::Value> {
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let activity = match Activity::from_value(body.activity) {
+        Ok(a) => a,
+        Err(e) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"ok": false, "errors": [
+                    {"field": "activity", "message": e.to_string()}
+                ]})),
+            ));
+        }
+    };
+    let raw = serde_json::to_value(&activity).unwrap_or_default();
+    if let Err(e) = validate_activity(&raw) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"ok": false, "errors": e.errors})),
+        ));
+    }
     let sid = body
         .socket_id
         .unwrap_or_else(|| format!("rest-{}", uuid::Uuid::new_v4()));
-    let norm = body.activity.normalize();
-    if allowed(&s, &norm.name) {
-        s.bus.publish(EventKind::ActivityUpdate {
-            socket_id: sid.clone(),
-            payload: serde_json::to_value(&norm).unwrap(),
-        });
-    }
-    Json(serde_json::json!({"ok": true, "socket_id": sid}))
+    let norm = activity.normalize();
+    // Denied names are dropped by the bus's privacy policy
+    s.bus.publish(EventKind::ActivityUpdate {
+        socket_id: sid.clone(),
+        origin: ActivityOrigin::Rest,
+        payload: serde_json::to_value(&norm).unwrap(),
+    });
+    Ok(Json(serde_json::json!({"ok": true, "socket_id": sid})))
 }
 
+// Omitted fields keep their current value
 #[derive(Deserialize)]
 struct PrivacyUpdate {
     allow: Option<Vec<String>>,
     deny: Option<Vec<String>>,
+    rules: Option<Vec<PrivacyRule>>,
+}
+
+impl PrivacyUpdate {
+    fn merge(self, current: PrivacySettings) -> PrivacySettings {
+        PrivacySettings {
+            allow: self.allow.or(current.allow),
+            deny: self.deny.or(current.deny),
+            rules: self.rules.unwrap_or(current.rules),
+        }
+    }
+}
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn privacy_error(e: PrivacyError) -> ApiError {
+    match e {
+        PrivacyError::InvalidRule {
+            index,
+            field,
+            message,
+        } => (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"ok": false, "errors": [
+                {"field": format!("rules[{index}].{field}"), "message": message}
+            ]})),
+        ),
+        other => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"ok": false, "error": other.to_string()})),
+        ),
+    }
 }
 
 async fn set_privacy(
     State(s): State<RestState>,
     Json(p): Json<PrivacyUpdate>,
-) -> Json<serde_json::Value> {
-    if let Some(a) = p.allow {
-        *s.allow.write() = Some(a);
-    }
-    if let Some(d) = p.deny {
-        *s.deny.write() = Some(d);
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let policy = s.bus.privacy();
+    let saved = policy.set(p.merge(policy.settings()));
+    // Saving can fail after the new settings took effect; invalid rules change nothing
+    if !matches!(saved, Err(PrivacyError::InvalidRule { .. })) {
+        s.bus.publish(EventKind::PrivacyRefresh);
     }
-    s.bus.publish(EventKind::PrivacyRefresh);
-    Json(serde_json::json!({"ok": true}))
+    saved.map_err(privacy_error)?;
+    Ok(Json(serde_json::json!({"ok": true})))
 }
 
 async fn get_privacy(State(s): State<RestState>) -> Json<serde_json::Value> {
+    let settings = s.bus.privacy().settings();
     Json(serde_json::json!({
-        "allow": s.allow.read().clone(),
-        "deny": s.deny.read().clone(),
+        "allow": settings.allow,
+        "deny": settings.deny,
+        "rules": settings.rules,
     }))
 }
 
-fn allowed(s: &RestState, name: &str) -> bool {
-    let name_lower = name.to_lowercase();
-    if let Some(deny) = s.deny.read().as_ref() {
-        if deny.iter().any(|d| name_lower.contains(&d.to_lowercase())) {
-            return false;
-        }
-    }
-    if let Some(allow) = s.allow.read().as_ref() {
-        return allow.iter().any(|a| name_lower.contains(&a.to_lowercase()));
-    }
-    true
+// What each current activity would look like under the given settings (merged like
+// `POST /privacy`), or under the active ones without a body
+async fn privacy_dry_run(
+    State(s): State<RestState>,
+    body: Option<Json<PrivacyUpdate>>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let policy = s.bus.privacy();
+    let candidate = body.map(|Json(p)| p.merge(policy.settings()));
+    let verdicts = policy.preview(candidate).map_err(privacy_error)?;
+    Ok(Json(serde_json::json!({"activities": verdicts})))
 }
 
 async fn clear_activity(
@@ -149,6 +296,75 @@ async fn clear_activity(
     Json(serde_json::json!({"ok": true}))
 }
 
+#[derive(Deserialize, Default)]
+struct SecretPayload {
+    secret: Option<String>,
+}
+
+async fn join_activity(
+    State(s): State<RestState>,
+    Path(socket_id): Path<String>,
+    body: Option<Json<SecretPayload>>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let secret = resolve_secret(&s, &socket_id, body, "join")?;
+    s.bus.publish(EventKind::ActivityJoin { socket_id, secret });
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+async fn spectate_activity(
+    State(s): State<RestState>,
+    Path(socket_id): Path<String>,
+    body: Option<Json<SecretPayload>>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let secret = resolve_secret(&s, &socket_id, body, "spectate")?;
+    s.bus
+        .publish(EventKind::ActivitySpectate { socket_id, secret });
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
+// Explicit secret from the body, else the one the activity itself advertised
+fn resolve_secret(
+    s: &RestState,
+    socket_id: &str,
+    body: Option<Json<SecretPayload>>,
+    kind: &str,
+) -> Result<String, axum::http::StatusCode> {
+    let activity = s
+        .registry
+        .get(socket_id)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    body.and_then(|Json(b)| b.secret)
+        .or_else(|| {
+            activity
+                .get("secrets")
+                .and_then(|sec| sec.get(kind))
+                .and_then(|v| v.as_str())
+                .map(str::to_owned)
+        })
+        .ok_or(axum::http::StatusCode::BAD_REQUEST)
+}
+
+#[derive(Deserialize, Default)]
+struct JoinRequestPayload {
+    user: Option<serde_json::Value>,
+}
+
+async fn join_request(
+    State(s): State<RestState>,
+    Path(socket_id): Path<String>,
+    body: Option<Json<JoinRequestPayload>>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    if s.registry.get(&socket_id).is_none() {
+        return Err(axum::http::StatusCode::NOT_FOUND);
+    }
+    let user = body
+        .and_then(|Json(b)| b.user)
+        .unwrap_or_else(|| serde_json::to_value(MockUser::default()).unwrap());
+    s.bus
+        .publish(EventKind::ActivityJoinRequest { socket_id, user });
+    Ok(Json(serde_json::json!({"ok": true})))
+}
+
 #[derive(Deserialize)]
 struct RefreshQuery {
     force: Option<bool>,
@@ -160,13 +376,16 @@ async fn refresh_detectables(
 ) -> Json<serde_json::Value> {
     if let Some(det) = &s.detectables {
         let force = q.force.unwrap_or(true); // default force
-        match drpc_core::load_detectables_async(force, s.detectables_ttl).await {
+        let opts = DetectablesOptions {
+            force_refresh: force,
+            ..s.detectables_options.clone()
+        };
+        match drpc_core::load_detectables_with(&opts).await {
             Ok(new_det) => {
-                // replace inner list without swapping Arc
-                let new_list = new_det.list();
-                let mut write_guard = det.inner.write();
-                *write_guard = (*new_list).clone();
-                return Json(serde_json::json!({"ok": true, "count": write_guard.len()}));
+                let count = new_det.len();
+                det.replace(new_det.list());
+                s.bus.publish(EventKind::DetectablesUpdated { count });
+                return Json(serde_json::json!({"ok": true, "count": count}));
             }
             Err(e) => return Json(serde_json::json!({"ok": false, "error": format!("{e}")})),
         }
@@ -174,6 +393,30 @@ async fn refresh_detectables(
     Json(serde_json::json!({"ok": false, "error": "detectables_not_enabled"}))
 }
 
+async fn local_detectables() -> Json<serde_json::Value> {
+    let local = drpc_core::load_local_detectables();
+    Json(serde_json::json!({
+        "path": drpc_core::local_detectables_path(),
+        "local": local,
+    }))
+}
+
+async fn get_detectable(
+    State(s): State<RestState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
+    let det = s
+        .detectables
+        .as_ref()
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    let list = det.list();
+    let entry = list
+        .iter()
+        .find(|d| d.id.as_deref() == Some(id.as_str()))
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    Ok(Json(serde_json::to_value(entry).unwrap()))
+}
+
 async fn metrics() -> Json<serde_json::Value> {
     Json(drpc_core::metrics::snapshot())
 }
@@ -0,0 +1,70 @@
+use drpc_core::EventKind;
+use reqwest::{Client, StatusCode};
+
+#[tokio::test]
+async fn rest_triggers_join_events() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), Default::default())
+        .await
+        .expect("rest");
+    let base = format!("http://127.0.0.1:{}", port);
+    let client = Client::new();
+    let mut rx = bus.subscribe();
+
+    let resp = client
+        .post(format!("{}/activities/nobody/join", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    registry.set(
+        "sock",
+        serde_json::json!({"name": "G", "secrets": {"join": "from-activity"}}),
+    );
+    // Secret defaults to the one the activity advertised
+    let resp = client
+        .post(format!("{}/activities/sock/join", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    match rx.recv().await {
+        Some(EventKind::ActivityJoin { socket_id, secret }) => {
+            assert_eq!(socket_id, "sock");
+            assert_eq!(secret, "from-activity");
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    // No spectate secret on the activity and none supplied
+    let resp = client
+        .post(format!("{}/activities/sock/spectate", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    client
+        .post(format!("{}/activities/sock/spectate", base))
+        .json(&serde_json::json!({"secret": "explicit"}))
+        .send()
+        .await
+        .unwrap();
+    match rx.recv().await {
+        Some(EventKind::ActivitySpectate { secret, .. }) => assert_eq!(secret, "explicit"),
+        other => panic!("unexpected {other:?}"),
+    }
+
+    client
+        .post(format!("{}/activities/sock/join-request", base))
+        .send()
+        .await
+        .unwrap();
+    match rx.recv().await {
+        Some(EventKind::ActivityJoinRequest { user, .. }) => assert_eq!(user["username"], "drpc"),
+        other => panic!("unexpected {other:?}"),
+    }
+}
@@ -0,0 +1,154 @@
+use reqwest::Client;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn normalized_activity_can_be_posted_back() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), Default::default())
+        .await
+        .expect("rest");
+    let base = format!("http://127.0.0.1:{}", port);
+    let client = Client::new();
+    let activity = json!({
+        "name": "RoundTrip",
+        "timestamps": {"start": 1_700_000_000},
+        "buttons": [{"label": "Site", "url": "https://example.com"}]
+    });
+    let post = |sid: &'static str, activity: Value| {
+        client
+            .post(format!("{}/activities", base))
+            .json(&json!({"socket_id": sid, "activity": activity}))
+            .send()
+    };
+    let fetch = |sid: &'static str| {
+        let client = client.clone();
+        let base = base.clone();
+        async move {
+            for _ in 0..20 {
+                let list: Value = client
+                    .get(format!("{}/activities", base))
+                    .send()
+                    .await
+                    .unwrap()
+                    .json()
+                    .await
+                    .unwrap();
+                if let Some(e) = list["activities"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|e| e[0] == sid)
+                {
+                    return e[1].clone();
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+            }
+            panic!("activity {sid} not found");
+        }
+    };
+
+    assert_eq!(post("rt-1", activity).await.unwrap().status(), 200);
+    let first = fetch("rt-1").await;
+    assert_eq!(first["buttons"], json!(["Site"]));
+    assert_eq!(
+        first["metadata"]["button_urls"],
+        json!(["https://example.com"])
+    );
+
+    // Feeding the wire form back in must not change it
+    assert_eq!(post("rt-2", first.clone()).await.unwrap().status(), 200);
+    let second = fetch("rt-2").await;
+    assert_eq!(second, first);
+}
+
+#[tokio::test]
+async fn listed_activities_carry_resolved_image_urls() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let registry = std::sync::Arc::new(drpc_core::ActivityRegistry::new());
+    let resolver = drpc_core::AssetResolver::default();
+    resolver.insert(
+        "123",
+        vec![drpc_core::AppAsset {
+            id: "555".into(),
+            name: "logo".into(),
+            kind: 1,
+        }],
+    );
+    let port = drpc_rest::run_rest(
+        drpc_core::EventBus::new(),
+        registry.clone(),
+        drpc_rest::RestOptions {
+            assets: std::sync::Arc::new(resolver),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("rest");
+    registry.set(
+        "s1",
+        json!({
+            "name": "Art",
+            "application_id": "123",
+            "assets": {"large_image": "logo", "small_image": "mp:external/a/b.png"}
+        }),
+    );
+    let list: Value = Client::new()
+        .get(format!("http://127.0.0.1:{}/activities", port))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let assets = &list["activities"][0][1]["assets"];
+    assert_eq!(
+        assets["large_image_url"],
+        "https://cdn.discordapp.com/app-assets/123/555.png"
+    );
+    assert_eq!(
+        assets["small_image_url"],
+        "https://media.discordapp.net/external/a/b.png"
+    );
+}
+
+#[tokio::test]
+async fn serves_cached_images_by_hash() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let dir = std::env::temp_dir().join(format!("drpc-rest-images-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let hash = drpc_core::url_hash("https://example.com/cover.png");
+    std::fs::write(dir.join(format!("{hash}.png")), b"png").unwrap();
+    let cache = drpc_core::ImageCache::open(drpc_core::ImageCacheOptions {
+        dir: dir.clone(),
+        ..Default::default()
+    })
+    .unwrap();
+    let cache = std::sync::Arc::new(cache);
+    let resolver = drpc_core::AssetResolver::default().with_image_cache(cache.clone());
+    let port = drpc_rest::run_rest(
+        drpc_core::EventBus::new(),
+        drpc_core::ActivityRegistry::new().into(),
+        drpc_rest::RestOptions {
+            assets: std::sync::Arc::new(resolver),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("rest");
+    // REST announces where it serves the cache from
+    let url = cache.local_url(&hash).unwrap();
+    assert_eq!(url, format!("http://127.0.0.1:{port}/assets/{hash}"));
+    let resp = Client::new().get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers()["content-type"], "image/png");
+    assert_eq!(resp.bytes().await.unwrap().as_ref(), b"png");
+    let missing = Client::new()
+        .get(format!("http://127.0.0.1:{port}/assets/{}", "0".repeat(32)))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), 404);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,72 @@
+use drpc_core::AuthConfig;
+use drpc_rest::RestOptions;
+use reqwest::{Client, StatusCode};
+use serde_json::json;
+
+#[tokio::test]
+async fn routes_require_token_and_local_host() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let port = drpc_rest::run_rest(
+        bus,
+        registry.into(),
+        RestOptions {
+            auth: AuthConfig::default().with_token("t0ken"),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("rest");
+    let base = format!("http://127.0.0.1:{port}");
+    let client = Client::new();
+
+    let status = |req: reqwest::RequestBuilder| async move { req.send().await.unwrap().status() };
+    assert_eq!(
+        status(client.get(format!("{base}/activities"))).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            client
+                .post(format!("{base}/privacy"))
+                .bearer_auth("wrong")
+                .json(&json!({"deny": ["x"]}))
+        )
+        .await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(
+            client
+                .get(format!("{base}/activities"))
+                .bearer_auth("t0ken")
+        )
+        .await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(client.get(format!("{base}/health"))).await,
+        StatusCode::OK
+    );
+    // DNS rebinding: right address and token, foreign Host
+    assert_eq!(
+        status(
+            client
+                .get(format!("{base}/activities"))
+                .bearer_auth("t0ken")
+                .header("host", "evil.example")
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(
+            client
+                .get(format!("{base}/health"))
+                .header("host", "evil.example")
+        )
+        .await,
+        StatusCode::FORBIDDEN
+    );
+}
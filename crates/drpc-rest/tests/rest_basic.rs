@@ -9,7 +9,7 @@ async fn rest_activity_and_metrics() {
     // Spin minimal components: build a bus + registry and rest server directly
     let bus = drpc_core::EventBus::new();
     let registry = drpc_core::ActivityRegistry::new();
-    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), None, 24, 0)
+    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), Default::default())
         .await
         .expect("rest");
     let base = format!("http://127.0.0.1:{}", port);
@@ -0,0 +1,96 @@
+use drpc_rest::RestOptions;
+use reqwest::Client;
+use serde_json::Value;
+
+#[tokio::test]
+async fn local_detectables_visible_through_rest() {
+    rustls::crypto::aws_lc_rs::default_provider()
+        .install_default()
+        .expect("crypto provider already set");
+    let base_dir = std::env::temp_dir().join(format!(
+        "drpc-rest-local-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    ));
+    std::fs::create_dir_all(base_dir.join(".drpc")).unwrap();
+    unsafe {
+        std::env::set_var("HOME", &base_dir);
+        std::env::set_var("USERPROFILE", &base_dir);
+        std::env::set_var("DRPC_OFFLINE", "1");
+    }
+    std::fs::write(
+        base_dir.join(".drpc").join("detectables.json"),
+        r#"[{"id":"100","name":"Remote Game","executables":[{"name":"remote.exe","is_launcher":false}]}]"#,
+    )
+    .unwrap();
+    std::fs::write(
+        base_dir.join(".drpc").join("detectables.local.toml"),
+        r#"
+[[entries]]
+id = "local-1"
+name = "Indie Game"
+executables = [{ name = "indie" }]
+
+[overrides."100"]
+name = "Renamed Game"
+"#,
+    )
+    .unwrap();
+    let detectables = drpc_core::load_detectables_async(false, 1000)
+        .await
+        .unwrap();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let opts = drpc_core::DetectablesOptions {
+        ttl_hours: 1000,
+        ..Default::default()
+    };
+    let port = drpc_rest::run_rest(
+        bus,
+        registry.into(),
+        RestOptions {
+            detectables: Some(detectables),
+            detectables_options: opts,
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("rest");
+    let base = format!("http://127.0.0.1:{}", port);
+    let client = Client::new();
+
+    let entry: Value = client
+        .get(format!("{}/detectables/100", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(entry["name"], "Renamed Game");
+    let local_entry = client
+        .get(format!("{}/detectables/local-1", base))
+        .send()
+        .await
+        .unwrap();
+    assert!(local_entry.status().is_success());
+    let missing = client
+        .get(format!("{}/detectables/404", base))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let local: Value = client
+        .get(format!("{}/detectables/local", base))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(local["local"]["entries"][0]["name"], "Indie Game");
+    assert_eq!(local["local"]["overrides"]["100"]["name"], "Renamed Game");
+}
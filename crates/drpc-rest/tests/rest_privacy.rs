@@ -0,0 +1,190 @@
+use drpc_core::{ActivityOrigin, EventKind};
+use reqwest::Client;
+use serde_json::{json, Value};
+
+async fn names(client: &Client, base: &str) -> Vec<String> {
+    let list: Value = client
+        .get(format!("{base}/activities"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let mut names: Vec<String> = list["activities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|e| e[1]["name"].as_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+async fn wait_for(client: &Client, base: &str, want: &[&str]) {
+    for _ in 0..50 {
+        if names(client, base).await == want {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(names(client, base).await, want);
+}
+
+#[tokio::test]
+async fn privacy_applies_to_all_origins_and_clears_retroactively() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), Default::default())
+        .await
+        .expect("rest");
+    let base = format!("http://127.0.0.1:{port}");
+    let client = Client::new();
+
+    // Activities that never went through REST
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "ipc-1".into(),
+        origin: ActivityOrigin::Ipc,
+        payload: json!({"name": "Secret Game"}),
+    });
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "proc-1".into(),
+        origin: ActivityOrigin::Process,
+        payload: json!({"name": "Open Game"}),
+    });
+    wait_for(&client, &base, &["Open Game", "Secret Game"]).await;
+
+    let resp = client
+        .post(format!("{base}/privacy"))
+        .json(&json!({"deny": ["secret"]}))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+    wait_for(&client, &base, &["Open Game"]).await;
+
+    // New activities from any transport are filtered too
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "ws-1".into(),
+        origin: ActivityOrigin::Ws,
+        payload: json!({"name": "Another Secret"}),
+    });
+    client
+        .post(format!("{base}/activities"))
+        .json(&json!({"activity": {"name": "Rest Secret"}}))
+        .send()
+        .await
+        .unwrap();
+    client
+        .post(format!("{base}/activities"))
+        .json(&json!({"activity": {"name": "Rest Visible"}}))
+        .send()
+        .await
+        .unwrap();
+    wait_for(&client, &base, &["Open Game", "Rest Visible"]).await;
+
+    // Omitted lists are left as they were
+    client
+        .post(format!("{base}/privacy"))
+        .json(&json!({"allow": ["game"]}))
+        .send()
+        .await
+        .unwrap();
+    let lists: Value = client
+        .get(format!("{base}/privacy"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        lists,
+        json!({"allow": ["game"], "deny": ["secret"], "rules": []})
+    );
+    wait_for(&client, &base, &["Open Game"]).await;
+}
+
+#[tokio::test]
+async fn rules_are_managed_over_rest_with_a_dry_run() {
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let bus = drpc_core::EventBus::new();
+    let registry = drpc_core::ActivityRegistry::new();
+    let port = drpc_rest::run_rest(bus.clone(), registry.clone().into(), Default::default())
+        .await
+        .expect("rest");
+    let base = format!("http://127.0.0.1:{port}");
+    let client = Client::new();
+    bus.publish(EventKind::ActivityUpdate {
+        socket_id: "ipc-1".into(),
+        origin: ActivityOrigin::Ipc,
+        payload: json!({"name": "Work Project", "application_id": "7", "details": "Editing"}),
+    });
+    wait_for(&client, &base, &["Work Project"]).await;
+
+    let redact = json!({"rules": [{
+        "match": {"client_id": "7"},
+        "action": "redact",
+        "redact": {"hide_details": true, "replace_name": "Playing a game"},
+    }]});
+    let dry: Value = client
+        .post(format!("{base}/privacy/dry-run"))
+        .json(&redact)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(
+        dry["activities"],
+        json!([{
+            "socket_id": "ipc-1",
+            "origin": "ipc",
+            "rule": 0,
+            "action": "redact",
+            "activity": {"name": "Playing a game"},
+        }])
+    );
+    // Only a preview: nothing changed yet
+    wait_for(&client, &base, &["Work Project"]).await;
+
+    let bad = client
+        .post(format!("{base}/privacy"))
+        .json(&json!({"rules": [{"match": {"name": "(["}, "action": "deny"}]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad.status(), reqwest::StatusCode::BAD_REQUEST);
+    let body: Value = bad.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "rules[0].match.name");
+
+    client
+        .post(format!("{base}/privacy"))
+        .json(&redact)
+        .send()
+        .await
+        .unwrap();
+    wait_for(&client, &base, &["Playing a game"]).await;
+    let settings: Value = client
+        .get(format!("{base}/privacy"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(settings["rules"], redact["rules"]);
+
+    // Without a body the dry run shows the active settings
+    let dry: Value = client
+        .post(format!("{base}/privacy/dry-run"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(dry["activities"][0]["activity"]["name"], "Playing a game");
+}
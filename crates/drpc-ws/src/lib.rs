@@ -2,21 +2,26 @@ use axum::{
     Router,
     extract::{
         Query,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
     },
     http::HeaderMap,
     response::IntoResponse,
     routing::get,
 };
 use drpc_core::{
-    Activity, EventBus, EventKind, MockUser, OutgoingFrame, ReadyConfig, ReadyEvent, RpcCommand,
+    ActivityOrigin, AuthenticateArgs, AuthorizeArgs, EventBus, EventKind, IncomingFrame, JsonCodec,
+    MockUser, OutgoingFrame, ReadyConfig, ReadyEvent, RpcCloseCode, RpcCommand, RpcErrorCode,
+    RpcEvent, SetActivityArgs, Subscriptions, WireCodec, WireMessage, negotiate_codec,
+    validate_activity,
 };
 use futures::StreamExt;
 use serde::Deserialize;
 use serde_json::json;
-use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{debug, info, info_span, warn};
 
+const MAX_FRAME_BYTES: usize = 64 * 1024;
+
 pub async fn run_ws_server(bus: EventBus) -> anyhow::Result<u16> {
     // Bind atomically to avoid test races selecting the same port
     let mut listener_opt = None;
@@ -60,6 +65,31 @@ async fn ws_handler(
     ws: WebSocketUpgrade,
     bus: EventBus,
 ) -> impl IntoResponse {
+    // Like Discord, bad handshakes are upgraded and then closed with an RPC close code
+    match check_handshake(&headers, &q) {
+        Ok(codec) => {
+            let session = Session {
+                socket_id: uuid::Uuid::new_v4().to_string(),
+                client_id: q.client_id,
+                codec,
+                subscriptions: Subscriptions::default(),
+            };
+            ws.on_upgrade(move |socket| async move { handle_socket(socket, bus, session).await })
+        }
+        Err(code) => {
+            debug!(?code, "rejecting rpc handshake");
+            ws.on_upgrade(move |mut socket| async move {
+                let frame = CloseFrame {
+                    code: code.into(),
+                    reason: code.reason().into(),
+                };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+            })
+        }
+    }
+}
+
+fn check_handshake(headers: &HeaderMap, q: &WsQuery) -> Result<Arc<dyn WireCodec>, RpcCloseCode> {
     if let Some(orig) = headers.get("origin") {
         let o = orig.to_str().unwrap_or("");
         if std::env::var("DRPC_DEBUG").ok().as_deref() != Some("1")
@@ -68,218 +98,91 @@ async fn ws_handler(
                 "https://discord.com" | "https://ptb.discord.com" | "https://canary.discord.com"
             )
         {
-            return axum::http::StatusCode::FORBIDDEN.into_response();
+            return Err(RpcCloseCode::InvalidOrigin);
         }
     }
     if q.v.unwrap_or(1) != 1 {
-        return axum::http::StatusCode::BAD_REQUEST.into_response();
-    }
-    let encoding = q.encoding.as_deref().unwrap_or("json").to_lowercase();
-    if encoding != "json" && encoding != "etf" {
-        return axum::http::StatusCode::BAD_REQUEST.into_response();
+        return Err(RpcCloseCode::InvalidVersion);
     }
     if q.client_id.is_none() {
-        return axum::http::StatusCode::BAD_REQUEST.into_response();
+        return Err(RpcCloseCode::InvalidClientId);
+    }
+    let encoding = q.encoding.as_deref().unwrap_or("json").to_lowercase();
+    match negotiate_codec(&encoding) {
+        Ok(c) => Ok(c),
+        // Clients asking for ETF still get a working connection when it isn't compiled in
+        Err(e) if encoding == "etf" => {
+            warn!(error=%e, "etf requested but not supported; using json for this connection");
+            Ok(Arc::new(JsonCodec))
+        }
+        Err(_) => Err(RpcCloseCode::InvalidEncoding),
     }
-    let use_etf = encoding == "etf";
-    ws.on_upgrade(move |socket| async move { handle_socket(socket, bus, use_etf).await })
 }
 
-async fn handle_socket(mut socket: WebSocket, bus: EventBus, use_etf: bool) {
-    let socket_id = uuid::Uuid::new_v4().to_string();
+// Per-connection state fixed at handshake, plus what the client has subscribed to
+struct Session {
+    socket_id: String,
+    client_id: Option<String>,
+    codec: Arc<dyn WireCodec>,
+    subscriptions: Subscriptions,
+}
+
+async fn handle_socket(mut socket: WebSocket, bus: EventBus, mut session: Session) {
+    let codec = session.codec.clone();
     drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-    let span = info_span!("ws_connection", %socket_id);
+    let span = info_span!(
+        "ws_connection",
+        socket_id = %session.socket_id,
+        codec = codec.name()
+    );
     let _enter = span.enter();
     let ready = ReadyEvent {
         v: 1,
         config: ReadyConfig::default(),
         user: MockUser::default(),
     };
-    let frame = OutgoingFrame {
-        cmd: RpcCommand::Dispatch,
-        evt: Some("READY".into()),
-        data: serde_json::to_value(ready).unwrap(),
-        nonce: None,
-        pid: None,
-    };
-    if use_etf {
-        #[cfg(feature = "etf")]
-        {
-            match encode_frame_etf(&frame) {
-                Ok(bin) => {
-                    if socket.send(Message::Binary(bin.into())).await.is_err() {
-                        return;
-                    }
-                }
-                Err(e) => {
-                    warn!(error=?e, "etf encode failed; fallback json");
-                    let json_txt = serde_json::to_string(&frame).unwrap();
-                    if socket.send(Message::Text(json_txt.into())).await.is_err() {
-                        return;
-                    }
+    let frame = OutgoingFrame::dispatch(RpcEvent::Ready, ready);
+    if !send_frame(&mut socket, &frame.to_value(), codec.as_ref()).await {
+        return;
+    }
+    let mut events = bus.subscribe();
+    loop {
+        let msg = tokio::select! {
+            msg = socket.next() => match msg {
+                Some(Ok(m)) => m,
+                _ => break,
+            },
+            Some(evt) = events.recv() => {
+                if let Some(out) = session.subscriptions.dispatch(&session.socket_id, &evt)
+                    && !send_frame(&mut socket, &out.to_value(), codec.as_ref()).await
+                {
+                    break;
                 }
+                continue;
             }
-        }
-        #[cfg(not(feature = "etf"))]
-        {
-            debug!("ETF requested but feature disabled; fallback json");
-            let json_txt = serde_json::to_string(&frame).unwrap();
-            if socket.send(Message::Text(json_txt.into())).await.is_err() {
-                return;
-            }
-        }
-    } else {
-        let json_txt = serde_json::to_string(&frame).unwrap();
-        if socket.send(Message::Text(json_txt.into())).await.is_err() {
-            return;
-        }
-    }
-    while let Some(Ok(msg)) = socket.next().await {
-        match msg {
+        };
+        let val = match msg {
             Message::Text(txt) => {
-                if txt.len() > 64 * 1024 {
+                if txt.len() > MAX_FRAME_BYTES {
                     let _ = socket.send(Message::Close(None)).await;
                     break;
                 }
                 debug!(%txt, "recv ws");
-                // Placeholder dispatch parsing
-                let val: serde_json::Value =
-                    serde_json::from_str(&txt).unwrap_or(json!({"_parse": "error"}));
-                // Recognize SET_ACTIVITY minimal path
-                let maybe_cmd = val
-                    .get("cmd")
-                    .and_then(|c| c.as_str())
-                    .unwrap_or("")
-                    .to_uppercase();
-                if maybe_cmd == "SET_ACTIVITY" {
-                    if let Some(err) = validate_activity_payload(&val) {
-                        let _ = send_json_or_etf(&mut socket, &err, use_etf).await;
-                        continue;
-                    }
-                    if let Some(resp) = build_activity_update(&val) {
-                        if !send_json_or_etf(&mut socket, &resp, use_etf).await {
-                            break;
-                        }
-                        if let Some(activity) = resp.get("data").and_then(|d| d.get("activity")) {
-                            bus.publish(EventKind::ActivityUpdate {
-                                socket_id: socket_id.clone(),
-                                payload: activity.clone(),
-                            });
-                        }
-                        continue;
-                    }
-                }
-                if maybe_cmd == "PING" {
-                    let nonce = val.get("nonce").cloned();
-                    let pong = json!({"cmd":"DISPATCH","evt":"PONG","data":{},"nonce":nonce});
-                    let _ = send_json_or_etf(&mut socket, &pong, use_etf).await;
-                    continue;
-                }
-                if maybe_cmd == "AUTHORIZE" {
-                    let nonce = val.get("nonce").cloned();
-                    let args = val.get("args");
-                    let client_id_ok = args
-                        .and_then(|a| a.get("client_id").and_then(|v| v.as_str()))
-                        .is_some();
-                    let scopes_ok = args
-                        .and_then(|a| a.get("scopes").and_then(|v| v.as_array()))
-                        .is_some();
-                    let (code, message) = if client_id_ok && scopes_ok {
-                        (1000, "Authorization not supported in drpc")
-                    } else {
-                        (4000, "Invalid payload: missing client_id or scopes")
-                    };
-                    let out = json!({"cmd":"AUTHORIZE","evt":"ERROR","data":{"code":code,"message":message},"nonce":nonce});
-                    let _ = send_json_or_etf(&mut socket, &out, use_etf).await;
-                    continue;
-                }
-                if maybe_cmd == "AUTHENTICATE" {
-                    let nonce = val.get("nonce").cloned();
-                    let args = val.get("args");
-                    let token_ok = args
-                        .and_then(|a| a.get("access_token").and_then(|v| v.as_str()))
-                        .is_some();
-                    let (code, message) = if token_ok {
-                        (1000, "Authentication not supported in drpc")
-                    } else {
-                        (4000, "Invalid payload: missing access_token")
-                    };
-                    let out = json!({"cmd":"AUTHENTICATE","evt":"ERROR","data":{"code":code,"message":message},"nonce":nonce});
-                    let _ = send_json_or_etf(&mut socket, &out, use_etf).await;
-                    continue;
+                // Text frames are JSON whatever the negotiated codec
+                serde_json::from_str(&txt).unwrap_or(json!({"_parse": "error"}))
+            }
+            Message::Binary(bin) => {
+                if bin.len() > MAX_FRAME_BYTES {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
                 }
-                if matches!(maybe_cmd.as_str(), "SUBSCRIBE" | "UNSUBSCRIBE") {
-                    let nonce = val.get("nonce").cloned();
-                    let evt_name = val
-                        .get("args")
-                        .and_then(|a| a.get("event"))
-                        .and_then(|e| e.as_str());
-                    if evt_name.is_none() {
-                        let err = json!({"cmd": maybe_cmd, "evt":"ERROR","data":{"code":4000, "message":"Invalid payload: missing args.event"}, "nonce": nonce});
-                        let _ = send_json_or_etf(&mut socket, &err, use_etf).await;
+                match codec.decode_value(&WireMessage::Binary(bin.into())) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!(error=%e, "undecodable binary frame ignored");
                         continue;
                     }
-                    // Minimal validation: allow known events else error
-                    let allowed = matches!(
-                        evt_name.unwrap(),
-                        "GUILD_STATUS"
-                            | "GUILD_CREATE"
-                            | "CHANNEL_CREATE"
-                            | "VOICE_CHANNEL_SELECT"
-                            | "VOICE_STATE_CREATE"
-                            | "VOICE_STATE_UPDATE"
-                            | "VOICE_STATE_DELETE"
-                            | "VOICE_SETTINGS_UPDATE"
-                            | "VOICE_CONNECTION_STATUS"
-                            | "SPEAKING_START"
-                            | "SPEAKING_STOP"
-                            | "MESSAGE_CREATE"
-                            | "MESSAGE_UPDATE"
-                            | "MESSAGE_DELETE"
-                            | "NOTIFICATION_CREATE"
-                            | "ACTIVITY_JOIN"
-                            | "ACTIVITY_SPECTATE"
-                            | "ACTIVITY_JOIN_REQUEST"
-                    );
-                    if !allowed {
-                        let err = json!({"cmd": maybe_cmd, "evt":"ERROR","data":{"code":4000, "message":"Invalid payload: unknown event"}, "nonce": nonce});
-                        let _ = send_json_or_etf(&mut socket, &err, use_etf).await;
-                        continue;
-                    }
-                    let ack = json!({"cmd": maybe_cmd, "evt":"ACK","data":{},"nonce":nonce});
-                    let _ = send_json_or_etf(&mut socket, &ack, use_etf).await;
-                    continue;
-                }
-                if maybe_cmd == "CONNECTIONS_CALLBACK" {
-                    let nonce = val.get("nonce").cloned();
-                    let out = json!({
-                        "cmd": "CONNECTIONS_CALLBACK",
-                        "evt": "ERROR",
-                        "data": {"code": 1000, "message": "Connections callback not supported"},
-                        "nonce": nonce,
-                    });
-                    let _ = send_json_or_etf(&mut socket, &out, use_etf).await;
-                    continue;
                 }
-                if matches!(
-                    maybe_cmd.as_str(),
-                    "INVITE_BROWSER" | "GUILD_TEMPLATE_BROWSER" | "DEEP_LINK"
-                ) {
-                    let nonce = val.get("nonce").cloned();
-                    let out =
-                        json!({"cmd": maybe_cmd, "evt":"ACK","data":{"ok":true}, "nonce": nonce});
-                    let _ = send_json_or_etf(&mut socket, &out, use_etf).await;
-                    continue;
-                }
-                // Unknown command -> ERROR
-                let nonce = val.get("nonce").cloned();
-                let err = json!({
-                    "cmd": maybe_cmd,
-                    "evt": "ERROR",
-                    "data": {"code": 4000, "message": "Invalid payload or unknown command"},
-                    "nonce": nonce,
-                });
-                let _ = send_json_or_etf(&mut socket, &err, use_etf).await;
             }
             Message::Close(c) => {
                 debug!(?c, "close");
@@ -287,324 +190,142 @@ async fn handle_socket(mut socket: WebSocket, bus: EventBus, use_etf: bool) {
             }
             Message::Ping(p) => {
                 let _ = socket.send(Message::Pong(p)).await;
+                continue;
             }
-            Message::Binary(bin) => {
-                #[cfg(feature = "etf")]
-                if use_etf {
-                    match decode_frame_etf(&bin) {
-                        Ok(val) => {
-                            handle_incoming_value(&mut socket, val, &bus, &socket_id, use_etf)
-                                .await;
-                            continue;
-                        }
-                        Err(e) => warn!(error=?e, "etf decode failed; ignoring frame"),
-                    }
-                }
-                warn!("binary ignored");
-            }
-            _ => {}
+            _ => continue,
+        };
+        if !handle_value(&mut socket, val, &bus, &mut session).await {
+            break;
         }
     }
-    bus.publish(EventKind::Clear { socket_id });
+    bus.publish(EventKind::Clear {
+        socket_id: session.socket_id,
+    });
     drpc_core::metrics::ACTIVE_CONNECTIONS.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
 }
 
-// shared handler for decoded JSON value (used by ETF path)
-async fn handle_incoming_value(
+// Handles one decoded frame; false once the socket is gone
+async fn handle_value(
     socket: &mut WebSocket,
     val: serde_json::Value,
     bus: &EventBus,
-    socket_id: &str,
-    use_etf: bool,
-) {
-    let maybe_cmd = val
-        .get("cmd")
-        .and_then(|c| c.as_str())
-        .unwrap_or("")
-        .to_uppercase();
-    if maybe_cmd == "SET_ACTIVITY" {
-        if let Some(err) = validate_activity_payload(&val) {
-            let _ = send_json_or_etf(socket, &err, use_etf).await;
-        } else if let Some(resp) = build_activity_update(&val) {
-            let _ = send_json_or_etf(socket, &resp, use_etf).await;
-            if let Some(activity) = resp.get("data").and_then(|d| d.get("activity")) {
+    session: &mut Session,
+) -> bool {
+    let codec = session.codec.clone();
+    let codec = codec.as_ref();
+    let frame = IncomingFrame::from_value(val);
+    let nonce = frame.nonce.clone();
+    let out = match &frame.cmd {
+        RpcCommand::SetActivity => {
+            if let Some(err) = validate_activity_payload(&frame) {
+                err
+            } else if let Some((resp, activity)) =
+                build_activity_update(&frame, session.client_id.as_deref())
+            {
+                if !send_frame(socket, &resp, codec).await {
+                    return false;
+                }
                 bus.publish(EventKind::ActivityUpdate {
-                    socket_id: socket_id.to_string(),
-                    payload: activity.clone(),
+                    socket_id: session.socket_id.clone(),
+                    origin: ActivityOrigin::Ws,
+                    payload: activity,
                 });
+                return true;
+            } else {
+                frame.error(
+                    RpcErrorCode::InvalidPayload,
+                    "Invalid payload: malformed activity",
+                )
             }
         }
-        return;
-    }
-    if maybe_cmd == "CONNECTIONS_CALLBACK" {
-        let out = json!({"cmd":"CONNECTIONS_CALLBACK","evt":"ERROR","data":{"code":1000, "message":"Connections callback not supported"}});
-        let _ = send_json_or_etf(socket, &out, use_etf).await;
-        return;
-    }
-    if matches!(
-        maybe_cmd.as_str(),
-        "INVITE_BROWSER" | "GUILD_TEMPLATE_BROWSER" | "DEEP_LINK" | "SUBSCRIBE" | "UNSUBSCRIBE"
-    ) {
-        let out = json!({"cmd": maybe_cmd, "evt":"ACK","data":{}});
-        let _ = send_json_or_etf(socket, &out, use_etf).await;
-        return;
-    }
-    if maybe_cmd == "AUTHORIZE" {
-        let args = val.get("args");
-        let client_id_ok = args
-            .and_then(|a| a.get("client_id").and_then(|v| v.as_str()))
-            .is_some();
-        let scopes_ok = args
-            .and_then(|a| a.get("scopes").and_then(|v| v.as_array()))
-            .is_some();
-        let (code, message) = if client_id_ok && scopes_ok {
-            (1000, "Authorization not supported in drpc")
-        } else {
-            (4000, "Invalid payload: missing client_id or scopes")
-        };
-        let out = json!({"cmd":"AUTHORIZE","evt":"ERROR","data":{"code":code,"message":message}});
-        let _ = send_json_or_etf(socket, &out, use_etf).await;
-        return;
-    }
-    if maybe_cmd == "AUTHENTICATE" {
-        let args = val.get("args");
-        let token_ok = args
-            .and_then(|a| a.get("access_token").and_then(|v| v.as_str()))
-            .is_some();
-        let (code, message) = if token_ok {
-            (1000, "Authentication not supported in drpc")
-        } else {
-            (4000, "Invalid payload: missing access_token")
-        };
-        let out =
-            json!({"cmd":"AUTHENTICATE","evt":"ERROR","data":{"code":code,"message":message}});
-        let _ = send_json_or_etf(socket, &out, use_etf).await;
-        return;
-    }
-    let out =
-        json!({"cmd": maybe_cmd, "evt":"ERROR","data":{"code":4000, "message":"Unknown command"}});
-    let _ = send_json_or_etf(socket, &out, use_etf).await;
-}
-#[cfg(feature = "etf")]
-fn encode_frame_etf(frame: &OutgoingFrame) -> anyhow::Result<Vec<u8>> {
-    use eetf::*;
-    let mut map = HashMap::new();
-    map.insert(
-        Term::from(Atom::from("cmd")),
-        Term::from(String::from(frame.cmd_as_str())),
-    );
-    if let Some(evt) = &frame.evt {
-        map.insert(Term::from(Atom::from("evt")), Term::from(evt.clone()));
-    }
-    map.insert(Term::from(Atom::from("data")), json_to_term(&frame.data)?);
-    if let Some(n) = &frame.nonce {
-        map.insert(Term::from(Atom::from("nonce")), Term::from(n.to_string()));
-    }
-    let term = Term::from(Map::from(map));
-    let mut buf = Vec::new();
-    term.encode(&mut buf)?;
-    Ok(buf)
-}
-
-#[cfg(feature = "etf")]
-fn decode_frame_etf(bin: &[u8]) -> anyhow::Result<serde_json::Value> {
-    use eetf::Term;
-    let term = Term::decode(bin)?;
-    term_to_json(&term)
-}
-
-#[cfg(feature = "etf")]
-fn json_to_term(v: &serde_json::Value) -> anyhow::Result<eetf::Term> {
-    use eetf::*;
-    Ok(match v {
-        serde_json::Value::Null => Term::from(Atom::from("nil")),
-        serde_json::Value::Bool(b) => Term::from(if *b {
-            Atom::from("true")
-        } else {
-            Atom::from("false")
-        }),
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Term::from(FixInteger::from(i as i32))
-            } else if let Some(u) = n.as_u64() {
-                Term::from(FixInteger::from(u as i32))
+        RpcCommand::Ping => OutgoingFrame::dispatch(RpcEvent::Pong, json!({})).with_nonce(nonce),
+        RpcCommand::Authorize => {
+            let args: AuthorizeArgs = frame.args().unwrap_or_default();
+            if args.client_id.is_some() && args.scopes.is_some() {
+                frame.error(
+                    RpcErrorCode::UnknownError,
+                    "Authorization not supported in drpc",
+                )
             } else {
-                Term::from(Float::try_from(n.as_f64().unwrap())?)
+                frame.error(
+                    RpcErrorCode::InvalidPayload,
+                    "Invalid payload: missing client_id or scopes",
+                )
             }
         }
-        serde_json::Value::String(s) => Term::from(s.clone()),
-        serde_json::Value::Array(arr) => {
-            let elements: Vec<Term> = arr.iter().map(|x| json_to_term(x).unwrap()).collect();
-            Term::from(List::from(elements))
-        }
-        serde_json::Value::Object(obj) => {
-            let mut map: HashMap<Term, Term> = HashMap::new();
-            for (k, val) in obj.iter() {
-                map.insert(Term::from(k.clone()), json_to_term(val)?);
+        RpcCommand::Authenticate => {
+            let args: AuthenticateArgs = frame.args().unwrap_or_default();
+            if args.access_token.is_some() {
+                frame.error(
+                    RpcErrorCode::UnknownError,
+                    "Authentication not supported in drpc",
+                )
+            } else {
+                frame.error(
+                    RpcErrorCode::InvalidPayload,
+                    "Invalid payload: missing access_token",
+                )
             }
-            Term::from(Map::from(map))
         }
-    })
-}
-
-#[cfg(feature = "etf")]
-fn term_to_json(t: &eetf::Term) -> anyhow::Result<serde_json::Value> {
-    use eetf::*;
-    use serde_json::{Map as JsonMap, Value};
-    Ok(match t {
-        Term::Atom(a) => Value::String(a.name.clone()),
-        Term::FixInteger(i) => Value::Number(i.value.into()),
-        Term::BigInteger(bi) => Value::String(bi.value.to_string()),
-        Term::Float(f) => serde_json::Number::from_f64(f.value)
-            .map(Value::Number)
-            .unwrap_or(Value::Null),
-        Term::Binary(b) => Value::String(String::from_utf8_lossy(&b.bytes).to_string()),
-        Term::ByteList(bl) => Value::String(String::from_utf8_lossy(&bl.bytes).to_string()),
-        Term::List(l) => Value::Array(
-            l.elements
-                .iter()
-                .map(|e| term_to_json(e).unwrap())
-                .collect(),
-        ),
-        Term::Tuple(tup) => Value::Array(
-            tup.elements
-                .iter()
-                .map(|e| term_to_json(e).unwrap())
-                .collect(),
+        RpcCommand::Subscribe | RpcCommand::Unsubscribe => session.subscriptions.handle(&frame),
+        RpcCommand::ConnectionsCallback => frame.error(
+            RpcErrorCode::UnknownError,
+            "Connections callback not supported",
         ),
-        Term::Map(m) => {
-            let mut jm = JsonMap::new();
-            for (k, v) in &m.map {
-                let key = match k {
-                    Term::Atom(a) => a.name.clone(),
-                    Term::Binary(b) => String::from_utf8_lossy(&b.bytes).to_string(),
-                    Term::ByteList(bl) => String::from_utf8_lossy(&bl.bytes).to_string(),
-                    other => other.to_string(),
-                };
-                jm.insert(key, term_to_json(v)?);
-            }
-            Value::Object(jm)
+        RpcCommand::InviteBrowser | RpcCommand::GuildTemplateBrowser | RpcCommand::DeepLink => {
+            OutgoingFrame::reply(frame.cmd.clone(), json!({"ok": true}), nonce)
+                .with_evt(RpcEvent::Ack)
         }
-        other => Value::String(format!("{:?}", other)),
-    })
+        _ => frame.error(RpcErrorCode::InvalidCommand, "Invalid command"),
+    };
+    let _ = send_frame(socket, &out.to_value(), codec).await;
+    true
 }
 
-// Provide a string conversion for RpcCommand for ETF map key value.
-trait RpcCommandAsStr {
-    fn cmd_as_str(&self) -> &'static str;
-}
-impl RpcCommandAsStr for OutgoingFrame {
-    fn cmd_as_str(&self) -> &'static str {
-        match self.cmd {
-            RpcCommand::Dispatch => "DISPATCH",
-            RpcCommand::SetActivity => "SET_ACTIVITY",
-            RpcCommand::InviteBrowser => "INVITE_BROWSER",
-            RpcCommand::GuildTemplateBrowser => "GUILD_TEMPLATE_BROWSER",
-            RpcCommand::DeepLink => "DEEP_LINK",
-            RpcCommand::ConnectionsCallback => "CONNECTIONS_CALLBACK",
-        }
-    }
-}
-// Build a unified ACTIVITY_UPDATE dispatch frame from raw incoming value
-fn build_activity_update(raw: &serde_json::Value) -> Option<serde_json::Value> {
-    let args = raw.get("args")?;
-    let act = args.get("activity")?;
-    let activity: Activity = serde_json::from_value(act.clone()).ok()?;
-    let norm = activity.normalize();
-    let pid = args.get("pid").and_then(|p| p.as_u64()).map(|p| p as u32);
-    let nonce = raw.get("nonce").cloned();
-    Some(json!({
-        "cmd": "DISPATCH",
-        "evt": "ACTIVITY_UPDATE",
-        "data": {"activity": norm},
-        "pid": pid,
-        "nonce": nonce,
-    }))
+// Build a unified ACTIVITY_UPDATE dispatch frame, plus the normalized activity for the bus
+fn build_activity_update(
+    frame: &IncomingFrame,
+    client_id: Option<&str>,
+) -> Option<(serde_json::Value, serde_json::Value)> {
+    let args: SetActivityArgs = frame.args().ok()?;
+    let norm = args.activity?.with_application_id(client_id).normalize();
+    let activity = serde_json::to_value(&norm).ok()?;
+    let out = OutgoingFrame::dispatch(RpcEvent::ActivityUpdate, json!({"activity": norm}))
+        .with_nonce(frame.nonce.clone())
+        .with_pid(args.pid);
+    Some((out.to_value(), activity))
 }
 
-async fn send_json_or_etf(
+// Frames the negotiated codec can't encode go out as JSON text instead of being dropped
+async fn send_frame(
     socket: &mut WebSocket,
     frame: &serde_json::Value,
-    use_etf: bool,
+    codec: &dyn WireCodec,
 ) -> bool {
-    if use_etf {
-        #[cfg(feature = "etf")]
-        {
-            match encode_value_etf(frame) {
-                Ok(bin) => return socket.send(Message::Binary(bin.into())).await.is_ok(),
-                Err(e) => warn!(error=?e, "etf encode failed; falling back to json"),
-            }
-        }
-    }
-    socket
-        .send(Message::Text(frame.to_string().into()))
-        .await
-        .is_ok()
-}
-
-// Validate activity payload per docs (partial): max 2 buttons
-fn validate_activity_payload(raw: &serde_json::Value) -> Option<serde_json::Value> {
-    let nonce = raw.get("nonce").cloned();
-    let args = match raw.get("args") {
-        Some(a) => a,
-        None => {
-            return Some(json!({
-                "cmd": "SET_ACTIVITY",
-                "evt": "ERROR",
-                "data": {"code": 4000, "message": "Invalid payload: missing args"},
-                "nonce": nonce,
-            }));
+    let msg = match codec.encode_value(frame) {
+        Ok(m) => m,
+        Err(e) => {
+            warn!(codec = codec.name(), error=%e, "encode failed; sending frame as json");
+            WireMessage::Text(frame.to_string())
         }
     };
-    let act = match args.get("activity") {
-        Some(a) => a,
-        None => {
-            return Some(json!({
-                "cmd": "SET_ACTIVITY",
-                "evt": "ERROR",
-                "data": {"code": 4000, "message": "Invalid payload: missing activity"},
-                "nonce": nonce,
-            }));
-        }
+    let msg = match msg {
+        WireMessage::Text(t) => Message::Text(t.into()),
+        WireMessage::Binary(b) => Message::Binary(b.into()),
     };
-    if !act.is_object() {
-        return Some(json!({
-            "cmd": "SET_ACTIVITY",
-            "evt": "ERROR",
-            "data": {"code": 4000, "message": "Invalid payload: activity must be object"},
-            "nonce": nonce,
-        }));
-    }
-    if let Some(btns) = act.get("buttons").and_then(|b| b.as_array()) {
-        if btns.len() > 2 {
-            return Some(json!({
-                "cmd": "SET_ACTIVITY",
-                "evt": "ERROR",
-                "data": {"code": 4002, "message": "Invalid payload: max 2 buttons"},
-                "nonce": nonce,
-            }));
-        }
-        // ensure each button has string label/url
-        for b in btns {
-            let label_ok = b.get("label").and_then(|v| v.as_str()).is_some();
-            let url_ok = b.get("url").and_then(|v| v.as_str()).is_some();
-            if !label_ok || !url_ok {
-                return Some(json!({
-                    "cmd": "SET_ACTIVITY",
-                    "evt": "ERROR",
-                    "data": {"code": 4000, "message": "Invalid payload: button requires label and url strings"},
-                    "nonce": nonce,
-                }));
-            }
-        }
-    }
-    None
+    socket.send(msg).await.is_ok()
 }
 
-#[cfg(feature = "etf")]
-fn encode_value_etf(v: &serde_json::Value) -> anyhow::Result<Vec<u8>> {
-    let term = json_to_term(v)?;
-    let mut buf = Vec::new();
-    term.encode(&mut buf)?;
-    Ok(buf)
+// Validate SET_ACTIVITY args against Discord's limits
+fn validate_activity_payload(frame: &IncomingFrame) -> Option<OutgoingFrame> {
+    let err = |message: &str| Some(frame.error(RpcErrorCode::InvalidPayload, message));
+    if frame.args.is_null() {
+        return err("Invalid payload: missing args");
+    }
+    let Some(act) = frame.args.get("activity") else {
+        return err("Invalid payload: missing activity");
+    };
+    validate_activity(act)
+        .err()
+        .map(|e| OutgoingFrame::invalid_activity(frame.cmd.clone(), &e, frame.nonce.clone()))
 }
@@ -1,3 +1,4 @@
+use drpc_core::RpcErrorCode;
 use futures::{SinkExt, StreamExt};
 use serde_json::Value;
 use tokio_tungstenite::connect_async;
@@ -40,5 +41,8 @@ async fn ws_set_activity_buttons_over_limit_errors() {
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
     assert_eq!(v["cmd"].as_str(), Some("SET_ACTIVITY"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4002));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
 }
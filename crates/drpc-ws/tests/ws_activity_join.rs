@@ -0,0 +1,81 @@
+use drpc_core::EventKind;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_json<S>(ws: &mut S) -> Value
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    match ws.next().await.expect("frame").expect("ok") {
+        Message::Text(t) => serde_json::from_str(&t).unwrap(),
+        other => panic!("expected text, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn ws_delivers_join_only_when_subscribed() {
+    let bus = drpc_core::EventBus::new();
+    let mut rx = bus.subscribe();
+    let port = drpc_ws::run_ws_server(bus.clone()).await.expect("start ws");
+    let (mut ws, _resp) = connect_async(format!(
+        "ws://127.0.0.1:{}/?v=1&encoding=json&client_id=1",
+        port
+    ))
+    .await
+    .expect("connect");
+    let _ = next_json(&mut ws).await; // READY
+
+    let set = r#"{"cmd":"SET_ACTIVITY","args":{"activity":{"name":"G","party":{"id":"p1"},"secrets":{"join":"s1"}}}}"#;
+    ws.send(Message::Text(set.into())).await.unwrap();
+    let _ = next_json(&mut ws).await;
+    let socket_id = loop {
+        if let Some(EventKind::ActivityUpdate { socket_id, .. }) = rx.recv().await {
+            break socket_id;
+        }
+    };
+
+    // Not subscribed yet: dropped, so the next frame is the SUBSCRIBE ack
+    bus.publish(EventKind::ActivityJoin {
+        socket_id: socket_id.clone(),
+        secret: "early".into(),
+    });
+    ws.send(Message::Text(
+        r#"{"cmd":"SUBSCRIBE","nonce":"s","args":{"event":"ACTIVITY_JOIN"}}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let ack = next_json(&mut ws).await;
+    assert_eq!(ack["evt"], "ACK");
+    assert_eq!(ack["data"]["evt"], "ACTIVITY_JOIN");
+
+    // Other sockets' events are not forwarded
+    bus.publish(EventKind::ActivityJoin {
+        socket_id: "someone-else".into(),
+        secret: "other".into(),
+    });
+    bus.publish(EventKind::ActivityJoin {
+        socket_id: socket_id.clone(),
+        secret: "s1".into(),
+    });
+    let join = next_json(&mut ws).await;
+    assert_eq!(join["cmd"], "DISPATCH");
+    assert_eq!(join["evt"], "ACTIVITY_JOIN");
+    assert_eq!(join["data"]["secret"], "s1");
+
+    ws.send(Message::Text(
+        r#"{"cmd":"UNSUBSCRIBE","nonce":"u","args":{"event":"ACTIVITY_JOIN"}}"#.into(),
+    ))
+    .await
+    .unwrap();
+    assert_eq!(next_json(&mut ws).await["cmd"], "UNSUBSCRIBE");
+    bus.publish(EventKind::ActivityJoin {
+        socket_id,
+        secret: "late".into(),
+    });
+    ws.send(Message::Text(r#"{"cmd":"PING","nonce":"p"}"#.into()))
+        .await
+        .unwrap();
+    assert_eq!(next_json(&mut ws).await["evt"], "PONG");
+}
@@ -38,6 +38,7 @@ async fn ws_set_activity_nonce_pid_json() {
     assert_eq!(v["data"]["activity"]["name"].as_str(), Some("GameX"));
 }
 
+#[cfg(feature = "etf")]
 #[tokio::test]
 async fn ws_set_activity_nonce_pid_etf() {
     let bus = drpc_core::EventBus::new();
@@ -1,3 +1,4 @@
+use drpc_core::RpcErrorCode;
 use futures::{SinkExt, StreamExt};
 use serde_json::Value;
 use tokio_tungstenite::connect_async;
@@ -33,7 +34,10 @@ async fn missing_args_errors() {
     };
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
 }
 
 #[tokio::test]
@@ -51,7 +55,10 @@ async fn missing_activity_errors() {
     };
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
 }
 
 #[tokio::test]
@@ -69,7 +76,10 @@ async fn non_object_activity_errors() {
     };
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
 }
 
 #[tokio::test]
@@ -90,5 +100,39 @@ async fn invalid_button_shape_errors() {
     };
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
+}
+
+#[tokio::test]
+async fn field_errors_are_reported() {
+    let (mut ws, _) = boot().await;
+    let p = serde_json::json!({"cmd":"SET_ACTIVITY","nonce":"n5","args":{"activity":{
+        "state": "a",
+        "timestamps": {"start": 10, "end": 1},
+    }}});
+    ws.send(tokio_tungstenite::tungstenite::Message::Text(
+        p.to_string().into(),
+    ))
+    .await
+    .unwrap();
+    let msg = ws.next().await.unwrap().unwrap();
+    let txt = match msg {
+        tokio_tungstenite::tungstenite::Message::Text(t) => t,
+        _ => panic!("expected text"),
+    };
+    let v: Value = serde_json::from_str(&txt).unwrap();
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
+    let fields: Vec<&str> = v["data"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, vec!["state", "timestamps"]);
 }
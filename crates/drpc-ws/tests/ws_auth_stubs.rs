@@ -1,3 +1,4 @@
+use drpc_core::RpcErrorCode;
 use futures::{SinkExt, StreamExt};
 use serde_json::Value;
 use tokio_tungstenite::connect_async;
@@ -27,7 +28,10 @@ async fn ws_authorize_and_authenticate_error_stubs() {
     let v1: Value = serde_json::from_str(&t1).unwrap();
     assert_eq!(v1["evt"].as_str(), Some("ERROR"));
     assert_eq!(v1["cmd"].as_str(), Some("AUTHORIZE"));
-    assert_eq!(v1["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v1["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
     // AUTHENTICATE missing token
     ws.send(tokio_tungstenite::tungstenite::Message::Text(
         "{\"cmd\":\"AUTHENTICATE\",\"nonce\":\"nt\"}".into(),
@@ -42,5 +46,8 @@ async fn ws_authorize_and_authenticate_error_stubs() {
     let v2: Value = serde_json::from_str(&t2).unwrap();
     assert_eq!(v2["evt"].as_str(), Some("ERROR"));
     assert_eq!(v2["cmd"].as_str(), Some("AUTHENTICATE"));
-    assert_eq!(v2["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v2["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidPayload)
+    );
 }
@@ -0,0 +1,40 @@
+use drpc_core::RpcCloseCode;
+use futures::StreamExt;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn close_code(query: &str) -> RpcCloseCode {
+    let bus = drpc_core::EventBus::new();
+    let port = drpc_ws::run_ws_server(bus).await.expect("start ws");
+    let (mut ws, _resp) = connect_async(format!("ws://127.0.0.1:{}/?{}", port, query))
+        .await
+        .expect("connect");
+    match ws.next().await.expect("close").expect("ok") {
+        Message::Close(Some(frame)) => RpcCloseCode::try_from(u16::from(frame.code)).unwrap(),
+        other => panic!("expected close, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn ws_missing_client_id_closes() {
+    assert_eq!(
+        close_code("v=1&encoding=json").await,
+        RpcCloseCode::InvalidClientId
+    );
+}
+
+#[tokio::test]
+async fn ws_bad_version_closes() {
+    assert_eq!(
+        close_code("v=2&encoding=json&client_id=1").await,
+        RpcCloseCode::InvalidVersion
+    );
+}
+
+#[tokio::test]
+async fn ws_bad_encoding_closes() {
+    assert_eq!(
+        close_code("v=1&encoding=xml&client_id=1").await,
+        RpcCloseCode::InvalidEncoding
+    );
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "etf")]
+
+use drpc_core::etf;
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+const START_MS: u64 = 1_700_000_000_000;
+const SNOWFLAKE: u64 = 1_234_567_890_123_456_789;
+const ABOVE_I64: u64 = u64::MAX - 1;
+
+fn small_big(v: u64) -> Vec<u8> {
+    let digits: Vec<u8> = v
+        .to_le_bytes()
+        .into_iter()
+        .rev()
+        .skip_while(|&b| b == 0)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let mut out = vec![110, digits.len() as u8, 0];
+    out.extend(digits);
+    out
+}
+
+#[tokio::test]
+async fn etf_preserves_wide_integers_and_atoms() {
+    let bus = drpc_core::EventBus::new();
+    let mut rx = bus.subscribe();
+    let port = drpc_ws::run_ws_server(bus).await.expect("start ws");
+    let (mut ws, _resp) = connect_async(format!(
+        "ws://127.0.0.1:{}/?v=1&encoding=etf&client_id=abc",
+        port
+    ))
+    .await
+    .expect("connect");
+    let _ = ws.next().await; // READY
+
+    // null and true go out as the atoms nil/true, wide numbers as SMALL_BIG_EXT
+    let frame = json!({
+        "cmd": "SET_ACTIVITY",
+        "nonce": "n1",
+        "args": {"activity": {
+            "name": "Wide",
+            "state": null,
+            "instance": true,
+            "timestamps": {"start": START_MS},
+            "snowflake": SNOWFLAKE,
+            "huge": ABOVE_I64,
+            "negative": -5_000_000_000i64,
+            "small": 7,
+        }},
+    });
+    let bin = etf::encode(&frame).unwrap();
+    assert!(bin.windows(5).any(|w| w == [115, 3, b'n', b'i', b'l']));
+    ws.send(Message::Binary(bin.into())).await.unwrap();
+
+    // Decode side: the bus sees exact JSON numbers and real null/bool values
+    let payload = loop {
+        if let Some(drpc_core::EventKind::ActivityUpdate { payload, .. }) = rx.recv().await {
+            break payload;
+        }
+    };
+    assert_eq!(payload["timestamps"]["start"].as_u64(), Some(START_MS));
+    assert_eq!(payload["snowflake"].as_u64(), Some(SNOWFLAKE));
+    assert_eq!(payload["huge"].as_u64(), Some(ABOVE_I64));
+    assert_eq!(payload["negative"].as_i64(), Some(-5_000_000_000));
+    assert!(payload.get("state").is_none());
+    assert_eq!(payload["flags"].as_u64(), Some(1));
+
+    // Encode side: the reply carries the same values
+    let Message::Binary(bin) = ws.next().await.unwrap().unwrap() else {
+        panic!("expected binary reply")
+    };
+    for v in [START_MS, SNOWFLAKE, ABOVE_I64] {
+        let enc = small_big(v);
+        assert!(
+            bin.windows(enc.len()).any(|w| w == enc),
+            "{v} not SMALL_BIG"
+        );
+    }
+    let reply = etf::decode(&bin).unwrap();
+    let act = &reply["data"]["activity"];
+    assert_eq!(act["timestamps"]["start"].as_u64(), Some(START_MS));
+    assert_eq!(act["snowflake"].as_u64(), Some(SNOWFLAKE));
+    assert_eq!(act["huge"].as_u64(), Some(ABOVE_I64));
+    assert_eq!(act["small"].as_u64(), Some(7));
+    assert!(reply["pid"].is_null());
+}
@@ -1,3 +1,5 @@
+#![cfg(feature = "etf")]
+
 use futures::StreamExt;
 use tokio_tungstenite::connect_async;
 
@@ -1,3 +1,5 @@
+#![cfg(feature = "etf")]
+
 use futures::{SinkExt, StreamExt};
 use tokio_tungstenite::connect_async;
 
@@ -33,3 +33,29 @@ async fn ws_ready_and_set_activity() {
     };
     assert!(txt.contains("ACTIVITY_UPDATE"));
 }
+
+#[tokio::test]
+async fn ws_listening_activity_uses_client_id() {
+    let bus = drpc_core::EventBus::new();
+    let mut rx = bus.subscribe();
+    let port = drpc_ws::run_ws_server(bus).await.expect("start ws");
+    let (mut ws, _resp) = connect_async(format!(
+        "ws://127.0.0.1:{}/?v=1&encoding=json&client_id=4242",
+        port
+    ))
+    .await
+    .expect("connect");
+    let _ = ws.next().await; // READY
+    ws.send(tokio_tungstenite::tungstenite::Message::Text(
+        r#"{"cmd":"SET_ACTIVITY","args":{"activity":{"name":"Player","type":2,"application_id":"1","details":"Song"}}}"#.into(),
+    ))
+    .await
+    .unwrap();
+    let payload = loop {
+        if let Some(drpc_core::EventKind::ActivityUpdate { payload, .. }) = rx.recv().await {
+            break payload;
+        }
+    };
+    assert_eq!(payload["type"], 2);
+    assert_eq!(payload["application_id"], "4242");
+}
@@ -43,6 +43,10 @@ async fn ws_subscribe_requires_event_and_valid_name() {
     )
     .unwrap();
     assert_eq!(v2["evt"].as_str(), Some("ERROR"));
+    assert_eq!(
+        v2["data"]["code"],
+        serde_json::json!(drpc_core::RpcErrorCode::InvalidEvent)
+    );
     // valid event name
     ws.send(tokio_tungstenite::tungstenite::Message::Text(
         "{\"cmd\":\"SUBSCRIBE\",\"nonce\":\"n3\",\"args\":{\"event\":\"GUILD_STATUS\"}}".into(),
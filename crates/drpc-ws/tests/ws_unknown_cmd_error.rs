@@ -1,3 +1,4 @@
+use drpc_core::RpcErrorCode;
 use futures::{SinkExt, StreamExt};
 use serde_json::Value;
 use tokio_tungstenite::connect_async;
@@ -26,6 +27,9 @@ async fn ws_unknown_command_returns_error() {
     };
     let v: Value = serde_json::from_str(&txt).unwrap();
     assert_eq!(v["evt"].as_str(), Some("ERROR"));
-    assert_eq!(v["data"]["code"].as_u64(), Some(4000));
+    assert_eq!(
+        v["data"]["code"],
+        serde_json::json!(RpcErrorCode::InvalidCommand)
+    );
     assert_eq!(v["nonce"].as_str(), Some("nx"));
 }
@@ -10,6 +10,9 @@ struct Cli {
     refresh_detectables: bool,
     #[arg(long)]
     detectables_ttl: Option<u64>,
+    // Repeatable; tried in order (http(s):// or file://)
+    #[arg(long = "detectables-source")]
+    detectables_sources: Vec<String>,
     #[arg(long)]
     no_process_scanning: bool,
     #[arg(long, value_parser=["pretty","json"], default_value="pretty")]
@@ -20,12 +23,25 @@ struct Cli {
     print_socket_paths: bool,
     #[arg(long, default_value_t = 0)]
     rest_port: u16,
+    // Download activity art to ~/.drpc/cache and point Bridge messages at REST's /assets
+    #[arg(long)]
+    image_cache: bool,
+    // Require the token from ~/.drpc/token on REST and Bridge connections
+    #[arg(long)]
+    auth: bool,
 }
 
 #[derive(Debug, Default, serde::Deserialize)]
 struct FileConfig {
     bridge_port: Option<u16>,
     detectables_ttl: Option<u64>,
+    detectables_sources: Option<Vec<String>>,
+    image_cache: Option<bool>,
+    image_cache_max_mb: Option<u64>,
+    #[serde(default)]
+    privacy: drpc_core::PrivacySettings,
+    auth: Option<bool>,
+    bridge_origins: Option<Vec<String>>,
 }
 
 #[tokio::main]
@@ -34,9 +50,13 @@ async fn main() -> anyhow::Result<()> {
         .install_default()
         .expect("crypto provider already set");
     let cli = Cli::parse();
-    let file_cfg = load_config(cli.config.clone())?;
+    let config_path = cli.config.clone().unwrap_or_else(default_config_path);
+    let file_cfg = load_config(&config_path)?;
     init_tracing(&cli);
-    let bus = drpc_core::EventBus::new();
+    // Privacy lists changed at runtime are written back to the same file
+    let privacy =
+        drpc_core::PrivacyPolicy::new(file_cfg.privacy.clone())?.with_config_path(&config_path);
+    let bus = drpc_core::EventBus::with_privacy(privacy);
     // Maintain an in-memory registry of active socket activities for graceful shutdown
     let registry = drpc_core::ActivityRegistry::new();
     {
@@ -46,13 +66,19 @@ async fn main() -> anyhow::Result<()> {
             let mut rx = bus_sub.subscribe();
             while let Some(evt) = rx.recv().await {
                 match evt {
-                    drpc_core::EventKind::ActivityUpdate { socket_id, payload } => {
+                    drpc_core::EventKind::ActivityUpdate {
+                        socket_id, payload, ..
+                    } => {
                         registry_clone.set(socket_id, payload);
                     }
                     drpc_core::EventKind::Clear { socket_id } => {
                         registry_clone.clear(&socket_id);
                     }
-                    drpc_core::EventKind::PrivacyRefresh => {
+                    drpc_core::EventKind::PrivacyRefresh
+                    | drpc_core::EventKind::DetectablesUpdated { .. }
+                    | drpc_core::EventKind::ActivityJoin { .. }
+                    | drpc_core::EventKind::ActivitySpectate { .. }
+                    | drpc_core::EventKind::ActivityJoinRequest { .. } => {
                         // no-op in registry
                     }
                 }
@@ -78,34 +104,67 @@ async fn main() -> anyhow::Result<()> {
             Err(e) => tracing::error!(error=?e, "failed to start ipc server"),
         }
     }
-    #[cfg(feature = "bridge")]
-    {
-        let bridge_port = cli.bridge_port.or(file_cfg.bridge_port);
-        match drpc_bridge::Bridge::run(bus.clone(), bridge_port).await {
-            Ok(b) => tracing::info!(port = b.port(), "started bridge server"),
-            Err(e) => tracing::error!(error=?e, "failed to start bridge server"),
+    #[cfg(any(feature = "bridge", feature = "rest"))]
+    let auth = {
+        let mut auth = drpc_core::AuthConfig::default();
+        if let Some(origins) = file_cfg.bridge_origins.clone() {
+            auth.bridge_origins = origins;
         }
-    }
+        if cli.auth || file_cfg.auth == Some(true) {
+            let path = drpc_core::token_path()
+                .ok_or_else(|| anyhow::anyhow!("no home directory for the auth token"))?;
+            auth = auth.with_token(drpc_core::load_or_create_token(&path)?);
+            tracing::info!(path=%path.display(), "auth token required");
+        }
+        auth
+    };
+    // Activity art URLs from the scanner and the asset resolver point at the same CDN
+    #[cfg(any(feature = "bridge", feature = "rest", feature = "process-scanning"))]
+    let ready_config = drpc_core::ReadyConfig::default();
+    // Asset lists are shared so an application's list is fetched once for Bridge and REST
+    #[cfg(any(feature = "bridge", feature = "rest"))]
+    let assets = {
+        let mut resolver = drpc_core::AssetResolver::new(&ready_config);
+        if let Some(dir) = drpc_core::assets_dir() {
+            resolver = resolver.with_cache_dir(dir);
+        }
+        // Served by REST, so only useful with it
+        if cfg!(feature = "rest") && (cli.image_cache || file_cfg.image_cache == Some(true)) {
+            let mut opts = drpc_core::ImageCacheOptions::default();
+            if let Some(mb) = file_cfg.image_cache_max_mb {
+                opts.max_bytes = mb * 1024 * 1024;
+            }
+            match drpc_core::ImageCache::open(opts) {
+                Ok(cache) => resolver = resolver.with_image_cache(std::sync::Arc::new(cache)),
+                Err(e) => tracing::error!(error=?e, "failed to open image cache"),
+            }
+        }
+        std::sync::Arc::new(resolver)
+    };
+    // Loaded once and shared by the scanner and REST, so background refreshes reach both
+    #[cfg(any(feature = "process-scanning", feature = "rest"))]
+    let (detectables, detectables_opts) = {
+        let opts = detectables_options(&cli, &file_cfg, cli.refresh_detectables)?;
+        // On failure everyone still shares one empty set, which the refresher fills in later
+        let detectables = match drpc_core::load_detectables_with(&opts).await {
+            Ok(d) => d,
+            Err(e) => {
+                tracing::error!(error=?e, "failed to load detectables; continuing with empty set");
+                drpc_core::Detectables::default()
+            }
+        };
+        drpc_core::DetectablesRefresher::new(detectables.clone(), bus.clone(), opts.clone())
+            .spawn();
+        (detectables, opts)
+    };
     #[cfg(feature = "process-scanning")]
     {
         if !cli.no_process_scanning
             && std::env::var("DRPC_NO_PROCESS_SCANNING").ok().as_deref() != Some("1")
         {
-            let ttl = cli
-                .detectables_ttl
-                .or(file_cfg.detectables_ttl)
-                .unwrap_or(24);
-            let detectables = match drpc_core::load_detectables_async(cli.refresh_detectables, ttl)
-                .await
-            {
-                Ok(d) => d,
-                Err(e) => {
-                    tracing::error!(error=?e, "failed to load detectables; continuing with empty set");
-                    drpc_core::Detectables::default()
-                }
-            };
-            let backend = drpc_process::LinuxBackend; // placeholder
-            let scanner = drpc_process::Scanner::new(backend, detectables, bus.clone());
+            let backend = drpc_process::LinuxBackend::new();
+            let scanner = drpc_process::Scanner::new(backend, detectables.clone(), bus.clone())
+                .with_cdn_host(ready_config.cdn_host.clone());
             scanner.spawn();
             tracing::info!("process scanner started");
         } else {
@@ -114,33 +173,31 @@ async fn main() -> anyhow::Result<()> {
     }
     #[cfg(feature = "rest")]
     {
-        let rest_port = cli.rest_port;
-        let reg_clone = registry.clone();
-        let ttl = cli
-            .detectables_ttl
-            .or(file_cfg.detectables_ttl)
-            .unwrap_or(24);
-        // Load detectables (non-forced) for REST refresh endpoint even if scanning disabled
-        let rest_detectables = match drpc_core::load_detectables_async(false, ttl).await {
-            Ok(d) => Some(d),
-            Err(e) => {
-                tracing::warn!(error=?e, "rest detectables load failed; refresh endpoint limited");
-                None
-            }
+        let options = drpc_rest::RestOptions {
+            port: cli.rest_port,
+            detectables: Some(detectables),
+            detectables_options: detectables_opts,
+            assets: assets.clone(),
+            auth: auth.clone(),
         };
-        match drpc_rest::run_rest(
-            bus.clone(),
-            reg_clone.into(),
-            rest_detectables,
-            ttl,
-            rest_port,
-        )
-        .await
-        {
+        match drpc_rest::run_rest(bus.clone(), registry.clone().into(), options).await {
             Ok(p) => tracing::info!(port = p, "started rest server"),
             Err(e) => tracing::error!(error=?e, "failed to start rest server"),
         }
     }
+    // After REST so Bridge messages can point at its /assets route
+    #[cfg(feature = "bridge")]
+    {
+        let options = drpc_bridge::BridgeOptions {
+            port: cli.bridge_port.or(file_cfg.bridge_port),
+            assets: assets.clone(),
+            auth,
+        };
+        match drpc_bridge::Bridge::run(bus.clone(), options).await {
+            Ok(b) => tracing::info!(port = b.port(), "started bridge server"),
+            Err(e) => tracing::error!(error=?e, "failed to start bridge server"),
+        }
+    }
     tokio::signal::ctrl_c().await.expect("install ctrl+c");
     tracing::info!("shutdown signal received; broadcasting CLEAR to active sockets");
     for (socket_id, _activity) in registry.non_null() {
@@ -167,11 +224,37 @@ fn init_tracing(cli: &Cli) {
     }
 }
 
-fn load_config(path: Option<PathBuf>) -> anyhow::Result<FileConfig> {
-    let p = path.unwrap_or_else(|| {
-        let home = std::env::home_dir().unwrap_or_else(|| PathBuf::from("."));
-        home.join(".drpc").join("config.toml")
-    });
+#[cfg(any(feature = "process-scanning", feature = "rest"))]
+fn detectables_options(
+    cli: &Cli,
+    file_cfg: &FileConfig,
+    force_refresh: bool,
+) -> anyhow::Result<drpc_core::DetectablesOptions> {
+    let raw: &[String] = if cli.detectables_sources.is_empty() {
+        file_cfg.detectables_sources.as_deref().unwrap_or_default()
+    } else {
+        &cli.detectables_sources
+    };
+    let mut opts = drpc_core::DetectablesOptions {
+        force_refresh,
+        ttl_hours: cli
+            .detectables_ttl
+            .or(file_cfg.detectables_ttl)
+            .unwrap_or(24),
+        ..Default::default()
+    };
+    if !raw.is_empty() {
+        opts.sources = raw.iter().map(|s| s.parse()).collect::<Result<_, _>>()?;
+    }
+    Ok(opts)
+}
+
+fn default_config_path() -> PathBuf {
+    let home = std::env::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    home.join(".drpc").join("config.toml")
+}
+
+fn load_config(p: &std::path::Path) -> anyhow::Result<FileConfig> {
     if p.exists() {
         Ok(toml::from_str(&std::fs::read_to_string(p)?)?)
     } else {
@@ -11,9 +11,15 @@ async fn e2e_ws_activity_replay() {
     // Start WS server
     let ws_port = drpc_ws::run_ws_server(bus.clone()).await.expect("ws start");
     // Start Bridge
-    let bridge = drpc_bridge::Bridge::run(bus.clone(), Some(0u16))
-        .await
-        .expect("bridge start");
+    let bridge = drpc_bridge::Bridge::run(
+        bus.clone(),
+        drpc_bridge::BridgeOptions {
+            port: Some(0),
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("bridge start");
     let bridge_port = bridge.port();
 
     // Connect WS client & receive READY